@@ -0,0 +1,83 @@
+//! Latency of the most common operations on the file based registry with 50k bookmarks.
+//! Run with `cargo bench --bench file_storage`.
+
+use bookmark_lib::filters::FilterSet;
+use bookmark_lib::registry::URLRegistry;
+use bookmark_lib::types::{URLRecord, URLRegistry as Registry};
+use bookmark_lib::{Registry as _, RegistryReader};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::Path;
+
+const RECORDS: usize = 50_000;
+const GROUPS: usize = 100;
+
+fn create_registry_file(path: &Path) -> Vec<String> {
+    let records: Vec<URLRecord> = (0..RECORDS)
+        .map(|i| {
+            URLRecord::new(
+                &format!("https://example-{}.com/path/{}", i % 1000, i),
+                &format!("bookmark-{}", i),
+                &format!("group-{}", i % GROUPS),
+                vec![format!("tag-{}", i % 10), format!("topic-{}", i % 37)],
+            )
+        })
+        .collect();
+    let ids = records.iter().map(|r| r.id.clone()).collect();
+
+    let content = serde_json::to_string(&Registry::new(records)).expect("Failed to serialize");
+    fs::write(path, content).expect("Failed to write registry file");
+
+    ids
+}
+
+fn file_storage_benchmark(c: &mut Criterion) {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("urls.json");
+    let ids = create_registry_file(&path);
+    let path = path.to_str().expect("Invalid path").to_string();
+
+    let registry = URLRegistry::new_file_based(path.clone());
+
+    c.bench_function("list 50k", |b| {
+        b.iter(|| registry.list_urls(None, None).expect("Failed to list URLs"))
+    });
+
+    c.bench_function("list 50k without cache", |b| {
+        b.iter(|| {
+            URLRegistry::new_file_based(path.clone())
+                .list_urls(None, None)
+                .expect("Failed to list URLs")
+        })
+    });
+
+    c.bench_function("search 50k", |b| {
+        let filter = FilterSet::new_combined_for_phrase("bookmark-4999");
+        b.iter(|| {
+            registry
+                .list_urls(Some(black_box(&filter)), None)
+                .expect("Failed to search URLs")
+        })
+    });
+
+    // Alternates tagging and untagging so that the registry does not grow between iterations
+    c.bench_function("tag 50k", |b| {
+        let mut tagged = false;
+        b.iter(|| {
+            let id = &ids[RECORDS / 2];
+            if tagged {
+                registry.untag(id, "bench").expect("Failed to untag URL");
+            } else {
+                registry.tag(id, "bench").expect("Failed to tag URL");
+            }
+            tagged = !tagged;
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = file_storage_benchmark
+}
+criterion_main!(benches);
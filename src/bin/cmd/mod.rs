@@ -1,11 +1,19 @@
 pub const GROUP_SUB_CMD: &str = "group";
 pub const GROUP_LIST_CMD: &str = "list";
+pub const GROUP_MOVE_CMD: &str = "move";
+pub const GROUP_MOVE_CMD_ALIAS: &str = "rename";
 
 pub const ADD_SUB_CMD: &str = "add";
+pub const BUNDLE_SUB_CMD: &str = "bundle";
 pub const LIST_SUB_CMD: &str = "list";
 pub const DELETE_SUB_CMD: &str = "delete";
+pub const OPEN_SUB_CMD: &str = "open";
+pub const NOTES_SUB_CMD: &str = "notes";
 pub const TAG_SUB_CMD: &str = "tag";
 pub const UNTAG_SUB_CMD: &str = "untag";
+pub const PIN_SUB_CMD: &str = "pin";
+pub const UNPIN_SUB_CMD: &str = "unpin";
+pub const KEYWORD_SUB_CMD: &str = "keyword";
 pub const IMPORT_SUB_CMD: &str = "import";
 pub const CHANGE_GROUP_SUB_CMD: &str = "chgroup";
 pub const CHANGE_GROUP_SUB_CMD_ALIAS: &str = "chg";
@@ -14,3 +22,124 @@ pub const CHANGE_NAME_SUB_CMD_ALIAS: &str = "chname";
 pub const CHANGE_URL_SUB_CMD: &str = "chu";
 pub const CHANGE_URL_SUB_CMD_ALIAS: &str = "churl";
 pub const SORT_CMD: &str = "sort";
+pub const COLLAPSE_CMD: &str = "collapse";
+pub const EXPAND_CMD: &str = "expand";
+
+pub const BACKUP_SUB_CMD: &str = "backup";
+pub const BACKUP_LIST_CMD: &str = "list";
+pub const BACKUP_RESTORE_CMD: &str = "restore";
+
+pub const UNDO_SUB_CMD: &str = "undo";
+pub const REDO_SUB_CMD: &str = "redo";
+
+pub const PROFILE_SUB_CMD: &str = "profile";
+pub const PROFILE_LIST_CMD: &str = "list";
+pub const PROFILE_CREATE_CMD: &str = "create";
+pub const PROFILE_DELETE_CMD: &str = "delete";
+pub const PROFILE_USE_CMD: &str = "use";
+
+pub const MERGE_SUB_CMD: &str = "merge";
+
+pub const DOCTOR_SUB_CMD: &str = "doctor";
+
+pub const SYNC_SUB_CMD: &str = "sync";
+pub const SYNC_INIT_CMD: &str = "init";
+
+pub const TRASH_SUB_CMD: &str = "trash";
+pub const TRASH_LIST_CMD: &str = "list";
+pub const TRASH_RESTORE_CMD: &str = "restore";
+pub const TRASH_PURGE_CMD: &str = "purge";
+
+/// Parses duration in the format `<NUMBER><UNIT>`, where unit is one of: m, h, d, w
+pub fn parse_duration(val: &str) -> Result<chrono::Duration, String> {
+    let val = val.trim();
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected number followed by one of: m, h, d, w (e.g. 30d)",
+            val
+        )
+    };
+
+    let unit_index = val.len().saturating_sub(1);
+    if !val.is_char_boundary(unit_index) {
+        return Err(invalid());
+    }
+    let (number, unit) = val.split_at(unit_index);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    if number < 0 {
+        return Err(invalid());
+    }
+
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(number)),
+        "h" => Ok(chrono::Duration::hours(number)),
+        "d" => Ok(chrono::Duration::days(number)),
+        "w" => Ok(chrono::Duration::weeks(number)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cmd;
+    use crate::cmd::parse_duration;
+    use bookmark_lib::types::RESERVED_KEYWORDS;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_duration("12h"), Ok(chrono::Duration::hours(12)));
+        assert_eq!(parse_duration("2w"), Ok(chrono::Duration::weeks(2)));
+        assert_eq!(parse_duration("15m"), Ok(chrono::Duration::minutes(15)));
+
+        println!("Should fail for invalid durations...");
+        for invalid in ["", "d", "30", "-1d", "30x", "1.5d", "3ść"] {
+            assert!(
+                parse_duration(invalid).is_err(),
+                "{} should be invalid",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_subcommands_are_reserved_keywords() {
+        let subcommands = [
+            cmd::GROUP_SUB_CMD,
+            cmd::ADD_SUB_CMD,
+            cmd::BUNDLE_SUB_CMD,
+            cmd::LIST_SUB_CMD,
+            "ls",
+            cmd::DELETE_SUB_CMD,
+            cmd::OPEN_SUB_CMD,
+            cmd::NOTES_SUB_CMD,
+            cmd::TAG_SUB_CMD,
+            cmd::UNTAG_SUB_CMD,
+            cmd::PIN_SUB_CMD,
+            cmd::UNPIN_SUB_CMD,
+            cmd::KEYWORD_SUB_CMD,
+            cmd::IMPORT_SUB_CMD,
+            cmd::CHANGE_GROUP_SUB_CMD,
+            cmd::CHANGE_GROUP_SUB_CMD_ALIAS,
+            cmd::CHANGE_NAME_SUB_CMD,
+            cmd::CHANGE_NAME_SUB_CMD_ALIAS,
+            cmd::CHANGE_URL_SUB_CMD,
+            cmd::CHANGE_URL_SUB_CMD_ALIAS,
+            cmd::BACKUP_SUB_CMD,
+            cmd::UNDO_SUB_CMD,
+            cmd::REDO_SUB_CMD,
+            cmd::PROFILE_SUB_CMD,
+            cmd::MERGE_SUB_CMD,
+            cmd::DOCTOR_SUB_CMD,
+            cmd::SYNC_SUB_CMD,
+            cmd::TRASH_SUB_CMD,
+        ];
+        for name in subcommands {
+            assert!(
+                RESERVED_KEYWORDS.contains(&name),
+                "{} should be reserved",
+                name
+            );
+        }
+    }
+}
@@ -1,43 +1,327 @@
-use bookmark_lib::types::URLRecord;
+use bookmark_lib::backup::Snapshot;
+use bookmark_lib::diff::Change;
+use bookmark_lib::doctor::Problem;
+use bookmark_lib::groups::GroupNode;
+use bookmark_lib::merge::{Conflict, ConflictKind};
+use bookmark_lib::types::{TrashedRecord, URLRecord};
+use chrono::{DateTime, Local, Utc};
 
-pub(crate) fn display_urls(urls: Vec<URLRecord>) {
-    println!("{}", display_str(urls))
+pub(crate) fn display_urls(urls: Vec<URLRecord>, show_dates: bool, show_keywords: bool) {
+    println!("{}", display_str(urls, show_dates, show_keywords))
 }
 
-fn display_str(urls: Vec<URLRecord>) -> String {
-    let (name_len, url_len, group_len, tags_len) = get_max_lengths(&urls);
+const TIME_LEN: usize = 16;
+
+fn display_str(urls: Vec<URLRecord>, show_dates: bool, show_keywords: bool) -> String {
+    let (name_len, url_len, group_len, mut tags_len) = get_max_lengths(&urls);
+    if show_dates {
+        // Tags are no longer the last column, so they need to be aligned with the header
+        tags_len = tags_len.max("Tags".len());
+    }
     let id_len = if !urls.is_empty() {
         urls[0].id.len() // Ids have uniform length
     } else {
         0
     };
 
-    let mut out = header(id_len, name_len, url_len, group_len, tags_len);
+    let keyword_len = if show_keywords {
+        let max_len = urls.iter().map(|u| u.keyword.len()).max().unwrap_or(0);
+        Some(max_len.max("Keyword".len()))
+    } else {
+        None
+    };
+
+    let mut out = header(id_len, name_len, keyword_len, url_len, group_len, tags_len);
+    if show_dates {
+        out.push_str(&format!(
+            "   {}   {}   Opened",
+            pad("Created".to_string(), TIME_LEN),
+            pad("Updated".to_string(), TIME_LEN)
+        ));
+    }
     out.push('\n');
 
     for u in urls {
+        let keyword = match keyword_len {
+            Some(len) => format!("   {}", pad(u.keyword.clone(), len)),
+            None => "".to_string(),
+        };
         out.push_str(&format!(
-            "\n{}   {}   {}   {}   {}",
+            "\n{}   {}{}   {}   {}   {}",
             pad(u.id.clone(), id_len),
             pad(u.name.clone(), name_len),
-            pad(u.url.clone(), url_len),
+            keyword,
+            pad(url_str(&u), url_len),
             pad(u.group.clone(), group_len),
             pad(u.tags_as_string(), tags_len)
+        ));
+        if show_dates {
+            out.push_str(&format!(
+                "   {}   {}   {}",
+                pad(time_str(u.created_at.as_ref(), "-"), TIME_LEN),
+                pad(time_str(u.updated_at.as_ref(), "-"), TIME_LEN),
+                time_str(u.last_opened_at.as_ref(), "never")
+            ));
+        }
+    }
+
+    out
+}
+
+/// Returns the URL of the bookmark, or the number of URLs for bundles, which have none
+pub(crate) fn url_str(record: &URLRecord) -> String {
+    if record.is_bundle() {
+        format!("bundle of {} URLs", record.bundle.len())
+    } else {
+        record.url.clone()
+    }
+}
+
+/// Formats the time of the record in the local timezone, or returns `missing` if it is not known
+pub(crate) fn time_str(time: Option<&DateTime<Utc>>, missing: &str) -> String {
+    match time {
+        Some(t) => t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+        None => missing.to_string(),
+    }
+}
+
+pub(crate) fn display_changes(changes: &[Change]) {
+    for c in changes {
+        println!("{}", change_str(c))
+    }
+}
+
+fn change_str(change: &Change) -> String {
+    match change {
+        Change::Added(r) => format!("+ {}", record_str(r)),
+        Change::Removed(r) => format!("- {}", record_str(r)),
+        Change::Modified { before, after } => {
+            let mut fields = vec![];
+            if before.name != after.name {
+                fields.push(format!("name: '{}' -> '{}'", before.name, after.name));
+            }
+            if before.url != after.url {
+                fields.push(format!("url: '{}' -> '{}'", before.url, after.url));
+            }
+            if before.group != after.group {
+                fields.push(format!("group: '{}' -> '{}'", before.group, after.group));
+            }
+            if before.tags != after.tags {
+                fields.push(format!(
+                    "tags: [{}] -> [{}]",
+                    before.tags_as_string(),
+                    after.tags_as_string()
+                ));
+            }
+            if before.keyword != after.keyword {
+                fields.push(format!(
+                    "keyword: '{}' -> '{}'",
+                    before.keyword, after.keyword
+                ));
+            }
+            if before.notes != after.notes {
+                fields.push("notes changed".to_string());
+            }
+            if before.pinned != after.pinned {
+                let pinned = if after.pinned { "pinned" } else { "unpinned" };
+                fields.push(pinned.to_string());
+            }
+            if before.last_opened_at != after.last_opened_at {
+                fields.push(format!(
+                    "opened: {} -> {}",
+                    time_str(before.last_opened_at.as_ref(), "never"),
+                    time_str(after.last_opened_at.as_ref(), "never")
+                ));
+            }
+            format!("~ {} '{}': {}", after.id, before.name, fields.join(", "))
+        }
+    }
+}
+
+fn record_str(record: &URLRecord) -> String {
+    format!(
+        "{} '{}': '{}' in '{}' group",
+        record.id,
+        record.name,
+        url_str(record),
+        record.group
+    )
+}
+
+/// Displays both versions of records changed differently on both sides
+pub(crate) fn display_conflicts(conflicts: &[Conflict]) {
+    for c in conflicts {
+        println!("{}", conflict_str(c))
+    }
+}
+
+fn conflict_str(conflict: &Conflict) -> String {
+    let version = |record: &Option<URLRecord>| match record {
+        Some(r) => format!(
+            "'{}': '{}' in '{}' group, tags: [{}]",
+            r.name,
+            r.url,
+            r.group,
+            r.tags_as_string()
+        ),
+        None => "deleted".to_string(),
+    };
+    let reason = match &conflict.kind {
+        ConflictKind::Fields { fields } => format!("both sides changed {}", fields.join(", ")),
+        ConflictKind::Deleted => "deleted on one side and changed on the other".to_string(),
+        ConflictKind::Duplicate => "name already used in the group".to_string(),
+        ConflictKind::DuplicateKeyword { keyword } => format!("keyword '{}' already used", keyword),
+    };
+
+    format!(
+        "! {}: {}\n    ours:   {}\n    theirs: {}",
+        conflict.id,
+        reason,
+        version(&conflict.ours),
+        version(&conflict.theirs)
+    )
+}
+
+/// Displays problems found in bookmarks together with fixes which are or would be applied
+pub(crate) fn display_problems(problems: &[Problem], fixed: bool) {
+    for p in problems {
+        println!("{}", problem_str(p, fixed))
+    }
+}
+
+fn problem_str(problem: &Problem, fixed: bool) -> String {
+    let fix = match (&problem.fix, fixed) {
+        (Some(fix), true) => format!("fixed: {}", fix),
+        (Some(fix), false) => format!("fix: {}", fix),
+        (None, _) => "has to be fixed by hand".to_string(),
+    };
+
+    format!("! {}: {} ({})", problem.id, problem.kind, fix)
+}
+
+/// Displays snapshots together with the number of bookmarks and changes relative to the current state
+pub(crate) fn display_snapshots(snapshots: Vec<(Snapshot, usize, Vec<Change>)>) {
+    let mut out = format!(
+        "{}   {}   {}   {}\n",
+        pad("Snapshot".to_string(), 19),
+        pad("Created".to_string(), 19),
+        "Bookmarks",
+        "Changes"
+    );
+
+    for (snapshot, count, changes) in snapshots {
+        out.push_str(&format!(
+            "\n{}   {}   {}   {}",
+            pad(snapshot.id, 19),
+            snapshot
+                .created_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            pad(count.to_string(), 9),
+            changes_summary(&changes)
+        ))
+    }
+
+    println!("{}", out)
+}
+
+fn changes_summary(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return "none".to_string();
+    }
+
+    let count = |f: fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+    format!(
+        "+{} -{} ~{}",
+        count(|c| matches!(c, Change::Added(_))),
+        count(|c| matches!(c, Change::Removed(_))),
+        count(|c| matches!(c, Change::Modified { .. }))
+    )
+}
+
+/// Displays trashed bookmarks together with the local time of deletion
+pub(crate) fn display_trash(trash: Vec<TrashedRecord>) {
+    println!("{}", trash_str(trash))
+}
+
+fn trash_str(trash: Vec<TrashedRecord>) -> String {
+    let records: Vec<URLRecord> = trash.iter().map(|t| t.record.clone()).collect();
+    let (name_len, url_len, group_len, _) = get_max_lengths(&records);
+    let id_len = if !records.is_empty() {
+        records[0].id.len()
+    } else {
+        2
+    };
+
+    let mut out = format!(
+        "{}   {}   {}   {}   Deleted\n",
+        pad("Id".to_string(), id_len),
+        pad("Name".to_string(), name_len),
+        pad("URL".to_string(), url_len),
+        pad("Group".to_string(), group_len),
+    );
+
+    for t in trash {
+        let url = url_str(&t.record);
+        out.push_str(&format!(
+            "\n{}   {}   {}   {}   {}",
+            pad(t.record.id, id_len),
+            pad(t.record.name, name_len),
+            pad(url, url_len),
+            pad(t.record.group, group_len),
+            t.deleted_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
         ))
     }
 
     out
 }
 
+pub(crate) fn display_group_tree(tree: &[GroupNode]) {
+    for line in group_tree_lines(tree) {
+        println!("{}", line)
+    }
+}
+
+/// Draws nested groups with the number of bookmarks in each of them, including nested ones
+fn group_tree_lines(tree: &[GroupNode]) -> Vec<String> {
+    let mut lines = vec![];
+    for node in tree {
+        lines.push(format!("{} ({})", node.name, node.count));
+        push_nested_groups(&node.children, "", &mut lines);
+    }
+    lines
+}
+
+fn push_nested_groups(nodes: &[GroupNode], prefix: &str, lines: &mut Vec<String>) {
+    for (i, node) in nodes.iter().enumerate() {
+        let (branch, indent) = if i == nodes.len() - 1 {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!(
+            "{}{}{} ({})",
+            prefix, branch, node.name, node.count
+        ));
+        push_nested_groups(&node.children, &format!("{}{}", prefix, indent), lines);
+    }
+}
+
 fn header(
     id_len: usize,
     name_len: usize,
+    keyword_len: Option<usize>,
     url_len: usize,
     group_len: usize,
     tags_len: usize,
 ) -> String {
     let id = pad("Id".to_string(), id_len);
-    let name = pad("Name".to_string(), name_len);
+    let mut name = pad("Name".to_string(), name_len);
+    if let Some(len) = keyword_len {
+        name.push_str(&format!("   {}", pad("Keyword".to_string(), len)));
+    }
     let url = pad("URL".to_string(), url_len);
     let group = pad("Group".to_string(), group_len);
     let tags = pad("Tags".to_string(), tags_len);
@@ -64,8 +348,9 @@ fn get_max_lengths(urls: &[URLRecord]) -> (usize, usize, usize, usize) {
         if u.name.len() > max_len[0] {
             max_len[0] = u.name.len()
         }
-        if u.url.len() > max_len[1] {
-            max_len[1] = u.url.len()
+        let url_len = url_str(u).len();
+        if url_len > max_len[1] {
+            max_len[1] = url_len
         }
         if u.group.len() > max_len[2] {
             max_len[2] = u.group.len()
@@ -81,8 +366,16 @@ fn get_max_lengths(urls: &[URLRecord]) -> (usize, usize, usize, usize) {
 
 #[cfg(test)]
 mod test {
-    use crate::display::display_str;
-    use bookmark_lib::types::URLRecord;
+    use crate::display::{
+        change_str, changes_summary, conflict_str, display_str, group_tree_lines, problem_str,
+        trash_str, url_str,
+    };
+    use bookmark_lib::diff::Change;
+    use bookmark_lib::doctor::{Problem, ProblemKind};
+    use bookmark_lib::groups::group_tree;
+    use bookmark_lib::merge::{Conflict, ConflictKind};
+    use bookmark_lib::types::{BundleMember, TrashedRecord, URLRecord};
+    use chrono::{Local, TimeZone, Utc};
 
     struct TestCase {
         description: String,
@@ -157,12 +450,252 @@ mod test {
 
         for test in test_cases {
             println!("Test: {}", test.description);
-            let display = display_str(test.records);
+            let display = display_str(test.records, false, false);
 
             let lines: Vec<&str> = display.split("\n").collect();
-            for i in 0..lines.len() {
-                assert_eq!(lines[i], test.expected_lines[i])
+            for (i, line) in lines.iter().enumerate() {
+                assert_eq!(*line, test.expected_lines[i])
             }
         }
     }
+
+    #[test]
+    fn test_display_str_with_dates() {
+        let mut record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let created_at = Utc.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+        record.created_at = Some(created_at);
+        record.updated_at = Some(created_at);
+        let local = created_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+
+        let display = display_str(vec![record.clone()], true, false);
+
+        let lines: Vec<&str> = display.split("\n").collect();
+        assert_eq!(
+            lines[0],
+            "Id                 Name   URL               Group     Tags   Created            Updated            Opened"
+        );
+        assert_eq!(
+            lines[2],
+            format!(
+                "{}   one    https://one.com   default   tag    {}   {}   never",
+                record.id, local, local
+            )
+        );
+    }
+
+    #[test]
+    fn test_display_str_with_keywords() {
+        let mut record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        record.keyword = "o".to_string();
+        let other = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+
+        let display = display_str(vec![record.clone(), other.clone()], false, true);
+
+        let lines: Vec<&str> = display.split("\n").collect();
+        assert_eq!(
+            lines[0],
+            "Id                 Name   Keyword   URL               Group     Tags"
+        );
+        assert_eq!(
+            lines[2],
+            format!(
+                "{}   one    o         https://one.com   default   tag",
+                record.id
+            )
+        );
+        assert_eq!(
+            lines[3],
+            format!(
+                "{}   two              https://two.com   default      ",
+                other.id
+            )
+        );
+    }
+
+    #[test]
+    fn test_change_str() {
+        let record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let mut modified = record.clone();
+        modified.group = "dev".to_string();
+        modified.tags.clear();
+        modified.keyword = "o".to_string();
+        modified.pinned = true;
+
+        let changes = vec![
+            Change::Added(record.clone()),
+            Change::Removed(record.clone()),
+            Change::Modified {
+                before: record.clone(),
+                after: modified,
+            },
+        ];
+
+        assert_eq!(
+            change_str(&changes[0]),
+            format!(
+                "+ {} 'one': 'https://one.com' in 'default' group",
+                record.id
+            )
+        );
+        assert_eq!(
+            change_str(&changes[1]),
+            format!(
+                "- {} 'one': 'https://one.com' in 'default' group",
+                record.id
+            )
+        );
+        assert_eq!(
+            change_str(&changes[2]),
+            format!(
+                "~ {} 'one': group: 'default' -> 'dev', tags: [tag] -> [], keyword: '' -> 'o', pinned",
+                record.id
+            )
+        );
+        assert_eq!(changes_summary(&changes), "+1 -1 ~1");
+        assert_eq!(changes_summary(&[]), "none");
+    }
+
+    #[test]
+    fn test_trash_str() {
+        let record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let deleted_at = Utc.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+        let local = deleted_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+
+        let display = trash_str(vec![TrashedRecord::new(record.clone(), deleted_at)]);
+        let lines: Vec<&str> = display.split('\n').collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Id                 Name   URL               Group     Deleted".to_string(),
+                "".to_string(),
+                format!(
+                    "{}   one    https://one.com   default   {}",
+                    record.id, local
+                ),
+            ]
+        );
+
+        println!("Should display only header for empty trash...");
+        assert_eq!(trash_str(vec![]), "Id   Name   URL   Group   Deleted\n");
+    }
+
+    #[test]
+    fn test_url_str() {
+        let record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        assert_eq!(url_str(&record), "https://one.com");
+
+        println!("Should display number of URLs of the bundle...");
+        let bundle = URLRecord::new_bundle(
+            "daily",
+            "default",
+            Vec::<String>::new(),
+            vec![
+                BundleMember::Url("https://one.com".to_string()),
+                BundleMember::Bookmark(record.id),
+            ],
+            Utc::now(),
+        );
+        assert_eq!(url_str(&bundle), "bundle of 2 URLs");
+    }
+
+    #[test]
+    fn test_group_tree_lines() {
+        let records: Vec<URLRecord> = [
+            "work/infra/grafana",
+            "work/infra/logs",
+            "work/docs",
+            "work",
+            "home",
+        ]
+        .iter()
+        .map(|g| URLRecord::new("https://one.com", "one", g, Vec::<String>::new()))
+        .collect();
+
+        let lines = group_tree_lines(&group_tree(&records));
+        assert_eq!(
+            lines,
+            vec![
+                "home (1)",
+                "work (4)",
+                "├── docs (1)",
+                "└── infra (2)",
+                "    ├── grafana (1)",
+                "    └── logs (1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflict_str() {
+        let record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let mut changed = record.clone();
+        changed.url = "https://other.com".to_string();
+
+        let conflict = Conflict {
+            id: record.id.clone(),
+            kind: ConflictKind::Fields {
+                fields: vec!["url".to_string()],
+            },
+            ours: Some(record.clone()),
+            theirs: Some(changed),
+        };
+        assert_eq!(
+            conflict_str(&conflict),
+            format!(
+                "! {}: both sides changed url\n    ours:   'one': 'https://one.com' in 'default' group, tags: [tag]\n    theirs: 'one': 'https://other.com' in 'default' group, tags: [tag]",
+                record.id
+            )
+        );
+
+        let conflict = Conflict {
+            id: record.id.clone(),
+            kind: ConflictKind::Deleted,
+            ours: None,
+            theirs: Some(record.clone()),
+        };
+        assert_eq!(
+            conflict_str(&conflict),
+            format!(
+                "! {}: deleted on one side and changed on the other\n    ours:   deleted\n    theirs: 'one': 'https://one.com' in 'default' group, tags: [tag]",
+                record.id
+            )
+        );
+    }
+
+    #[test]
+    fn test_problem_str() {
+        let problem = Problem {
+            id: "1234".to_string(),
+            kind: ProblemKind::MalformedTag {
+                tag: " ".to_string(),
+            },
+            fix: Some("remove tag".to_string()),
+        };
+        assert_eq!(
+            problem_str(&problem, false),
+            "! 1234: tag ' ' is malformed (fix: remove tag)"
+        );
+        assert_eq!(
+            problem_str(&problem, true),
+            "! 1234: tag ' ' is malformed (fixed: remove tag)"
+        );
+
+        let problem = Problem {
+            id: "1234".to_string(),
+            kind: ProblemKind::MalformedUrl {
+                url: "not a url".to_string(),
+            },
+            fix: None,
+        };
+        assert_eq!(
+            problem_str(&problem, true),
+            "! 1234: URL 'not a url' is malformed (has to be fixed by hand)"
+        );
+    }
 }
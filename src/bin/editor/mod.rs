@@ -0,0 +1,67 @@
+use std::env;
+use std::fs;
+use std::process;
+
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Opens the text in the editor from `$VISUAL` or `$EDITOR` and returns the edited text
+/// without trailing whitespaces, which editors usually add
+pub fn edit_text(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+
+    edit_with(&editor, text)
+}
+
+/// Runs the editor command, which can contain arguments (e.g. `code --wait`),
+/// on the temporary file with the text
+fn edit_with(editor: &str, text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("editor command is empty")?;
+
+    let path = env::temp_dir().join(format!(
+        "bookmark-notes-{}-{:08x}.txt",
+        process::id(),
+        rand::random::<u32>()
+    ));
+    fs::write(&path, text)?;
+
+    let status = process::Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+
+    let status = status.map_err(|err| format!("failed to run editor '{}': {}", editor, err))?;
+    if !status.success() {
+        return Err(From::from(format!(
+            "editor '{}' exited with {}",
+            editor, status
+        )));
+    }
+
+    Ok(edited?.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::editor::edit_with;
+
+    #[test]
+    fn test_edit_with() {
+        println!("Should return edited text...");
+        let edited =
+            edit_with("sed -i s/staging/prod/", "Use staging creds\n").expect("Failed to edit");
+        assert_eq!(edited, "Use prod creds");
+
+        println!("Should keep multi-line text...");
+        let edited = edit_with("true", "first\nsecond\n\n").expect("Failed to edit");
+        assert_eq!(edited, "first\nsecond");
+
+        println!("Should fail if editor fails...");
+        assert!(edit_with("false", "text").is_err());
+        assert!(edit_with("not-existing-editor-command", "text").is_err());
+    }
+}
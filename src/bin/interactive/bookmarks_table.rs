@@ -1,15 +1,20 @@
 use crate::interactive::event::Event;
 use crate::interactive::event::Signal;
+use crate::interactive::profile_switcher::ProfileSwitcher;
 use crate::interactive::table::{StatefulTable, TableItem};
 use crate::interactive::url_table_item::{default_columns, Columns, URLItem};
+use bookmark_lib::error::Error;
 use bookmark_lib::filters::{Filter, UnorderedWordSetFilter};
-use bookmark_lib::types::URLRecord;
+use bookmark_lib::groups;
+use bookmark_lib::template;
+use bookmark_lib::types::{TrashedRecord, URLRecord};
 use bookmark_lib::Registry;
 use std::sync::mpsc;
 use termion::event::Key;
 
 use crate::cmd;
 use bookmark_lib::sort::{SortBy, SortConfig};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::str::FromStr;
 
 type CommandResult = Result<(), Box<dyn std::error::Error>>;
@@ -21,6 +26,9 @@ pub struct BookmarksTable {
     columns: Vec<String>,
     filter: Option<Box<dyn Filter>>,
     sort_cfg: Option<SortConfig>,
+    profiles: Option<ProfileSwitcher>,
+    /// Paths of groups shown as single rows
+    collapsed: BTreeSet<String>,
 }
 
 impl BookmarksTable {
@@ -44,6 +52,11 @@ impl BookmarksTable {
         &self.columns
     }
 
+    /// Returns name of the current profile, if switching profiles is enabled
+    pub fn profile(&self) -> Option<&str> {
+        self.profiles.as_ref().map(|p| p.current())
+    }
+
     pub fn get_selected(&self) -> Result<Option<URLRecord>, Box<dyn std::error::Error>> {
         let selected_id = self.get_selected_id();
         if selected_id.is_none() {
@@ -55,19 +68,141 @@ impl BookmarksTable {
         Ok(url_record)
     }
 
-    pub fn open(&self) -> Result<(), Box<dyn std::error::Error>> {
-        match self.table.state.selected() {
-            Some(id) => match open::that(self.table.items[id].url().as_str()) {
-                Ok(_) => Ok(()),
-                Err(err) => Err(From::from(format!(
+    pub fn open(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.open_with(&BTreeMap::new())
+    }
+
+    /// Opens the selected bookmark, filling placeholders of the URL template with the values.
+    /// Fails if any placeholder does not have the value.
+    pub fn open_with(
+        &mut self,
+        values: &BTreeMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let item = match self.table.state.selected() {
+            Some(index) => &self.table.items[index],
+            None => return Ok(()),
+        };
+        if let Some(group) = item.collapsed_group() {
+            let group = group.to_string();
+            return self.expand_group(Some(&group));
+        }
+
+        let id = item.id();
+        let record = match self.registry.get_url(&id)? {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+        let urls = self
+            .urls_to_open(&record)?
+            .iter()
+            .map(|url| template::fill(url, values))
+            .collect::<Result<Vec<String>, _>>()?;
+        for url in urls {
+            if let Err(err) = open::that(url.as_str()) {
+                return Err(From::from(format!(
                     "failed to open URL in the browser: {}",
-                    err.to_string()
-                ))),
-            },
-            None => Ok(()),
+                    err
+                )));
+            }
+        }
+        self.registry.mark_opened(&id)?;
+
+        // Opening changes the frecency, so keep the bookmark selected if it moves
+        self.refresh_items()?;
+        let index = self.table.items.iter().position(|i| i.id() == id);
+        self.table.state.select(index);
+        Ok(())
+    }
+
+    /// Checks if URL of the selected bookmark has placeholders to fill before opening it
+    pub fn selected_is_template(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        match self.get_selected()? {
+            Some(record) => Ok(self
+                .urls_to_open(&record)?
+                .iter()
+                .any(|url| template::is_template(url))),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns URLs opened with the bookmark, i.e. all members of the bundle
+    pub fn urls_to_open(
+        &self,
+        record: &URLRecord,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.registry.urls_to_open(record)?)
+    }
+
+    /// Pins the selected bookmark or unpins it if it is already pinned
+    pub fn toggle_pin(&mut self) -> CommandResult {
+        let record = match self.get_selected()? {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+
+        if record.pinned {
+            self.registry.unpin(&record.id)?;
+        } else {
+            self.registry.pin(&record.id)?;
+        }
+
+        // Pinning moves the bookmark to the top, so keep it selected
+        self.refresh_items()?;
+        self.select_where(|item| item.id() == record.id);
+        Ok(())
+    }
+
+    /// Collapses group of the selected bookmark, or expands the selected collapsed group
+    pub fn toggle_group(&mut self) -> CommandResult {
+        let item = match self.table.state.selected() {
+            Some(index) => &self.table.items[index],
+            None => return Ok(()),
+        };
+
+        match item.collapsed_group() {
+            Some(group) => {
+                let group = group.to_string();
+                self.expand_group(Some(&group))
+            }
+            None => {
+                let group = item.group().to_string();
+                self.collapse_group(&group)
+            }
         }
     }
 
+    /// Shows all bookmarks of the group and groups nested in it as a single row
+    pub fn collapse_group(&mut self, group: &str) -> CommandResult {
+        let group = groups::normalize(group);
+        if group.is_empty() {
+            return Err(From::from("error: group not specified"));
+        }
+        // Groups nested in the collapsed one are hidden anyway
+        self.collapsed.retain(|g| !groups::is_in_subtree(g, &group));
+        self.collapsed.insert(group.clone());
+
+        self.refresh_items()?;
+        self.select_where(|item| item.collapsed_group() == Some(group.as_str()));
+        Ok(())
+    }
+
+    /// Expands the group and groups nested in it, or all groups if not specified
+    pub fn expand_group(&mut self, group: Option<&str>) -> CommandResult {
+        let group = group.map(groups::normalize);
+        match &group {
+            Some(group) => self
+                .collapsed
+                .retain(|g| !groups::is_in_subtree(g, group) && !groups::is_in_subtree(group, g)),
+            None => self.collapsed.clear(),
+        }
+
+        self.refresh_items()?;
+        if let Some(group) = group {
+            self.select_where(|item| groups::is_in_subtree(item.group(), &group));
+        }
+        Ok(())
+    }
+
     pub fn search(&mut self, phrase: &str) -> Result<(), Box<dyn std::error::Error>> {
         self.filter = Some(Box::new(UnorderedWordSetFilter::new(phrase)));
         self.refresh_items()
@@ -97,6 +232,9 @@ impl BookmarksTable {
             }
             cmd::CHANGE_URL_SUB_CMD | cmd::CHANGE_URL_SUB_CMD_ALIAS => self.change_url(id, args)?,
             cmd::SORT_CMD => self.sort_urls(id, args)?,
+            cmd::PROFILE_SUB_CMD => self.switch_profile(id, args)?,
+            cmd::COLLAPSE_CMD => self.collapse(id, args)?,
+            cmd::EXPAND_CMD => self.expand_group(args.first().copied())?,
             "q" | "quit" => self.signal_sender.send(Event::Signal(Signal::Quit))?,
             _ => return Err(From::from(format!("error: command {} not found", command))),
         };
@@ -175,7 +313,7 @@ impl BookmarksTable {
         let sort_cfg = if args.is_empty() {
             SortConfig::new_by(SortBy::Name)
         } else {
-            let sort_by = SortBy::from_str(&args[0])?;
+            let sort_by = SortBy::from_str(args[0])?;
             SortConfig::new_by(sort_by)
         };
         self.sort_cfg = Some(sort_cfg);
@@ -183,6 +321,40 @@ impl BookmarksTable {
         self.refresh_items()
     }
 
+    pub fn collapse(&mut self, id: Option<String>, args: Vec<&str>) -> CommandResult {
+        if let Some(group) = args.first() {
+            return self.collapse_group(group);
+        }
+
+        let id = unwrap_id(id)?;
+        match self.registry.get_url(&id)? {
+            Some(record) => self.collapse_group(&record.group),
+            None => Err(From::from(format!("error: bookmark {} not found", id))),
+        }
+    }
+
+    pub fn switch_profile(&mut self, _: Option<String>, args: Vec<&str>) -> CommandResult {
+        let profiles = match &mut self.profiles {
+            Some(profiles) => profiles,
+            None => {
+                return Err(From::from(
+                    "error: profiles cannot be switched when registry file is specified",
+                ))
+            }
+        };
+
+        if args.is_empty() {
+            return Err(From::from(format!(
+                "profile requires exactly one argument. Usage: profile [NAME]. Available profiles: {}",
+                profiles.list()?.join(", ")
+            )));
+        }
+
+        self.registry = profiles.switch(args[0])?;
+        self.table.unselect();
+        self.refresh_items()
+    }
+
     pub fn delete(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
         match self.get_selected_id() {
             Some(id) => {
@@ -196,6 +368,29 @@ impl BookmarksTable {
         }
     }
 
+    pub fn list_trash(&self) -> Result<Vec<TrashedRecord>, Box<dyn std::error::Error>> {
+        Ok(self.registry.list_trash()?)
+    }
+
+    pub fn restore(&mut self, id: &str) -> Result<URLRecord, Box<dyn std::error::Error>> {
+        let record = self.registry.restore(id)?;
+        self.refresh_items()?;
+        Ok(record)
+    }
+
+    /// Reverts the last operation. Returns false if there was nothing to undo or
+    /// the bookmark was changed in the meantime.
+    pub fn undo(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        let undone = match self.registry.undo() {
+            Ok(entry) => entry.is_some(),
+            Err(Error::Conflict(_)) => false,
+            Err(err) => return Err(From::from(err)),
+        };
+
+        self.refresh_items()?;
+        Ok(undone)
+    }
+
     fn refresh_items(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let urls = match &self.filter {
             Some(f) => self.registry.list_urls(Some(f.as_ref()), self.sort_cfg)?,
@@ -203,15 +398,23 @@ impl BookmarksTable {
         };
 
         self.table
-            .override_items(URLItem::from_vec(urls, Some(&self.columns)));
+            .override_items(fold_collapsed(urls, &self.collapsed, &self.columns));
         Ok(())
     }
 
+    fn select_where<P: Fn(&URLItem) -> bool>(&mut self, predicate: P) {
+        if let Some(index) = self.table.items.iter().position(predicate) {
+            self.table.state.select(Some(index));
+        }
+    }
+
+    /// Returns id of the selected bookmark, or None if nothing or a collapsed group is selected
     fn get_selected_id(&self) -> Option<String> {
         self.table
             .state
             .selected()
             .map(|index| self.table.items[index].id())
+            .filter(|id| !id.is_empty())
     }
 }
 
@@ -221,9 +424,11 @@ impl BookmarksTable {
         registry: Box<dyn Registry>,
     ) -> Result<BookmarksTable, Box<dyn std::error::Error>> {
         let default_columns = default_columns();
+        // Most used bookmarks are shown first, until other order is chosen
+        let sort_cfg = Some(SortConfig::new_by(SortBy::Frecency));
 
         let items: Vec<URLItem> =
-            URLItem::from_vec(registry.list_urls(None, None)?, Some(&default_columns));
+            URLItem::from_vec(registry.list_urls(None, sort_cfg)?, Some(&default_columns));
         let table = StatefulTable::with_items(items);
 
         Ok(BookmarksTable {
@@ -231,10 +436,52 @@ impl BookmarksTable {
             registry,
             table,
             filter: None,
-            sort_cfg: None,
+            sort_cfg,
             columns: default_columns,
+            profiles: None,
+            collapsed: BTreeSet::new(),
         })
     }
+
+    /// Enables switching profiles with the `profile` command
+    pub fn with_profiles(self, profiles: ProfileSwitcher) -> BookmarksTable {
+        BookmarksTable {
+            profiles: Some(profiles),
+            ..self
+        }
+    }
+}
+
+/// Replaces bookmarks of collapsed groups with a single item for each of them,
+/// placed where the first of its bookmarks would be
+fn fold_collapsed(
+    urls: Vec<URLRecord>,
+    collapsed: &BTreeSet<String>,
+    cols: &Columns,
+) -> Vec<URLItem> {
+    let mut items = vec![];
+    let mut folded: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for url in urls {
+        let root = groups::ancestors(&url.group)
+            .into_iter()
+            .find_map(|path| collapsed.get(path));
+        match root {
+            Some(root) => {
+                let (_, count) = folded.entry(root.as_str()).or_insert_with(|| {
+                    items.push(URLItem::new_collapsed_group(root, 0, cols));
+                    (items.len() - 1, 0)
+                });
+                *count += 1;
+            }
+            None => items.push(URLItem::new(url, Some(cols))),
+        }
+    }
+
+    for (root, (index, count)) in folded {
+        items[index] = URLItem::new_collapsed_group(root, count, cols);
+    }
+    items
 }
 
 fn unwrap_id(id: Option<String>) -> Result<String, Box<dyn std::error::Error>> {
@@ -22,15 +22,9 @@ pub struct Events {
     _input_handle: thread::JoinHandle<()>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Config {}
 
-impl Default for Config {
-    fn default() -> Config {
-        Config {}
-    }
-}
-
 impl Events {
     pub fn new() -> Events {
         Events::with_config(Config::default())
@@ -42,12 +36,10 @@ impl Events {
             let tx = tx.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if let Err(err) = tx.send(Event::Input(key)) {
-                            eprintln!("{}", err);
-                            return;
-                        }
+                for key in stdin.keys().flatten() {
+                    if let Err(err) = tx.send(Event::Input(key)) {
+                        eprintln!("{}", err);
+                        return;
                     }
                 }
             })
@@ -6,9 +6,13 @@ use super::event::Events;
 
 use crate::interactive::bookmarks_table::BookmarksTable;
 use crate::interactive::interface::Interface;
+use crate::interactive::profile_switcher::ProfileSwitcher;
 use bookmark_lib::Registry;
 
-pub fn enter_interactive_mode<T: Registry + 'static>(registry: T) -> Result<(), Box<dyn Error>> {
+pub fn enter_interactive_mode<T: Registry + 'static>(
+    registry: T,
+    profiles: Option<ProfileSwitcher>,
+) -> Result<(), Box<dyn Error>> {
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
     let stdout = AlternateScreen::from(stdout);
@@ -18,9 +22,12 @@ pub fn enter_interactive_mode<T: Registry + 'static>(registry: T) -> Result<(),
 
     let events = Events::new();
 
-    let bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry));
+    let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry))?;
+    if let Some(profiles) = profiles {
+        bookmarks_table = bookmarks_table.with_profiles(profiles);
+    }
 
-    let mut user_interface = Interface::new(bookmarks_table?)?;
+    let mut user_interface = Interface::new(bookmarks_table)?;
 
     loop {
         terminal.draw(|f| user_interface.draw(f))?;
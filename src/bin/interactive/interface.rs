@@ -3,8 +3,11 @@ use crate::interactive::event::{Event, Signal};
 use crate::interactive::helpers::to_string;
 use crate::interactive::modules::command::Command;
 use crate::interactive::modules::delete::Delete;
+use crate::interactive::modules::details::Details;
 use crate::interactive::modules::help::HelpPanel;
 use crate::interactive::modules::search::Search;
+use crate::interactive::modules::template::Template;
+use crate::interactive::modules::trash::Trash;
 use crate::interactive::modules::Module;
 use crate::interactive::table::TableItem;
 use crate::interactive::url_table_item::default_columns;
@@ -29,6 +32,9 @@ pub enum InputMode {
 pub enum SuppressedAction {
     ShowHelp,
     Delete,
+    Trash,
+    Details,
+    FillTemplate,
 }
 
 pub struct Interface {
@@ -46,6 +52,10 @@ pub struct Interface {
     cols_constraints: Vec<Constraint>,
 
     display_ids: bool,
+
+    display_dates: bool,
+
+    display_keywords: bool,
 }
 
 struct Styles {
@@ -62,6 +72,9 @@ impl Interface {
         let help_mod: Box<dyn Module> = Box::new(HelpPanel::new());
         let delete_mod: Box<dyn Module> = Box::new(Delete::new());
         let command_mod: Box<dyn Module> = Box::new(Command::new()?);
+        let trash_mod: Box<dyn Module> = Box::new(Trash::new());
+        let details_mod: Box<dyn Module> = Box::new(Details::new());
+        let template_mod: Box<dyn Module> = Box::new(Template::new());
 
         Ok(Interface {
             bookmarks_table,
@@ -70,6 +83,9 @@ impl Interface {
                 InputMode::Search => search_mod,
                 InputMode::Suppressed(SuppressedAction::ShowHelp) => help_mod,
                 InputMode::Suppressed(SuppressedAction::Delete) => delete_mod,
+                InputMode::Suppressed(SuppressedAction::Trash) => trash_mod,
+                InputMode::Suppressed(SuppressedAction::Details) => details_mod,
+                InputMode::Suppressed(SuppressedAction::FillTemplate) => template_mod,
                 InputMode::Command => command_mod
             ],
             styles: Styles {
@@ -85,6 +101,10 @@ impl Interface {
             cols_constraints: default_columns_constraints(),
 
             display_ids: false,
+
+            display_dates: false,
+
+            display_keywords: false,
         })
     }
 
@@ -104,12 +124,28 @@ impl Interface {
                     Key::Up => {
                         self.bookmarks_table.previous();
                     }
-                    Key::Char('\n') => {
+                    // URL templates are opened by the module asking for the placeholders
+                    Key::Char('\n') if !self.bookmarks_table.selected_is_template()? => {
                         self.bookmarks_table.open()?;
                     }
                     Key::Char('i') => {
                         self.toggle_ids_display()?;
                     }
+                    Key::Char('c') => {
+                        self.toggle_dates_display()?;
+                    }
+                    Key::Char('k') => {
+                        self.toggle_keywords_display()?;
+                    }
+                    Key::Char('u') => {
+                        self.bookmarks_table.undo()?;
+                    }
+                    Key::Char('z') => {
+                        self.bookmarks_table.toggle_group()?;
+                    }
+                    Key::Char('p') => {
+                        self.bookmarks_table.toggle_pin()?;
+                    }
                     // Activate first module that can handle the key - if none just skip
                     _ => {
                         for m in self.modules.values_mut() {
@@ -161,22 +197,22 @@ impl Interface {
                 .columns()
                 .clone()
                 .into_iter()
-                .map(|s| Text::raw(s)),
+                .map(Text::raw),
         )
         .style(self.styles.header);
+        let title = match self.bookmarks_table.profile() {
+            Some(profile) => format!("URLs [{}] - Press 'h' to show help", profile),
+            None => "URLs - Press 'h' to show help".to_string(),
+        };
         let table = self.bookmarks_table.table();
 
         let rows = table
             .items
             .iter()
-            .map(|i| Row::new(i.row().iter().map(|s| Text::raw(s))).style(normal_style));
+            .map(|i| Row::new(i.row().iter().map(Text::raw)).style(normal_style));
         let t = Table::new(rows, &self.cols_constraints)
             .header(header)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("URLs - Press 'h' to show help"),
-            )
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(self.styles.selected)
             .highlight_symbol("> ")
             .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
@@ -191,8 +227,21 @@ impl Interface {
 
     fn toggle_ids_display(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.display_ids = !self.display_ids;
+        self.update_columns()
+    }
+
+    fn toggle_dates_display(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.display_dates = !self.display_dates;
+        self.update_columns()
+    }
 
-        let (cols, constraints) = if self.display_ids {
+    fn toggle_keywords_display(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.display_keywords = !self.display_keywords;
+        self.update_columns()
+    }
+
+    fn update_columns(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut cols, mut constraints) = if self.display_ids {
             (
                 to_string(vec!["Id", "Name", "URL", "Group", "Tags"]),
                 columns_with_id_constraints(),
@@ -201,6 +250,17 @@ impl Interface {
             (default_columns(), default_columns_constraints())
         };
 
+        if self.display_keywords {
+            // Keywords are shown right after names, which are always displayed
+            let index = cols.iter().position(|c| c == "Name").map_or(0, |i| i + 1);
+            cols.insert(index, "Keyword".to_string());
+            constraints = with_keywords_constraints(constraints, index);
+        }
+        if self.display_dates {
+            cols.extend(to_string(vec!["Created", "Updated", "Opened"]));
+            constraints = with_dates_constraints(constraints);
+        }
+
         self.cols_constraints = constraints;
         self.bookmarks_table.set_columns(cols)?;
         Ok(())
@@ -226,19 +286,43 @@ fn columns_with_id_constraints() -> Vec<Constraint> {
     ]
 }
 
+/// Shrinks other columns to make space for dates, which take 12% of the width each
+fn with_dates_constraints(constraints: Vec<Constraint>) -> Vec<Constraint> {
+    let mut constraints: Vec<Constraint> = constraints
+        .into_iter()
+        .map(|c| match c {
+            Constraint::Percentage(p) => Constraint::Percentage(p * 64 / 100),
+            c => c,
+        })
+        .collect();
+    constraints.extend([Constraint::Percentage(12); 3]);
+    constraints
+}
+
+/// Shrinks other columns to make space for keywords, which take 10% of the width
+fn with_keywords_constraints(constraints: Vec<Constraint>, index: usize) -> Vec<Constraint> {
+    let mut constraints: Vec<Constraint> = constraints
+        .into_iter()
+        .map(|c| match c {
+            Constraint::Percentage(p) => Constraint::Percentage(p * 90 / 100),
+            c => c,
+        })
+        .collect();
+    constraints.insert(index, Constraint::Percentage(10));
+    constraints
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use crate::interactive::bookmarks_table::BookmarksTable;
     use crate::interactive::event::{Event, Events, Signal};
     use crate::interactive::interface::{InputMode, Interface, SuppressedAction};
+    use crate::interactive::profile_switcher::{ProfileSwitcher, RegistryOpener};
     use crate::interactive::table::TableItem;
+    use bookmark_lib::profile::{Profiles, DEFAULT_PROFILE};
     use bookmark_lib::registry::URLRegistry;
     use bookmark_lib::types::URLRecord;
     use bookmark_lib::Registry;
-    use rand::distributions::Alphanumeric;
-    use rand::{thread_rng, Rng};
-    use std::fs;
-    use std::path::{Path, PathBuf};
     use termion::event::Key;
 
     pub fn to_keys(text: &str) -> Vec<Key> {
@@ -259,40 +343,10 @@ pub(crate) mod test {
         ]
     }
 
-    struct Cleaner {
-        file_path: PathBuf,
-    }
-
-    // TODO: as general trait?
-    impl Cleaner {
-        fn new(file_path: PathBuf) -> Cleaner {
-            Cleaner { file_path }
-        }
-
-        fn clean(&self) {
-            if Path::new(&self.file_path).exists() {
-                fs::remove_file(&self.file_path).expect("Failed to remove file");
-            }
-        }
-    }
-
-    impl Drop for Cleaner {
-        fn drop(&mut self) {
-            self.clean()
-        }
-    }
-
-    fn rand_str() -> String {
-        let rand_string: String = thread_rng().sample_iter(&Alphanumeric).take(30).collect();
-
-        rand_string
-    }
-
     macro_rules! init {
     ($($urls:expr), *) => (
         {
-            let (registry, file_path) = URLRegistry::with_temp_file(&rand_str()).expect("Failed to initialize registry");
-            let cleaner = Cleaner::new(file_path); // makes sure that temp file is deleted even in case of panic
+            let registry = URLRegistry::new_in_memory();
             $(
                 for u in $urls {
                     registry.add(u).expect("Failed to add url");
@@ -302,9 +356,7 @@ pub(crate) mod test {
             let events = Events::new();
             let bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry)).expect("Failed to initialize Bookmarks table");
 
-            let interface = Interface::new(bookmarks_table).expect("Failed to initialize interface");
-
-            (interface, cleaner)
+            Interface::new(bookmarks_table).expect("Failed to initialize interface")
         }
     );
     () => (
@@ -314,7 +366,7 @@ pub(crate) mod test {
 
     #[test]
     fn test_handle_input_returns() {
-        let (mut interface, _) = init!();
+        let mut interface = init!();
 
         // Should quit when input 'q'
         let event = Event::Input(Key::Char('q'));
@@ -347,7 +399,7 @@ pub(crate) mod test {
 
     #[test]
     fn test_handle_input_input_modes() {
-        let (mut interface, _) = init!();
+        let mut interface = init!();
 
         assert!(InputMode::Normal == interface.input_mode);
 
@@ -426,7 +478,7 @@ pub(crate) mod test {
 
     #[test]
     fn test_handle_input_switch_input_modes() {
-        let (mut interface, _) = init!();
+        let mut interface = init!();
 
         assert!(InputMode::Normal == interface.input_mode);
 
@@ -474,7 +526,7 @@ pub(crate) mod test {
 
     #[test]
     fn test_handle_input_search() {
-        let (mut interface, _cleaner) = init!(fix_url_records());
+        let mut interface = init!(fix_url_records());
 
         println!("Should filter items in table on input...");
         let event = Event::Input(Key::Char('/'));
@@ -483,7 +535,7 @@ pub(crate) mod test {
             .expect("Failed to handle event");
         assert!(!quit);
 
-        for event in vec![
+        for event in [
             Event::Input(Key::Char('t')),
             Event::Input(Key::Char('a')),
             Event::Input(Key::Char('g')),
@@ -512,7 +564,7 @@ pub(crate) mod test {
         assert_eq!(2, interface.bookmarks_table.table().items.len()); // URLs with tag 'tag'
 
         println!("Should filter items in table on backspace...");
-        for event in vec![Event::Input(Key::Backspace), Event::Input(Key::Backspace)] {
+        for event in [Event::Input(Key::Backspace), Event::Input(Key::Backspace)] {
             let quit = interface
                 .handle_input(event)
                 .expect("Failed to handle event");
@@ -611,7 +663,7 @@ pub(crate) mod test {
         for test in &test_cases {
             println!("Running test case: {}", test.description);
 
-            let (mut interface, _) = init!(fix_url_records());
+            let mut interface = init!(fix_url_records());
 
             for i in 0..test.events.len() {
                 let quit = interface
@@ -628,7 +680,7 @@ pub(crate) mod test {
 
     #[test]
     fn test_toggle_ids() {
-        let (mut interface, _cleaner) = init!(fix_url_records());
+        let mut interface = init!(fix_url_records());
 
         println!("Should be hidden at start...");
         let row = interface.bookmarks_table.table().items[0].row();
@@ -666,6 +718,372 @@ pub(crate) mod test {
         assert_eq!(row[3], "tag, \"with space\"");
     }
 
+    #[test]
+    fn test_collapse_groups() {
+        let mut interface = init!([
+            URLRecord::new("home.com", "home", "home", Vec::<String>::new()),
+            URLRecord::new("grafana.com", "grafana", "work/infra", Vec::<String>::new()),
+            URLRecord::new("wiki.com", "wiki", "work/docs", Vec::<String>::new()),
+            URLRecord::new("mail.com", "mail", "work", Vec::<String>::new())
+        ]);
+        let names = |interface: &mut Interface| -> Vec<String> {
+            let table = interface.bookmarks_table.table();
+            table.items.iter().map(|i| i.row()[0].clone()).collect()
+        };
+
+        println!("Should collapse group of selected URL...");
+        for key in [Key::Down, Key::Down, Key::Char('z')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert_eq!(
+            names(&mut interface),
+            vec!["home", "▸ work/infra (1)", "wiki", "mail"]
+        );
+        assert_eq!(interface.bookmarks_table.table().state.selected(), Some(1));
+        assert!(interface
+            .bookmarks_table
+            .get_selected()
+            .expect("Failed to get selected")
+            .is_none());
+
+        println!("Should expand selected group...");
+        interface
+            .handle_input(Event::Input(Key::Char('z')))
+            .expect("Failed to handle event");
+        assert_eq!(
+            names(&mut interface),
+            vec!["home", "grafana", "wiki", "mail"]
+        );
+
+        println!("Should collapse whole subtree...");
+        interface
+            .bookmarks_table
+            .exec("collapse", vec!["work"])
+            .expect("Failed to collapse group");
+        assert_eq!(names(&mut interface), vec!["home", "▸ work (3)"]);
+        assert!(interface.bookmarks_table.exec("tag", vec!["x"]).is_err());
+
+        println!("Should expand group on enter...");
+        interface
+            .handle_input(Event::Input(Key::Char('\n')))
+            .expect("Failed to handle event");
+        assert_eq!(
+            names(&mut interface),
+            vec!["home", "grafana", "wiki", "mail"]
+        );
+
+        println!("Should expand all groups...");
+        for group in ["work/infra", "home"] {
+            interface
+                .bookmarks_table
+                .exec("collapse", vec![group])
+                .expect("Failed to collapse group");
+        }
+        assert_eq!(
+            names(&mut interface),
+            vec!["▸ home (1)", "▸ work/infra (1)", "wiki", "mail"]
+        );
+        interface
+            .bookmarks_table
+            .exec("expand", vec![])
+            .expect("Failed to expand groups");
+        assert_eq!(
+            names(&mut interface),
+            vec!["home", "grafana", "wiki", "mail"]
+        );
+    }
+
+    #[test]
+    fn test_toggle_pin() {
+        let mut interface = init!(fix_url_records());
+        let names = |interface: &mut Interface| -> Vec<String> {
+            let table = interface.bookmarks_table.table();
+            table.items.iter().map(|i| i.row()[0].clone()).collect()
+        };
+
+        println!("Should pin selected URL and keep it selected...");
+        for key in [Key::Down, Key::Down, Key::Down, Key::Char('p')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert_eq!(
+            names(&mut interface),
+            vec!["★ three", "one", "two", "four", "five"]
+        );
+        assert_eq!(interface.bookmarks_table.table().state.selected(), Some(0));
+
+        println!("Should unpin selected URL...");
+        interface
+            .handle_input(Event::Input(Key::Char('p')))
+            .expect("Failed to handle event");
+        assert_eq!(
+            names(&mut interface),
+            vec!["one", "two", "three", "four", "five"]
+        );
+    }
+
+    #[test]
+    fn test_key_value_tags() {
+        let mut interface = init!(fix_url_records());
+        interface
+            .handle_input(Event::Input(Key::Down))
+            .expect("Failed to handle event");
+
+        println!("Should show key/value tags in the tags column...");
+        for command in [":tag env=prod\n", ":tag env=staging\n", ":untag tag\n"] {
+            for e in to_key_events(command) {
+                interface.handle_input(e).expect("Failed to handle event");
+            }
+        }
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row[3], "env=staging, \"with space\"");
+    }
+
+    #[test]
+    fn test_show_details() {
+        let mut interface = init!(fix_url_records());
+
+        println!("Should not show details if nothing is selected...");
+        interface
+            .handle_input(Event::Input(Key::Char('v')))
+            .expect("Failed to handle event");
+        assert!(InputMode::Normal == interface.input_mode);
+
+        println!("Should show details of selected URL...");
+        for key in [Key::Down, Key::Char('v')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert!(InputMode::Suppressed(SuppressedAction::Details) == interface.input_mode);
+
+        println!("Should close details...");
+        interface
+            .handle_input(Event::Input(Key::Esc))
+            .expect("Failed to handle event");
+        assert!(InputMode::Normal == interface.input_mode);
+    }
+
+    #[test]
+    fn test_toggle_dates() {
+        let mut interface = init!(fix_url_records());
+
+        println!("Should show dates...");
+        interface
+            .handle_input(Event::Input(Key::Char('c')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.cols_constraints.len(), 7);
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 7);
+        assert_eq!(row[0], "one");
+        assert_eq!(row[4].len(), 16);
+        assert_eq!(row[5].len(), 16);
+        assert_eq!(row[6], "never");
+
+        println!("Should show dates together with ids...");
+        interface
+            .handle_input(Event::Input(Key::Char('i')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.cols_constraints.len(), 8);
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 8);
+        assert_eq!(row[0].len(), 16);
+
+        println!("Should hide dates...");
+        interface
+            .handle_input(Event::Input(Key::Char('c')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.cols_constraints.len(), 5);
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 5);
+    }
+
+    #[test]
+    fn test_toggle_keywords() {
+        let mut records = fix_url_records();
+        records[0].keyword = "o".to_string();
+        let mut interface = init!(records);
+
+        println!("Should show keywords after names...");
+        interface
+            .handle_input(Event::Input(Key::Char('k')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.cols_constraints.len(), 5);
+        assert_eq!(interface.bookmarks_table.columns()[1], "Keyword");
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 5);
+        assert_eq!(row[0], "one");
+        assert_eq!(row[1], "o");
+
+        println!("Should show keywords together with ids...");
+        interface
+            .handle_input(Event::Input(Key::Char('i')))
+            .expect("Failed to handle event");
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 6);
+        assert_eq!(row[1], "one");
+        assert_eq!(row[2], "o");
+
+        println!("Should hide keywords...");
+        for key in [Key::Char('k'), Key::Char('i')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert_eq!(interface.cols_constraints.len(), 4);
+        let row = interface.bookmarks_table.table().items[0].row();
+        assert_eq!(row.len(), 4);
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let mut interface = init!([URLRecord::new(
+            "https://jira.example.com/browse/{ticket}",
+            "jira",
+            "work",
+            Vec::<String>::new()
+        )]);
+        interface
+            .handle_input(Event::Input(Key::Down))
+            .expect("Failed to handle event");
+
+        println!("Should reject opening template without values...");
+        assert!(interface.bookmarks_table.open().is_err());
+
+        println!("Should ask for placeholders instead of opening template...");
+        interface
+            .handle_input(Event::Input(Key::Char('\n')))
+            .expect("Failed to handle event");
+        assert!(InputMode::Suppressed(SuppressedAction::FillTemplate) == interface.input_mode);
+
+        println!("Should not accept empty value...");
+        interface
+            .handle_input(Event::Input(Key::Char('\n')))
+            .expect("Failed to handle event");
+        assert!(InputMode::Suppressed(SuppressedAction::FillTemplate) == interface.input_mode);
+
+        println!("Should cancel filling template...");
+        for event in to_key_events("ABC-1") {
+            interface
+                .handle_input(event)
+                .expect("Failed to handle event");
+        }
+        interface
+            .handle_input(Event::Input(Key::Esc))
+            .expect("Failed to handle event");
+        assert!(InputMode::Normal == interface.input_mode);
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut interface = init!(fix_url_records());
+
+        println!("Should delete URL...");
+        interface
+            .handle_input(Event::Input(Key::Down))
+            .expect("Failed to handle event");
+        for key in [Key::Char('d'), Key::Char('\n')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert_eq!(interface.bookmarks_table.table().items.len(), 4);
+
+        println!("Should restore deleted URL...");
+        let quit = interface
+            .handle_input(Event::Input(Key::Char('u')))
+            .expect("Failed to handle event");
+        assert!(!quit);
+        assert_eq!(interface.bookmarks_table.table().items.len(), 5);
+    }
+
+    #[test]
+    fn test_trash() {
+        let mut interface = init!(fix_url_records());
+
+        println!("Should move URL to trash...");
+        interface
+            .handle_input(Event::Input(Key::Down))
+            .expect("Failed to handle event");
+        for key in [Key::Char('d'), Key::Char('\n')] {
+            interface
+                .handle_input(Event::Input(key))
+                .expect("Failed to handle event");
+        }
+        assert_eq!(interface.bookmarks_table.table().items.len(), 4);
+
+        println!("Should open trash...");
+        interface
+            .handle_input(Event::Input(Key::Char('t')))
+            .expect("Failed to handle event");
+        assert!(InputMode::Suppressed(SuppressedAction::Trash) == interface.input_mode);
+
+        println!("Should restore URL from trash...");
+        interface
+            .handle_input(Event::Input(Key::Char('\n')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.bookmarks_table.table().items.len(), 5);
+
+        println!("Should do nothing when trash is empty...");
+        interface
+            .handle_input(Event::Input(Key::Char('\n')))
+            .expect("Failed to handle event");
+        assert_eq!(interface.bookmarks_table.table().items.len(), 5);
+
+        println!("Should close trash...");
+        interface
+            .handle_input(Event::Input(Key::Esc))
+            .expect("Failed to handle event");
+        assert!(InputMode::Normal == interface.input_mode);
+    }
+
+    #[test]
+    fn test_switch_profile() {
+        let mut config_dir = std::env::temp_dir();
+        config_dir.push(format!("bookmark_profiles_test_{}", std::process::id()));
+        let profiles = Profiles::new(config_dir.clone());
+        profiles.create("work").expect("Failed to create profile");
+
+        let opener: RegistryOpener = Box::new(|profile| {
+            let registry = URLRegistry::new_in_memory();
+            if profile == "work" {
+                registry
+                    .add(URLRecord::new("work", "work", "work", Vec::<String>::new()))
+                    .expect("Failed to add url");
+            }
+            Ok(Box::new(registry))
+        });
+
+        let events = Events::new();
+        let bookmarks_table =
+            BookmarksTable::new(events.tx.clone(), Box::new(URLRegistry::new_in_memory()))
+                .expect("Failed to initialize Bookmarks table")
+                .with_profiles(ProfileSwitcher::new(profiles, DEFAULT_PROFILE, opener));
+        let mut interface =
+            Interface::new(bookmarks_table).expect("Failed to initialize interface");
+        assert_eq!(interface.bookmarks_table.table().items.len(), 0);
+
+        println!("Should switch to other profile...");
+        for e in to_key_events(&join_command((":profile", "work"))) {
+            interface.handle_input(e).expect("Failed to handle event");
+        }
+        assert_eq!(interface.bookmarks_table.profile(), Some("work"));
+        assert_eq!(interface.bookmarks_table.table().items.len(), 1);
+
+        println!("Should not switch to not existing profile...");
+        for e in to_key_events(&join_command((":profile", "missing"))) {
+            interface.handle_input(e).expect("Failed to handle event");
+        }
+        assert_eq!(interface.bookmarks_table.profile(), Some("work"));
+        assert_eq!(interface.bookmarks_table.table().items.len(), 1);
+
+        println!("Cleanup...");
+        std::fs::remove_dir_all(config_dir).expect("Failed to remove config dir");
+    }
+
     struct TestCaseCommands {
         commands_chain: Vec<(&'static str, &'static str)>,
     }
@@ -704,7 +1122,7 @@ pub(crate) mod test {
         ];
 
         for test_case in test_cases {
-            let (mut interface, _cleaner) = init!(fix_url_records());
+            let mut interface = init!(fix_url_records());
 
             println!("Select first URL...");
             interface
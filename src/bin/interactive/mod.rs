@@ -17,4 +17,6 @@ mod bookmarks_table;
 
 pub mod interactive_mode;
 
+pub mod profile_switcher;
+
 pub mod subcommand;
@@ -1,6 +1,7 @@
 use crate::interactive::bookmarks_table::BookmarksTable;
 use crate::interactive::interface::InputMode;
 use crate::interactive::modules::{Draw, HandleInput, Module};
+use bookmark_lib::error::Error as RegistryError;
 use ratatui::layout::Rect;
 use ratatui::style::Style;
 use ratatui::text::Text;
@@ -46,14 +47,14 @@ impl HandleInput for Command {
                 return Ok(Some(InputMode::Normal));
             }
             Key::Char('\n') => {
-                if self.command_input == "" {
+                if self.command_input.is_empty() {
                     return Ok(None);
                 }
 
                 let action_index = self
                     .command_input
                     .find(' ')
-                    .unwrap_or_else(|| self.command_input.len());
+                    .unwrap_or(self.command_input.len());
 
                 let action = &self.command_input.as_str()[0..action_index];
 
@@ -61,7 +62,7 @@ impl HandleInput for Command {
                     self.parse_args(&(self.command_input.as_str())[action_index + 1..])?
                         .iter()
                         .map(|s| s.to_owned())
-                        .filter(|s| *s != "")
+                        .filter(|s| !s.is_empty())
                         .collect()
                 } else {
                     vec![]
@@ -74,7 +75,7 @@ impl HandleInput for Command {
                         Ok(Some(InputMode::Normal))
                     }
                     Err(err) => {
-                        self.info_display = err.to_string();
+                        self.info_display = error_message(err.as_ref());
                         Ok(None)
                     }
                 };
@@ -105,7 +106,7 @@ impl Draw for Command {
             }
             _ => {
                 // if search phrase is not empty - keep displaying search box
-                if self.command_input != "" {
+                if !self.command_input.is_empty() {
                     self.render_command_input(f);
                 }
             }
@@ -183,6 +184,22 @@ impl Command {
     }
 }
 
+/// Describes the registry errors in terms of the selected bookmark
+pub(crate) fn error_message(err: &(dyn Error + 'static)) -> String {
+    match err.downcast_ref::<RegistryError>() {
+        Some(RegistryError::NotFound(id)) => {
+            format!("error: bookmark '{}' does not exist anymore", id)
+        }
+        Some(RegistryError::Duplicate { name, group }) => format!(
+            "error: bookmark with name '{}' already exists in '{}' group",
+            name, group
+        ),
+        Some(RegistryError::EmptyField(field)) => format!("error: {} cannot be empty", field),
+        Some(err) => format!("error: {}", err),
+        None => err.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::interactive::bookmarks_table::BookmarksTable;
@@ -198,8 +215,7 @@ mod test {
     #[test]
     fn test_exec_command() {
         let mut command_module = Command::new().expect("Failed to create command module");
-        let (registry, _) = URLRegistry::with_temp_file("command_test1.json")
-            .expect("Failed to initialize Registry");
+        let registry = URLRegistry::new_in_memory();
         registry
             .create("abcd", "url", None, vec![])
             .expect("Failed to create Bookmark");
@@ -215,7 +231,7 @@ mod test {
             let mode = command_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
 
         println!("Should execute 'tag' command...");
@@ -232,8 +248,7 @@ mod test {
     #[test]
     fn test_command_with_no_args() {
         let mut command_module = Command::new().expect("Failed to create command module");
-        let (registry, _) = URLRegistry::with_temp_file("command_test2.json")
-            .expect("Failed to initialize Registry");
+        let registry = URLRegistry::new_in_memory();
         registry
             .create("xyz", "url_xyz", None, vec![])
             .expect("Failed to create Bookmark");
@@ -252,7 +267,7 @@ mod test {
             let mode = command_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
 
         println!("Should execute 'sort' command...");
@@ -269,8 +284,7 @@ mod test {
     #[test]
     fn test_exec_display_error_message_when_cmd_failed() {
         let mut command_module = Command::new().expect("Failed to create command module");
-        let (registry, _) = URLRegistry::with_temp_file("command_test3.json")
-            .expect("Failed to initialize Registry");
+        let registry = URLRegistry::new_in_memory();
         let events = Events::new();
 
         let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry))
@@ -283,24 +297,57 @@ mod test {
             let mode = command_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
 
         println!("Should fail to execute 'tag' command when no item selected...");
         let mode = command_module
             .handle_input(Key::Char('\n'), &mut bookmarks_table)
             .expect("Failed to handle event");
-        assert!(mode == None);
+        assert!(mode.is_none());
         assert_eq!(command_module.info_display, "error: item not selected");
         assert_eq!(command_module.command_input, "tag test");
         assert_eq!(command_module.command_display, ":tag test");
     }
 
+    #[test]
+    fn test_exec_display_registry_error_message() {
+        let mut command_module = Command::new().expect("Failed to create command module");
+        let registry = URLRegistry::new_in_memory();
+        registry
+            .create("abcd", "url_abcd", None, vec![])
+            .expect("Failed to create Bookmark");
+        registry
+            .create("xyz", "url_xyz", None, vec![])
+            .expect("Failed to create Bookmark");
+        let events = Events::new();
+
+        let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry))
+            .expect("Failed to initialized Bookmarks table");
+
+        println!("Should input command phrase...");
+        for key in to_keys("chn xyz") {
+            command_module
+                .handle_input(key, &mut bookmarks_table)
+                .expect("Failed to handle event");
+        }
+
+        println!("Should fail to change name to the one already existing in group...");
+        bookmarks_table.table().state.select(Some(0));
+        let mode = command_module
+            .handle_input(Key::Char('\n'), &mut bookmarks_table)
+            .expect("Failed to handle event");
+        assert!(mode.is_none());
+        assert_eq!(
+            command_module.info_display,
+            "error: bookmark with name 'xyz' already exists in 'default' group"
+        );
+    }
+
     #[test]
     fn test_do_nothing_when_input_empty() {
         let mut command_module = Command::new().expect("Failed to create command module");
-        let (registry, _) = URLRegistry::with_temp_file("command_test4.json")
-            .expect("Failed to initialize Registry");
+        let registry = URLRegistry::new_in_memory();
         let events = Events::new();
 
         let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry))
@@ -310,7 +357,7 @@ mod test {
         let mode = command_module
             .handle_input(Key::Char('\n'), &mut bookmarks_table)
             .expect("Failed to handle event");
-        assert!(mode == None);
+        assert!(mode.is_none());
         assert_eq!(command_module.info_display, DEFAULT_INFO_MESSAGE);
         assert_eq!(command_module.command_input, "");
         assert_eq!(command_module.command_display, ":");
@@ -319,8 +366,7 @@ mod test {
     #[test]
     fn test_handle_input_write_command() {
         let mut command_module = Command::new().expect("Failed to create command module");
-        let (registry, _) = URLRegistry::with_temp_file("command_test5.json")
-            .expect("Failed to initialize Registry");
+        let registry = URLRegistry::new_in_memory();
         let events = Events::new();
 
         let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(registry))
@@ -333,7 +379,7 @@ mod test {
             let mode = command_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
         assert_eq!("tag test".to_string(), command_module.command_input);
 
@@ -348,7 +394,7 @@ mod test {
             let mode = command_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
         assert_eq!("tag temp".to_string(), command_module.command_input);
     }
@@ -412,7 +458,7 @@ mod test {
                 .expect("Error parsing args")
                 .iter()
                 .map(|s| s.to_owned())
-                .filter(|s| *s != "")
+                .filter(|s| !s.is_empty())
                 .collect();
 
             println!("{:?}", out);
@@ -80,7 +80,7 @@ impl Delete {
 
         let mut text = Text::raw("");
         text.push_line(format!(
-            "Delete '{}' from '{}' group?",
+            "Move '{}' from '{}' group to trash?",
             record.name, record.group
         ));
         text.push_line("");
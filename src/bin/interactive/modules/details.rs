@@ -0,0 +1,188 @@
+use crate::display::{time_str, url_str};
+use crate::interactive::bookmarks_table::BookmarksTable;
+use crate::interactive::interface::{InputMode, SuppressedAction};
+use crate::interactive::modules::{Draw, HandleInput, Module};
+use crate::interactive::widgets::rect::centered_fixed_rect;
+use bookmark_lib::types::{BundleMember, URLRecord};
+use ratatui::layout::Alignment;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+use std::error::Error;
+use termion::event::Key;
+
+const MIN_WIDTH: u16 = 50;
+
+/// Shows all fields of the selected bookmark, including its notes
+pub(crate) struct Details {
+    record: Option<URLRecord>,
+}
+
+impl Module for Details {}
+
+impl HandleInput for Details {
+    fn try_activate(
+        &mut self,
+        input: Key,
+        table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        if input != Key::Char('v') {
+            return Ok(None);
+        }
+
+        self.record = table.get_selected()?;
+        if self.record.is_none() {
+            return Ok(Some(InputMode::Normal));
+        }
+
+        Ok(Some(InputMode::Suppressed(SuppressedAction::Details)))
+    }
+
+    fn handle_input(
+        &mut self,
+        input: Key,
+        _table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        match input {
+            Key::Esc | Key::Char('\n') | Key::Char('q') | Key::Char('v') => {
+                self.record = None;
+                Ok(Some(InputMode::Normal))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Draw for Details {
+    fn draw(&self, mode: InputMode, f: &mut Frame) {
+        if mode == InputMode::Suppressed(SuppressedAction::Details) {
+            if let Some(record) = &self.record {
+                self.show_details_popup(record, f);
+            }
+        }
+    }
+}
+
+impl Details {
+    pub fn new() -> Details {
+        Details { record: None }
+    }
+
+    fn show_details_popup(&self, record: &URLRecord, f: &mut Frame) {
+        let text = detail_lines(record);
+
+        let size = f.size();
+        let max_width = text.iter().map(|t| t.len()).max().unwrap_or_default() as u16;
+        let width = (max_width + 4).max(MIN_WIDTH).min(size.width);
+        let height = (text.len() as u16 + 2).min(size.height);
+        let lines: Vec<Line> = text.into_iter().map(Line::from).collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Black).fg(Color::LightBlue))
+            .title(Span::styled(
+                format!("{} - press ESC to close", record.name),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+
+        let area = centered_fixed_rect(width, height, size);
+        let paragraph = Paragraph::new(lines)
+            .style(Style::default().bg(Color::Black).fg(Color::White))
+            .block(block)
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+}
+
+fn detail_lines(record: &URLRecord) -> Vec<String> {
+    let mut lines = vec![
+        format!("Id:      {}", record.id),
+        format!("Name:    {}", record.name),
+        format!("URL:     {}", url_str(record)),
+        format!("Group:   {}", record.group),
+        format!("Tags:    {}", record.tags_as_string()),
+        format!("Created: {}", time_str(record.created_at.as_ref(), "-")),
+        format!("Updated: {}", time_str(record.updated_at.as_ref(), "-")),
+        format!(
+            "Opened:  {} ({} times)",
+            time_str(record.last_opened_at.as_ref(), "never"),
+            record.open_count
+        ),
+        "".to_string(),
+    ];
+    if !record.keyword.is_empty() {
+        lines.insert(2, format!("Keyword: {}", record.keyword));
+    }
+    if record.is_bundle() {
+        lines.push("Bundle:".to_string());
+        lines.extend(record.bundle.iter().map(|m| match m {
+            BundleMember::Url(url) => format!("  {}", url),
+            BundleMember::Bookmark(id) => format!("  bookmark {}", id),
+        }));
+        lines.push("".to_string());
+    }
+
+    if record.notes.is_empty() {
+        lines.push("No notes, add them with `bookmark notes <ID>`".to_string());
+    } else {
+        lines.push("Notes:".to_string());
+        lines.extend(record.notes.lines().map(|l| l.to_string()));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::modules::details::detail_lines;
+    use bookmark_lib::types::{BundleMember, URLRecord};
+    use chrono::Utc;
+
+    #[test]
+    fn test_detail_lines() {
+        let mut record = URLRecord::new("https://one.com", "one", "dev", vec!["tag"]);
+
+        println!("Should show hint when there are no notes...");
+        let lines = detail_lines(&record);
+        assert_eq!(lines[1], "Name:    one");
+        assert_eq!(lines[7], "Opened:  never (0 times)");
+        assert_eq!(
+            lines.last().expect("No lines"),
+            "No notes, add them with `bookmark notes <ID>`"
+        );
+
+        println!("Should show keyword after the name...");
+        record.keyword = "o".to_string();
+        assert_eq!(detail_lines(&record)[2], "Keyword: o");
+
+        println!("Should list members of the bundle...");
+        let bundle = URLRecord::new_bundle(
+            "daily",
+            "dev",
+            Vec::<String>::new(),
+            vec![
+                BundleMember::Url("https://one.com".to_string()),
+                BundleMember::Bookmark("abcd".to_string()),
+            ],
+            Utc::now(),
+        );
+        let lines = detail_lines(&bundle);
+        assert_eq!(lines[2], "URL:     bundle of 2 URLs");
+        assert_eq!(
+            lines[9..12].to_vec(),
+            vec!["Bundle:", "  https://one.com", "  bookmark abcd"]
+        );
+
+        println!("Should show multi-line notes...");
+        record.notes = "Use staging creds\nfor the demo".to_string();
+        let lines = detail_lines(&record);
+        assert_eq!(
+            lines[lines.len() - 3..].to_vec(),
+            vec!["Notes:", "Use staging creds", "for the demo"]
+        );
+    }
+}
@@ -63,21 +63,31 @@ impl HelpPanel {
     fn show_help_popup(&self, f: &mut Frame) {
         let text = vec![
             "Action               Description",
-            "'ENTER'            | open bookmarked URL",
+            "'ENTER'            | open bookmarked URL or bundle, asking for values of URL template placeholders",
             "'/' or 'CTRL + F'  | search for URLs",
-            "'d'                | delete URL",
+            "'d'                | move URL to trash",
+            "'t'                | show trash and restore deleted URLs",
+            "'v'                | show details and notes of selected URL",
             "'i'                | show/hide ids",
+            "'c'                | show/hide created, updated and opened dates",
+            "'k'                | show/hide keywords",
+            "'u'                | undo last change",
+            "'p'                | pin selected URL at the top or unpin it",
+            "'z'                | collapse group of selected URL or expand selected group",
             "'q'                | exit interactive mode",
             "':'                | go to command mode",
             "",
             "",
             "Command                Alias     Description",
-            "':tag <TAG_NAME>'    |         | add tag <TAG_NAME> to selected bookmark",
-            "':untag <TAG_NAME>'  |         | remove tag <TAG_NAME> from selected bookmark",
+            "':tag <TAG>'         |         | add tag <TAG>, plain or key=value, to selected bookmark",
+            "':untag <TAG>'       |         | remove tag <TAG>, by key or key=value, from selected bookmark",
             "':chgroup <GROUP>'   | chg     | change group to <GROUP> for selected bookmark",
             "':chname <NAME>'     | chn     | change name to <NAME> for selected bookmark",
             "':churl <URL>'       | chu     | change url to <URL> for selected bookmark",
-            "':sort [SORT_BY]'    |         | sort bookmarks by one of: [name, url, group]",
+            "':sort [SORT_BY]'    |         | sort bookmarks by one of: [name, url, group, created, updated, opened, frecency]",
+            "':profile <NAME>'    |         | switch to bookmarks of profile <NAME>",
+            "':collapse [GROUP]'  |         | show <GROUP> or group of selected bookmark as single row",
+            "':expand [GROUP]'    |         | expand <GROUP> or all collapsed groups",
             "':q'                 | quit    | exit interactive mode",
             "",
         ];
@@ -5,8 +5,11 @@ use termion::event::Key;
 
 pub mod command;
 pub mod delete;
+pub mod details;
 pub mod help;
 pub mod search;
+pub mod template;
+pub mod trash;
 
 pub trait Module: HandleInput + Draw {}
 
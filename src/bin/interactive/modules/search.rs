@@ -68,7 +68,7 @@ impl Draw for Search {
             }
             _ => {
                 // if search phrase is not empty - keep displaying search box
-                if self.search_phrase != "" {
+                if !self.search_phrase.is_empty() {
                     self.render_search_input(f);
                 }
             }
@@ -112,8 +112,7 @@ mod test {
     #[test]
     fn test_handle_input_search_phrase() {
         let mut search_module = Search::new();
-        let (dummy_registry, _) = URLRegistry::with_temp_file("search_test1.json")
-            .expect("Failed to initialize Registry");
+        let dummy_registry = URLRegistry::new_in_memory();
         let events = Events::new();
 
         let mut bookmarks_table = BookmarksTable::new(events.tx.clone(), Box::new(dummy_registry))
@@ -126,7 +125,7 @@ mod test {
             let mode = search_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
         assert_eq!("test 1".to_string(), search_module.search_phrase);
 
@@ -141,7 +140,7 @@ mod test {
             let mode = search_module
                 .handle_input(key, &mut bookmarks_table)
                 .expect("Failed to handle event");
-            assert!(mode == None);
+            assert!(mode.is_none());
         }
         assert_eq!("test-2".to_string(), search_module.search_phrase);
     }
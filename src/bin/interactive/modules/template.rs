@@ -0,0 +1,219 @@
+use crate::interactive::bookmarks_table::BookmarksTable;
+use crate::interactive::interface::{InputMode, SuppressedAction};
+use crate::interactive::modules::{Draw, HandleInput, Module};
+use crate::interactive::widgets::rect::centered_fixed_rect;
+use bookmark_lib::template::{is_template, placeholders_of_all};
+use bookmark_lib::types::URLRecord;
+use ratatui::layout::Alignment;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+use std::collections::BTreeMap;
+use std::error::Error;
+use termion::event::Key;
+
+const MIN_WIDTH: u16 = 50;
+
+/// Asks for values of the placeholders of the URL templates, one by one,
+/// before opening the selected bookmark or bundle
+pub(crate) struct Template {
+    record: Option<URLRecord>,
+    /// URLs opened with the record, of which some are templates
+    urls: Vec<String>,
+    /// Placeholders without values, in the order they are asked for
+    missing: Vec<String>,
+    values: BTreeMap<String, String>,
+    input: String,
+}
+
+impl Module for Template {}
+
+impl HandleInput for Template {
+    fn try_activate(
+        &mut self,
+        input: Key,
+        table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        if input != Key::Char('\n') {
+            return Ok(None);
+        }
+
+        let record = match table.get_selected()? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let urls = table.urls_to_open(&record)?;
+        let missing = placeholders_of_all(&urls);
+        if missing.is_empty() {
+            return Ok(None);
+        }
+
+        self.reset();
+        self.record = Some(record);
+        self.urls = urls;
+        self.missing = missing;
+        Ok(Some(InputMode::Suppressed(SuppressedAction::FillTemplate)))
+    }
+
+    fn handle_input(
+        &mut self,
+        input: Key,
+        table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        match input {
+            Key::Esc => {
+                self.reset();
+                return Ok(Some(InputMode::Normal));
+            }
+            Key::Char('\n') => {
+                // Placeholders cannot be left empty
+                if self.input.trim().is_empty() || self.missing.is_empty() {
+                    return Ok(None);
+                }
+                let name = self.missing.remove(0);
+                self.values.insert(name, self.input.trim().to_string());
+                self.input.clear();
+
+                if self.missing.is_empty() {
+                    let values = self.values.clone();
+                    self.reset();
+                    table.open_with(&values)?;
+                    return Ok(Some(InputMode::Normal));
+                }
+            }
+            Key::Char(c) => self.input.push(c),
+            Key::Backspace => {
+                self.input.pop();
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+}
+
+impl Draw for Template {
+    fn draw(&self, mode: InputMode, f: &mut Frame) {
+        if let InputMode::Suppressed(SuppressedAction::FillTemplate) = mode {
+            if let Some(record) = &self.record {
+                self.show_template_popup(record, f);
+            }
+        }
+    }
+}
+
+impl Template {
+    pub fn new() -> Template {
+        Template {
+            record: None,
+            urls: vec![],
+            missing: vec![],
+            values: BTreeMap::new(),
+            input: "".to_string(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.record = None;
+        self.urls.clear();
+        self.missing.clear();
+        self.values.clear();
+        self.input.clear();
+    }
+
+    fn show_template_popup(&self, record: &URLRecord, f: &mut Frame) {
+        let text = prompt_lines(&self.urls, &self.values, &self.missing, &self.input);
+
+        let size = f.size();
+        let max_width = text.iter().map(|t| t.len()).max().unwrap_or_default() as u16;
+        let width = (max_width + 4).max(MIN_WIDTH).min(size.width);
+        let height = (text.len() as u16 + 2).min(size.height);
+        let lines: Vec<Line> = text.into_iter().map(Line::from).collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Black).fg(Color::LightBlue))
+            .title(Span::styled(
+                format!("Open {} - press ESC to cancel", record.name),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+
+        let area = centered_fixed_rect(width, height, size);
+        let paragraph = Paragraph::new(lines)
+            .style(Style::default().bg(Color::Black).fg(Color::White))
+            .block(block)
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Lists URL templates and values given so far followed by the prompt for the next placeholder
+fn prompt_lines(
+    urls: &[String],
+    values: &BTreeMap<String, String>,
+    missing: &[String],
+    input: &str,
+) -> Vec<String> {
+    let templates: Vec<&String> = urls.iter().filter(|u| is_template(u)).collect();
+    let mut lines: Vec<String> = templates.iter().map(|u| format!("URL: {}", u)).collect();
+    lines.push("".to_string());
+
+    for name in placeholders_of_all(urls) {
+        if let Some(value) = values.get(&name) {
+            lines.push(format!("{}: {}", name, value));
+        }
+    }
+    if let Some(name) = missing.first() {
+        lines.push(format!("{}: {}_", name, input));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::modules::template::prompt_lines;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_prompt_lines() {
+        let urls = vec!["https://github.com/{org}/{repo}/pulls".to_string()];
+        let mut values = BTreeMap::new();
+        values.insert("org".to_string(), "rust-lang".to_string());
+
+        println!("Should prompt for the next placeholder...");
+        let lines = prompt_lines(&urls, &values, &["repo".to_string()], "ru");
+
+        assert_eq!(
+            lines,
+            vec![
+                "URL: https://github.com/{org}/{repo}/pulls",
+                "",
+                "org: rust-lang",
+                "repo: ru_",
+            ]
+        );
+
+        println!("Should list only templates of the bundle...");
+        let urls = vec![
+            "https://github.com/{org}".to_string(),
+            "https://one.com".to_string(),
+            "https://example.com/{user}".to_string(),
+        ];
+        let lines = prompt_lines(&urls, &values, &["user".to_string()], "");
+        assert_eq!(
+            lines,
+            vec![
+                "URL: https://github.com/{org}",
+                "URL: https://example.com/{user}",
+                "",
+                "org: rust-lang",
+                "user: _",
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::display::url_str;
+use crate::interactive::bookmarks_table::BookmarksTable;
+use crate::interactive::interface::{InputMode, SuppressedAction};
+use crate::interactive::modules::command::error_message;
+use crate::interactive::modules::{Draw, HandleInput, Module};
+use crate::interactive::widgets::rect::centered_fixed_rect;
+use bookmark_lib::types::TrashedRecord;
+use chrono::Local;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Span, Text};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use std::error::Error;
+use termion::event::Key;
+
+const DEFAULT_INFO_MESSAGE: &str =
+    "Press 'Enter' to restore selected bookmark. Press 'Esc' to close.";
+const EMPTY_TRASH_MESSAGE: &str = "Trash is empty. Press 'Esc' to close.";
+
+/// Trash displays deleted bookmarks and allows to restore them
+pub(crate) struct Trash {
+    items: Vec<TrashedRecord>,
+    state: TableState,
+    info_display: String,
+}
+
+impl Module for Trash {}
+
+impl HandleInput for Trash {
+    fn try_activate(
+        &mut self,
+        input: Key,
+        table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        if input != Key::Char('t') {
+            return Ok(None);
+        }
+
+        self.reload(table)?;
+        self.info_display = self.default_info().to_string();
+
+        Ok(Some(InputMode::Suppressed(SuppressedAction::Trash)))
+    }
+
+    fn handle_input(
+        &mut self,
+        input: Key,
+        table: &mut BookmarksTable,
+    ) -> Result<Option<InputMode>, Box<dyn Error>> {
+        match input {
+            Key::Esc | Key::Char('q') | Key::Char('t') => {
+                return Ok(Some(InputMode::Normal));
+            }
+            Key::Down => self.next(),
+            Key::Up => self.previous(),
+            Key::Char('\n') => {
+                let id = match self.state.selected() {
+                    Some(index) => self.items[index].record.id.clone(),
+                    None => return Ok(None),
+                };
+
+                match table.restore(&id) {
+                    Ok(record) => {
+                        self.reload(table)?;
+                        self.info_display = format!(
+                            "Restored '{}' to '{}' group. {}",
+                            record.name,
+                            record.group,
+                            self.default_info()
+                        );
+                    }
+                    Err(err) => self.info_display = error_message(err.as_ref()),
+                }
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+}
+
+impl Draw for Trash {
+    fn draw(&self, mode: InputMode, f: &mut Frame) {
+        if mode == InputMode::Suppressed(SuppressedAction::Trash) {
+            self.show_trash_popup(f);
+        }
+    }
+}
+
+impl Trash {
+    pub fn new() -> Trash {
+        Trash {
+            items: vec![],
+            state: TableState::default(),
+            info_display: DEFAULT_INFO_MESSAGE.to_string(),
+        }
+    }
+
+    fn reload(&mut self, table: &BookmarksTable) -> Result<(), Box<dyn Error>> {
+        self.items = table.list_trash()?;
+        // Most recently deleted bookmarks first
+        self.items.reverse();
+
+        let selected = match self.state.selected() {
+            _ if self.items.is_empty() => None,
+            Some(index) => Some(index.min(self.items.len() - 1)),
+            None => Some(0),
+        };
+        self.state.select(selected);
+
+        Ok(())
+    }
+
+    fn default_info(&self) -> &'static str {
+        if self.items.is_empty() {
+            EMPTY_TRASH_MESSAGE
+        } else {
+            DEFAULT_INFO_MESSAGE
+        }
+    }
+
+    fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.items.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i > 0 => i - 1,
+            _ => self.items.len() - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    fn show_trash_popup(&self, f: &mut Frame) {
+        let size = f.size();
+        let area = centered_fixed_rect(
+            size.width.min(100),
+            size.height.min(self.items.len() as u16 + 7),
+            size,
+        );
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Black).fg(Color::LightBlue))
+            .title(Span::styled(
+                "Trash - press ESC to close".to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+            .split(inner);
+
+        let header = Row::new(vec!["Name", "URL", "Group", "Deleted"]).style(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+        let rows = self.items.iter().map(|t| {
+            Row::new(vec![
+                t.record.name.clone(),
+                url_str(&t.record),
+                t.record.group.clone(),
+                t.deleted_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+            ])
+            .style(Style::default().fg(Color::White))
+        });
+        let widths = [
+            Constraint::Percentage(25),
+            Constraint::Percentage(40),
+            Constraint::Percentage(15),
+            Constraint::Percentage(20),
+        ];
+        let trash_table = Table::new(rows, widths)
+            .header(header)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+
+        let info = Paragraph::new(Text::raw(self.info_display.as_str()))
+            .style(Style::default().fg(Color::White));
+
+        let mut state = self.state.clone();
+
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        f.render_stateful_widget(trash_table, chunks[0], &mut state);
+        f.render_widget(info, chunks[1]);
+    }
+}
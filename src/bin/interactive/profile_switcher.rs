@@ -0,0 +1,45 @@
+use bookmark_lib::error::Error;
+use bookmark_lib::profile::Profiles;
+use bookmark_lib::Registry;
+
+/// Opens registry of the profile with given name
+pub type RegistryOpener =
+    Box<dyn Fn(&str) -> Result<Box<dyn Registry>, Box<dyn std::error::Error>>>;
+
+/// ProfileSwitcher allows to change the registry displayed in the interactive mode.
+/// Switching does not change the profile used by default.
+pub struct ProfileSwitcher {
+    profiles: Profiles,
+    current: String,
+    open: RegistryOpener,
+}
+
+impl ProfileSwitcher {
+    pub fn new(profiles: Profiles, current: &str, open: RegistryOpener) -> ProfileSwitcher {
+        ProfileSwitcher {
+            profiles,
+            current: current.to_string(),
+            open,
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.profiles.list()?)
+    }
+
+    /// Opens registry of the profile and makes it the current one
+    pub fn switch(&mut self, name: &str) -> Result<Box<dyn Registry>, Box<dyn std::error::Error>> {
+        if !self.profiles.exists(name) {
+            return Err(From::from(Error::ProfileNotFound(name.to_string())));
+        }
+
+        let registry = (self.open)(name)?;
+        self.current = name.to_string();
+
+        Ok(registry)
+    }
+}
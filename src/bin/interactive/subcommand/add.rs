@@ -14,11 +14,7 @@ impl AddData {
             name: name.to_string(),
             url: url.to_string(),
             group: group.to_string(),
-            tags: tags
-                .to_vec()
-                .into_iter()
-                .filter(|t| !t.is_empty())
-                .collect(),
+            tags: tags.iter().filter(|t| !t.is_empty()).cloned().collect(),
         }
     }
 }
@@ -40,11 +36,7 @@ pub fn interactive_add(add_data: AddData) -> Result<AddData, Box<dyn std::error:
     let tags_raw = ask_for_string("Tags", &add_data.tags.join(", "))?;
 
     // TODO: handle whitespaces better here
-    let tags: Vec<String> = tags_raw
-        .split(", ")
-        .into_iter()
-        .map(|f| f.to_string())
-        .collect();
+    let tags: Vec<String> = tags_raw.split(", ").map(|f| f.to_string()).collect();
 
     // TODO: after adding validation, add it here too
 
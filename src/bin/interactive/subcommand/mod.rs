@@ -26,7 +26,7 @@ pub(crate) fn ask_for_string(
     }
 
     print!("{}: ", input_req);
-    let _ = io::stdout().flush()?;
+    io::stdout().flush()?;
 
     let mut buffer = String::new();
     io::stdin().read_line(&mut buffer)?;
@@ -43,3 +43,46 @@ pub(crate) fn ask_for_string(
         Ok(buffer)
     }
 }
+
+pub(crate) fn confirm(req: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let answer = ask_for_string(&format!("{} [y/N]", req), "")?;
+
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+/// Asks until one of the choices is given. Empty answer selects the first choice.
+pub(crate) fn ask_for_choice(
+    req: &str,
+    choices: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let req = format!("{} [{}]", req, choices.join("/"));
+    loop {
+        let answer = ask_for_string(&req, "")?.to_lowercase();
+        if answer.is_empty() {
+            return Ok(choices[0].to_string());
+        }
+        if choices.contains(&answer.as_str()) {
+            return Ok(answer);
+        }
+        println!("Answer one of: {}", choices.join(", "));
+    }
+}
+
+/// Reads passphrase without echoing it. New passphrase has to be entered twice.
+pub(crate) fn ask_for_passphrase(new: bool) -> Result<String, Box<dyn std::error::Error>> {
+    if !new {
+        return Ok(rpassword::prompt_password("Passphrase: ")?);
+    }
+
+    loop {
+        let passphrase = rpassword::prompt_password("New passphrase: ")?;
+        if passphrase.is_empty() {
+            println!("Passphrase cannot be empty");
+            continue;
+        }
+        if rpassword::prompt_password("Repeat passphrase: ")? == passphrase {
+            return Ok(passphrase);
+        }
+        println!("Passphrases do not match");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::display::{time_str, url_str};
 use crate::interactive::table::TableItem;
 use bookmark_lib::types::URLRecord;
 
@@ -5,8 +6,11 @@ pub const DEFAULT_URL_COLS: [&str; 4] = ["Name", "URL", "Group", "Tags"];
 
 pub type Columns = Vec<String>;
 
+/// Marks names of pinned bookmarks
+const PINNED_MARKER: &str = "★ ";
+
 pub fn default_columns() -> Columns {
-    (&DEFAULT_URL_COLS)
+    DEFAULT_URL_COLS
         .iter()
         .map(|s| s.to_string())
         .collect::<Columns>()
@@ -15,6 +19,8 @@ pub fn default_columns() -> Columns {
 #[derive(Clone, Debug)]
 pub struct URLItem {
     url: URLRecord,
+    /// Path of the group, if the item stands for all bookmarks of the collapsed group
+    collapsed_group: Option<String>,
     row: Vec<String>,
 }
 
@@ -22,6 +28,7 @@ impl URLItem {
     pub fn new(record: URLRecord, cols: Option<&Columns>) -> URLItem {
         URLItem {
             url: record.clone(),
+            collapsed_group: None,
             row: url_to_row(
                 &record,
                 cols.unwrap_or(
@@ -41,9 +48,36 @@ impl URLItem {
             .collect()
     }
 
+    /// Creates the item standing for `count` bookmarks of the collapsed group
+    pub fn new_collapsed_group(path: &str, count: usize, cols: &Columns) -> URLItem {
+        let row = cols
+            .iter()
+            .map(|c| match c.trim().to_lowercase().as_str() {
+                "name" => format!("▸ {} ({})", path, count),
+                "group" => path.to_string(),
+                _ => "".to_string(),
+            })
+            .collect();
+
+        URLItem {
+            url: URLRecord::new("", "", path, Vec::<String>::new()),
+            collapsed_group: Some(path.to_string()),
+            row,
+        }
+    }
+
+    #[cfg(test)]
     pub fn url(&self) -> String {
         self.url.url.clone()
     }
+
+    pub fn group(&self) -> &str {
+        &self.url.group
+    }
+
+    pub fn collapsed_group(&self) -> Option<&str> {
+        self.collapsed_group.as_deref()
+    }
 }
 
 impl TableItem for URLItem {
@@ -51,8 +85,12 @@ impl TableItem for URLItem {
         &self.row
     }
 
+    /// Returns empty id for collapsed groups, as they do not stand for a single bookmark
     fn id(&self) -> String {
-        self.url.id.clone()
+        match self.collapsed_group {
+            Some(_) => "".to_string(),
+            None => self.url.id.clone(),
+        }
     }
 }
 
@@ -64,10 +102,15 @@ fn url_to_row(record: &URLRecord, cols: &Columns) -> Vec<String> {
 
         match col_name.as_str() {
             "id" => vals.push(record.id.clone()),
+            "name" if record.pinned => vals.push(format!("{}{}", PINNED_MARKER, record.name)),
             "name" => vals.push(record.name.clone()),
-            "url" => vals.push(record.url.clone()),
+            "keyword" => vals.push(record.keyword.clone()),
+            "url" => vals.push(url_str(record)),
             "group" => vals.push(record.group.clone()),
             "tags" => vals.push(record.tags_as_string()),
+            "created" => vals.push(time_str(record.created_at.as_ref(), "-")),
+            "updated" => vals.push(time_str(record.updated_at.as_ref(), "-")),
+            "opened" => vals.push(time_str(record.last_opened_at.as_ref(), "never")),
             _ => {}
         }
     }
@@ -119,6 +162,14 @@ mod test {
                 expected_row: to_string(vec!["name4", "url4", "group4", ""]),
                 columns: None,
             },
+            TestCase {
+                url_record: URLRecord {
+                    pinned: true,
+                    ..URLRecord::new("url6", "name6", "group6", Vec::<String>::new())
+                },
+                expected_row: to_string(vec!["★ name6", "url6", "group6", ""]),
+                columns: None,
+            },
             TestCase {
                 url_record: URLRecord::new("url5", "name5", "group5", vec!["tag", "with space"]),
                 expected_row: to_string(vec!["name5", "url5", "group5", "tag, \"with space\""]),
@@ -127,12 +178,25 @@ mod test {
         ];
 
         for item in items {
-            let table_item = URLItem::new(item.url_record, item.columns.clone());
+            let table_item = URLItem::new(item.url_record, item.columns);
             let row = table_item.row();
             assert_eq!(&item.expected_row, row);
         }
     }
 
+    #[test]
+    fn test_collapsed_group_item() {
+        let cols = to_string(vec!["ID", "Name", "URL", "Group", "Tags"]);
+
+        let item = URLItem::new_collapsed_group("work/infra", 3, &cols);
+        assert_eq!(
+            item.row(),
+            &to_string(vec!["", "▸ work/infra (3)", "", "work/infra", ""])
+        );
+        assert_eq!(item.id(), "");
+        assert_eq!(item.collapsed_group(), Some("work/infra"));
+    }
+
     #[test]
     fn test_default_columns() {
         let def_cols = default_columns();
@@ -1,35 +1,171 @@
 extern crate clap;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
 use clap::{Arg, ArgAction, ArgMatches, Command};
 
 use crate::interactive::interactive_mode::enter_interactive_mode;
-use crate::interactive::subcommand::add;
+use crate::interactive::profile_switcher::{ProfileSwitcher, RegistryOpener};
+use crate::interactive::subcommand::{add, ask_for_choice, ask_for_passphrase, confirm};
 
+use bookmark_lib::backup::{BackupPolicy, DEFAULT_KEEP_DAILY, DEFAULT_KEEP_LAST};
+use bookmark_lib::diff::diff;
+use bookmark_lib::encrypted::EncryptedStorage;
+use bookmark_lib::error::Error;
+use bookmark_lib::format::Format;
+use bookmark_lib::merge::{merge_records, Side};
+use bookmark_lib::migration::load_registry;
+use bookmark_lib::profile::{Profiles, DEFAULT_PROFILE};
 use bookmark_lib::registry::{URLRegistry, DEFAULT_GROUP};
+use bookmark_lib::sqlite::SqliteStorage;
 use bookmark_lib::storage::FileStorage;
-use bookmark_lib::Registry;
+use bookmark_lib::sync::{GitSync, Pull, DEFAULT_REMOTE};
+use bookmark_lib::template;
+use bookmark_lib::types::URLRecord;
+use bookmark_lib::{Registry, RegistryReader};
 
 use bookmark_lib::filters::{Filter, GroupFilter, NoopFilter, TagsFilter};
+use bookmark_lib::groups;
 use bookmark_lib::sort::{SortBy, SortConfig};
+use std::fs;
 use std::str::FromStr;
 
 mod cmd;
 mod display;
+mod editor;
 mod interactive;
 
 const URLS_V0_0_X_DEFAULT_FILE_PATH: &str = ".bookmark-cli/urls.json";
 
-const URLS_DEFAULT_FILE_PATH: &str = ".bookmark/urls_v0.1.json";
+const CONFIG_DIR: &str = ".bookmark";
 
-const VERSION_V0_0_X: &str = " v0.0.x";
+const URLS_FILE_NAME: &str = "urls_v0.1.json";
+
+const URLS_SQLITE_FILE_NAME: &str = "urls_v0.1.db";
+
+const URLS_ENCRYPTED_FILE_NAME: &str = "urls_v0.1.enc";
+
+const PASSPHRASE_ENV: &str = "BOOKMARK_PASSPHRASE";
+
+const STORAGE_FILE: &str = "file";
+const STORAGE_SQLITE: &str = "sqlite";
+const STORAGE_ENCRYPTED: &str = "encrypted";
 
 fn main() {
     let urls_v0_0_x_default_full_path = path_with_homedir(URLS_V0_0_X_DEFAULT_FILE_PATH)
         .expect("Failed to get default v0_0_x path");
 
-    let cmd = Command::new("Bookmark")
+    let mut cmd = build_cli(urls_v0_0_x_default_full_path);
+    let matches = get_matches(&mut cmd, std::env::args_os()).unwrap_or_else(|e| e.exit());
+
+    let profiles = Profiles::new(path_with_homedir(CONFIG_DIR).expect("Failed to get config dir"));
+    if let Some((cmd::PROFILE_SUB_CMD, profile_matches)) = matches.subcommand() {
+        profile_sub_cmd(&profiles, profile_matches);
+        return;
+    }
+
+    let profile = match get_profile(&matches, &profiles) {
+        Ok(profile) => profile,
+        Err(Error::ProfileNotFound(name)) => {
+            println!(
+                "Error: profile '{}' does not exist, create it with `bookmark profile create {}`",
+                name, name
+            );
+            return;
+        }
+        Err(why) => {
+            println!("Error: failed to get profile: {}", why);
+            return;
+        }
+    };
+
+    let storage = matches
+        .get_one::<String>("storage")
+        .map(|s| s.as_str())
+        .unwrap_or(STORAGE_FILE);
+    let backup_policy = get_backup_policy(&matches);
+    let format = get_format(&matches);
+
+    match storage {
+        STORAGE_SQLITE => {
+            let file_path =
+                get_registry_file_path(&matches, &profiles, &profile, URLS_SQLITE_FILE_NAME);
+            let opener: RegistryOpener = Box::new(move |profile| {
+                let file_path = profile_file_path(profile, URLS_SQLITE_FILE_NAME)?;
+                Ok(Box::new(URLRegistry::new_sqlite(&file_path)?))
+            });
+
+            match Application::new_sqlite_registry(&file_path) {
+                Ok(application) => run(application, &matches, profile_switcher(profile, opener)),
+                Err(why) => println!(
+                    "Error: failed to open SQLite registry '{}': {}",
+                    file_path, why
+                ),
+            }
+        }
+        STORAGE_ENCRYPTED => {
+            let file_path =
+                get_registry_file_path(&matches, &profiles, &profile, URLS_ENCRYPTED_FILE_NAME);
+            let passphrase = match get_passphrase(&file_path) {
+                Ok(passphrase) => passphrase,
+                Err(why) => {
+                    println!("Error: failed to read passphrase: {}", why);
+                    return;
+                }
+            };
+            let application = Application::new_encrypted_registry(&file_path, &passphrase);
+            // Registries of other profiles are opened with the same passphrase
+            let opener: RegistryOpener = Box::new(move |profile| {
+                let file_path = profile_file_path(profile, URLS_ENCRYPTED_FILE_NAME)?;
+                Ok(Box::new(URLRegistry::new_encrypted(
+                    &file_path,
+                    &passphrase,
+                )?))
+            });
+
+            match application {
+                Ok(application) => run(application, &matches, profile_switcher(profile, opener)),
+                Err(Error::InvalidPassphrase) => println!(
+                    "Error: failed to decrypt registry '{}', check the passphrase",
+                    file_path
+                ),
+                Err(why) => println!(
+                    "Error: failed to open encrypted registry '{}': {}",
+                    file_path, why
+                ),
+            }
+        }
+        _ => {
+            let file_path = get_registry_file_path(&matches, &profiles, &profile, URLS_FILE_NAME);
+            let application =
+                Application::new_file_based_registry(file_path.clone(), backup_policy, format);
+            let opener: RegistryOpener = Box::new(move |profile| {
+                let file_path = profile_file_path(profile, URLS_FILE_NAME)?;
+                Ok(Box::new(file_based_registry(
+                    file_path,
+                    backup_policy,
+                    format,
+                )))
+            });
+
+            match matches.subcommand() {
+                Some((cmd::BACKUP_SUB_CMD, backup_matches)) => {
+                    application.backup_sub_cmd(backup_matches)
+                }
+                Some((cmd::SYNC_SUB_CMD, sync_matches)) => sync_sub_cmd(&file_path, sync_matches),
+                _ => run(application, &matches, profile_switcher(profile, opener)),
+            }
+        }
+    }
+}
+
+fn build_cli(urls_v0_0_x_default_full_path: String) -> Command {
+    Command::new("Bookmark")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Szymon Gibała <szumongib@gmail.com>")
         .about("Group, tag and quickly access your URLs from terminal")
+        // Unknown subcommands are keywords of bookmarks to open, e.g. `bookmark gh`
+        .allow_external_subcommands(true)
+        .external_subcommand_value_parser(clap::value_parser!(String))
         .arg(Arg::new("file")
             .short('f')
             .long("file")
@@ -38,10 +174,79 @@ fn main() {
             .help("Path to file storing the URLs")
             .action(ArgAction::Set)
         )
+        .arg(Arg::new("profile")
+            .short('p')
+            .long("profile")
+            .value_name("PROFILE")
+            .env("BOOKMARK_PROFILE")
+            .required(false)
+            .help("Profile which bookmarks should be used. Defaults to the profile set with `profile use`")
+            .action(ArgAction::Set)
+        )
+        .arg(Arg::new("storage")
+            .long("storage")
+            .value_name("STORAGE")
+            .required(false)
+            .help(format!(
+                "Storage backend for the URLs. One of: {}, {}, {}. Passphrase of the encrypted storage is read from the {} environment variable or prompted for",
+                STORAGE_FILE, STORAGE_SQLITE, STORAGE_ENCRYPTED, PASSPHRASE_ENV
+            ))
+            .value_parser([STORAGE_FILE, STORAGE_SQLITE, STORAGE_ENCRYPTED])
+            .default_value(STORAGE_FILE)
+            .action(ArgAction::Set)
+        )
+        .arg(Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .env("BOOKMARK_FORMAT")
+            .required(false)
+            .help(format!(
+                "Format in which the file storage is written. One of: {}. By default it is detected from the file header or extension and the file is written in the same format",
+                Format::names().join(", ")
+            ))
+            .value_parser(Format::names())
+            .action(ArgAction::Set)
+        )
+        .arg(Arg::new("backups")
+            .long("backups")
+            .value_name("N")
+            .env("BOOKMARK_BACKUPS")
+            .required(false)
+            .help(format!(
+                "Number of the most recent snapshots of the registry file to keep. Backups are disabled unless this or --daily-backups is set, e.g. --backups {}",
+                DEFAULT_KEEP_LAST
+            ))
+            .value_parser(clap::value_parser!(usize))
+            .action(ArgAction::Set)
+        )
+        .arg(Arg::new("daily-backups")
+            .long("daily-backups")
+            .value_name("N")
+            .env("BOOKMARK_DAILY_BACKUPS")
+            .required(false)
+            .help(format!(
+                "Number of days for which the first snapshot of the registry file of the day is kept, e.g. --daily-backups {}",
+                DEFAULT_KEEP_DAILY
+            ))
+            .value_parser(clap::value_parser!(usize))
+            .action(ArgAction::Set)
+        )
         .subcommand(Command::new(cmd::GROUP_SUB_CMD)
             .about("Manage URL groups")
             .subcommand(Command::new(cmd::GROUP_LIST_CMD)
-                .about("List groups")
+                .about("List groups as a tree of nested groups")
+            )
+            .subcommand(Command::new(cmd::GROUP_MOVE_CMD)
+                .about("Move or rename group together with all groups nested in it")
+                .alias(cmd::GROUP_MOVE_CMD_ALIAS)
+                .arg(Arg::new("from")
+                    .help("Group to move, e.g. 'work/infra'")
+                    .required(true)
+                    .index(1))
+                .arg(Arg::new("to")
+                    .help("New group path, e.g. 'ops'")
+                    .required(true)
+                    .index(2))
             )
         )
         .subcommand(Command::new(cmd::ADD_SUB_CMD)
@@ -55,7 +260,7 @@ fn main() {
                 .index(2)
             )
             .arg(Arg::new("tag")
-                .help("URL tags. Accepts multiple values: url add [NAME] [URL] -t tag1 -t tag2")
+                .help("URL tags, either plain or in the key=value format. Accepts multiple values: url add [NAME] [URL] -t tag1 -t env=prod")
                 .required(false)
                 .short('t')
                 .long("tag")
@@ -64,7 +269,45 @@ fn main() {
                 // TODO: add validator to exclude forbidden chars (like ,)
             )
             .arg(Arg::new("group")
-                .help("Group to which URL should be assigned")
+                .help("Group to which URL should be assigned. Nested groups are separated with '/', e.g. work/infra")
+                .required(false)
+                .action(ArgAction::Set)
+                .short('g')
+                .long("group"))
+            .arg(Arg::new("note")
+                .help("Notes about the bookmark. Accepts multiple values, which become separate lines: url add [NAME] [URL] --note line1 --note line2")
+                .required(false)
+                .short('n')
+                .long("note")
+                .action(ArgAction::Append)
+                .number_of_values(1))
+            .arg(Arg::new("keyword")
+                .help("Unique keyword with which the bookmark can be opened: bookmark [KEYWORD]")
+                .required(false)
+                .short('k')
+                .long("keyword")
+                .action(ArgAction::Set))
+        )
+        .subcommand(Command::new(cmd::BUNDLE_SUB_CMD)
+            .about("Add bundle opening several URLs at once")
+            .arg(Arg::new("name")
+                .help("Bundle name")
+                .required(true)
+                .index(1))
+            .arg(Arg::new("members")
+                .help("URLs with the scheme, e.g. https://, or ids of bookmarks opened together, in order: bookmark bundle [NAME] https://one.com [ID]")
+                .required(true)
+                .num_args(1..)
+                .index(2))
+            .arg(Arg::new("tag")
+                .help("Bundle tags, either plain or in the key=value format. Accepts multiple values: bookmark bundle [NAME] [MEMBERS]... -t tag1 -t env=prod")
+                .required(false)
+                .short('t')
+                .long("tag")
+                .action(ArgAction::Append)
+                .number_of_values(1))
+            .arg(Arg::new("group")
+                .help("Group to which bundle should be assigned. Nested groups are separated with '/', e.g. work/infra")
                 .required(false)
                 .action(ArgAction::Set)
                 .short('g')
@@ -74,24 +317,67 @@ fn main() {
             .alias("ls")
             .about("List bookmarks ")
             .arg(Arg::new("group") // If not specified use default or global
-                .help("Group from which URLs should be listed")
+                .help("Group from which URLs should be listed, including groups nested in it")
                 .required(false)
                 .action(ArgAction::Set)
                 .short('g')
                 .long("group"))
             .arg(Arg::new("tag")
-                .help("URL tags. Accepts multiple values: url add [NAME] [URL] -t tag1 -t tag2")
+                .help("URL tags. Lists URLs with any of the tags, matched by key, key=value or key=* for any value: url ls -t tag1 -t env=*")
                 .required(false)
                 .short('t')
                 .long("tag")
                 .action(ArgAction::Append)
                 .number_of_values(1))
             .arg(Arg::new("sort")
-                .help("Specifies to sort bookmarks by one of the columns: [name, url, group]")
+                .help("Specifies to sort bookmarks by one of the columns: [name, url, group, created, updated, opened, frecency]")
                 .required(false)
                 .long("sort")
                 .action(ArgAction::Set)
                 .number_of_values(1))
+            .arg(Arg::new("top")
+                .help("Lists only N most used bookmarks, ranked by frecency")
+                .required(false)
+                .long("top")
+                .value_name("N")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("sort"))
+            .arg(Arg::new("dates")
+                .help("Show when bookmarks were created, updated and last opened")
+                .required(false)
+                .long("dates")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("keywords")
+                .help("Show keywords of bookmarks")
+                .required(false)
+                .long("keywords")
+                .action(ArgAction::SetTrue))
+        )
+        .subcommand(Command::new(cmd::NOTES_SUB_CMD)
+            .about("Edit notes of the bookmark in the editor from $VISUAL or $EDITOR")
+            .arg(Arg::new("id")
+                .help("Bookmark id")
+                .required(true)
+                .index(1))
+            .arg(Arg::new("show")
+                .help("Print notes instead of editing them")
+                .required(false)
+                .long("show")
+                .action(ArgAction::SetTrue))
+        )
+        .subcommand(Command::new(cmd::OPEN_SUB_CMD)
+            .about("Open bookmark in the browser")
+            .arg(Arg::new("id")
+                .help("Id or keyword of the bookmark to open")
+                .required(true)
+                .index(1)
+            )
+            .arg(Arg::new("values")
+                .help("Values of the placeholders if the URL is a template, e.g. https://jira.example.com/browse/{ticket}. Either named, e.g. ticket=ABC-123, or positional")
+                .required(false)
+                .index(2)
+                .action(ArgAction::Append))
         )
         .subcommand(Command::new(cmd::DELETE_SUB_CMD)
             .about("Delete bookmark")
@@ -110,7 +396,7 @@ fn main() {
                 .required(true)
                 .index(1))
             .arg(Arg::new("tag")
-                .help("Tag to add")
+                .help("Tag to add, either plain or in the key=value format, which replaces the value of the existing tag")
                 .required(true)
                 .index(2)
             )
@@ -123,11 +409,41 @@ fn main() {
                 .required(true)
                 .index(1))
             .arg(Arg::new("tag")
-                .help("Tag to remove")
+                .help("Tag to remove. Tag in the key=value format is removed only if it has the value")
                 .required(true)
                 .index(2)
             )
         )
+        .subcommand(Command::new(cmd::PIN_SUB_CMD)
+            .about("Pin bookmark, so that it is listed before all others, whatever the order")
+            .arg(Arg::new("id")
+                .help("Bookmark id to pin")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(Command::new(cmd::UNPIN_SUB_CMD)
+            .about("Unpin bookmark")
+            .arg(Arg::new("id")
+                .help("Bookmark id to unpin")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(Command::new(cmd::KEYWORD_SUB_CMD)
+            .about("Set keyword with which the bookmark can be opened: bookmark [KEYWORD]")
+            .arg(Arg::new("id")
+                .help("Bookmark id")
+                .required(true)
+                .index(1))
+            .arg(Arg::new("keyword")
+                .help("Keyword unique among all bookmarks, e.g. 'gh'")
+                .required_unless_present("remove")
+                .index(2))
+            .arg(Arg::new("remove")
+                .help("Remove keyword of the bookmark")
+                .long("remove")
+                .conflicts_with("keyword")
+                .action(ArgAction::SetTrue))
+        )
         .subcommand(Command::new(cmd::CHANGE_GROUP_SUB_CMD)
             .about("Change group of the bookmark")
             // .usage("bookmark chg [ID] [GROUP]")
@@ -170,37 +486,180 @@ fn main() {
                 .index(2)
             )
         )
+        .subcommand(Command::new(cmd::UNDO_SUB_CMD)
+            .about("Revert the last change of bookmarks")
+        )
+        .subcommand(Command::new(cmd::REDO_SUB_CMD)
+            .about("Apply again the last reverted change of bookmarks")
+        )
+        .subcommand(Command::new(cmd::BACKUP_SUB_CMD)
+            .about("Manage snapshots of the registry file taken before every change")
+            .subcommand(Command::new(cmd::BACKUP_LIST_CMD)
+                .about("List snapshots together with the changes restoring them would apply")
+            )
+            .subcommand(Command::new(cmd::BACKUP_RESTORE_CMD)
+                .about("Restore the registry from the snapshot")
+                .arg(Arg::new("snapshot")
+                    .help("Snapshot to restore")
+                    .required(true)
+                    .index(1))
+                .arg(Arg::new("dry-run")
+                    .help("Only show the changes without restoring the snapshot")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue))
+                .arg(Arg::new("yes")
+                    .help("Restore without asking for confirmation")
+                    .short('y')
+                    .long("yes")
+                    .action(ArgAction::SetTrue))
+            )
+        )
+        .subcommand(Command::new(cmd::MERGE_SUB_CMD)
+            .about("Merge bookmarks from the other registry file, matching them by ids")
+            .arg(Arg::new("other-file")
+                .help("Registry file to merge")
+                .required(true)
+                .index(1))
+            .arg(Arg::new("base")
+                .help("Common ancestor of both registries. Without it, changes cannot be told apart from the original values and bookmarks are only added")
+                .long("base")
+                .value_name("ANCESTOR")
+                .action(ArgAction::Set))
+            .arg(Arg::new("resolve")
+                .help("Resolve conflicts keeping the version from the given side instead of asking")
+                .long("resolve")
+                .value_parser(["ours", "theirs"])
+                .action(ArgAction::Set))
+            .arg(Arg::new("json")
+                .help("Print changes and conflicts as JSON. Unless conflicts are resolved with --resolve, changes are applied only if there are no conflicts")
+                .long("json")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("dry-run")
+                .help("Only show the changes without applying them")
+                .long("dry-run")
+                .action(ArgAction::SetTrue))
+        )
+        .subcommand(Command::new(cmd::SYNC_SUB_CMD)
+            .about("Commit changes of bookmarks, pull and push them using the git repository containing the registry file")
+            .arg(Arg::new("remote")
+                .long("remote")
+                .value_name("REMOTE")
+                .env("BOOKMARK_SYNC_REMOTE")
+                .help("Name of the git remote to synchronize with")
+                .default_value(DEFAULT_REMOTE)
+                .action(ArgAction::Set))
+            .subcommand(Command::new(cmd::SYNC_INIT_CMD)
+                .about("Initialize the git repository in the registry directory and commit every change of bookmarks")
+                .arg(Arg::new("url")
+                    .help("URL of the remote repository")
+                    .required(false)
+                    .index(1))
+            )
+        )
+        .subcommand(Command::new(cmd::PROFILE_SUB_CMD)
+            .about("Manage profiles, each having separate bookmarks")
+            .subcommand(Command::new(cmd::PROFILE_LIST_CMD)
+                .about("List profiles, marking the one used by default")
+            )
+            .subcommand(Command::new(cmd::PROFILE_CREATE_CMD)
+                .about("Create profile")
+                .arg(Arg::new("name")
+                    .help("Profile name. Only letters, digits, '-' and '_' are allowed")
+                    .required(true)
+                    .index(1))
+            )
+            .subcommand(Command::new(cmd::PROFILE_DELETE_CMD)
+                .about("Delete profile together with all its bookmarks")
+                .arg(Arg::new("name")
+                    .help("Profile to delete")
+                    .required(true)
+                    .index(1))
+                .arg(Arg::new("yes")
+                    .help("Delete without asking for confirmation")
+                    .short('y')
+                    .long("yes")
+                    .action(ArgAction::SetTrue))
+            )
+            .subcommand(Command::new(cmd::PROFILE_USE_CMD)
+                .about("Set profile used by default")
+                .arg(Arg::new("name")
+                    .help("Profile to use")
+                    .required(true)
+                    .index(1))
+            )
+        )
+        .subcommand(Command::new(cmd::TRASH_SUB_CMD)
+            .about("Manage deleted bookmarks")
+            .subcommand(Command::new(cmd::TRASH_LIST_CMD)
+                .about("List bookmarks in the trash")
+            )
+            .subcommand(Command::new(cmd::TRASH_RESTORE_CMD)
+                .about("Restore bookmark from the trash")
+                .arg(Arg::new("id")
+                    .help("Bookmark id to restore")
+                    .required(true)
+                    .index(1))
+            )
+            .subcommand(Command::new(cmd::TRASH_PURGE_CMD)
+                .about("Permanently remove bookmarks from the trash")
+                .arg(Arg::new("older-than")
+                    .help("Remove only bookmarks deleted earlier than the duration ago, e.g. 30d. Supported units: m, h, d, w")
+                    .long("older-than")
+                    .value_name("DURATION")
+                    .value_parser(cmd::parse_duration)
+                    .action(ArgAction::Set))
+                .arg(Arg::new("yes")
+                    .help("Purge without asking for confirmation")
+                    .short('y')
+                    .long("yes")
+                    .action(ArgAction::SetTrue))
+            )
+        )
+        .subcommand(Command::new(cmd::DOCTOR_SUB_CMD)
+            .about("Check bookmarks for problems such as duplicated ids, empty names, clashing names, malformed URLs or tags")
+            .arg(Arg::new("fix")
+                .help("Repair problems that can be fixed automatically. The repair can be reverted with `undo`")
+                .long("fix")
+                .action(ArgAction::SetTrue))
+        )
         // TODO: I think I can drop it at this point
         .subcommand(Command::new(cmd::IMPORT_SUB_CMD)
-            .about("Imports bookmarks from the previous versions")
-            .arg(Arg::new("version")
-                .help(format!("Version from which URLs should be imported. One of: {}", VERSION_V0_0_X))
-                .required(false)
-                .action(ArgAction::Set)
-                .short('v')
-                .long("version")
-                .default_value(VERSION_V0_0_X))
+            .about("Imports bookmarks from the file of any previous version")
             .arg(Arg::new("old-file")
-                .help("Path to the file storing URLs from previous version")
+                .help("Path to the file storing URLs. Defaults to the v0.0.x file")
                 .required(false)
                 .action(ArgAction::Set)
                 .long("old-file")
                 .default_value(urls_v0_0_x_default_full_path)
             )
-        );
-
-    let matches = cmd.get_matches();
+        )
+}
 
-    let file_path = match matches.get_one::<String>("file") {
-        Some(t) => t.to_string(),
-        None => match get_default_registry_file_path() {
-            Some(path) => path,
-            None => panic!("Failed to get default file path"),
-        },
-    };
+/// Parses the arguments. The file conflicts with the profile only if the profile is given
+/// on the command line, as the one set with `BOOKMARK_PROFILE` serves as a default.
+fn get_matches<I, T>(cmd: &mut Command, args: I) -> Result<ArgMatches, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let matches = cmd.try_get_matches_from_mut(args)?;
+    if matches.get_one::<String>("file").is_some()
+        && matches.value_source("profile") == Some(ValueSource::CommandLine)
+    {
+        return Err(cmd.error(
+            ErrorKind::ArgumentConflict,
+            "the argument '--file <FILE>' cannot be used with '--profile <PROFILE>'",
+        ));
+    }
 
-    let application = Application::new_file_based_registry(file_path);
+    Ok(matches)
+}
 
+fn run<T: Registry + 'static>(
+    application: Application<T>,
+    matches: &ArgMatches,
+    profiles: Option<ProfileSwitcher>,
+) {
     match matches.subcommand() {
         Some((cmd::GROUP_SUB_CMD, group_matches)) => {
             application.group_sub_cmd(group_matches);
@@ -208,21 +667,45 @@ fn main() {
         Some((cmd::ADD_SUB_CMD, add_matches)) => {
             application.add_sub_cmd(add_matches);
         }
+        Some((cmd::BUNDLE_SUB_CMD, bundle_matches)) => {
+            application.bundle_sub_cmd(bundle_matches);
+        }
         Some((cmd::LIST_SUB_CMD, list_matches)) => {
             application.list_sub_cmd(list_matches);
         }
         Some((cmd::DELETE_SUB_CMD, delete_matches)) => {
             application.delete_sub_cmd(delete_matches);
         }
+        Some((cmd::OPEN_SUB_CMD, open_matches)) => {
+            application.open_sub_cmd(open_matches);
+        }
+        Some((cmd::NOTES_SUB_CMD, notes_matches)) => {
+            application.notes_sub_cmd(notes_matches);
+        }
         Some((cmd::IMPORT_SUB_CMD, import_matches)) => {
             application.import_sub_cmd(import_matches);
         }
+        Some((cmd::MERGE_SUB_CMD, merge_matches)) => {
+            application.merge_sub_cmd(merge_matches);
+        }
+        Some((cmd::DOCTOR_SUB_CMD, doctor_matches)) => {
+            application.doctor_sub_cmd(doctor_matches);
+        }
         Some((cmd::TAG_SUB_CMD, tag_matches)) => {
             application.tag_sub_cmd(tag_matches);
         }
         Some((cmd::UNTAG_SUB_CMD, untag_matches)) => {
             application.untag_sub_cmd(untag_matches);
         }
+        Some((cmd::PIN_SUB_CMD, pin_matches)) => {
+            application.pin_sub_cmd(pin_matches, true);
+        }
+        Some((cmd::UNPIN_SUB_CMD, unpin_matches)) => {
+            application.pin_sub_cmd(unpin_matches, false);
+        }
+        Some((cmd::KEYWORD_SUB_CMD, keyword_matches)) => {
+            application.keyword_sub_cmd(keyword_matches);
+        }
         Some((cmd::CHANGE_GROUP_SUB_CMD, chg_matches)) => {
             application.change_group_sub_cmd(chg_matches);
         }
@@ -232,20 +715,88 @@ fn main() {
         Some((cmd::CHANGE_URL_SUB_CMD, chu_matches)) => {
             application.change_url_sub_cmd(chu_matches);
         }
+        Some((cmd::UNDO_SUB_CMD, undo_matches)) => {
+            application.undo_sub_cmd(undo_matches);
+        }
+        Some((cmd::REDO_SUB_CMD, redo_matches)) => {
+            application.redo_sub_cmd(redo_matches);
+        }
+        Some((cmd::TRASH_SUB_CMD, trash_matches)) => {
+            application.trash_sub_cmd(trash_matches);
+        }
+        Some((cmd::BACKUP_SUB_CMD, _)) => {
+            println!("Error: backups are supported only by the file storage")
+        }
+        Some((cmd::SYNC_SUB_CMD, _)) => {
+            println!("Error: sync is supported only by the file storage")
+        }
         None => {
-            if let Err(err) = enter_interactive_mode(application.registry) {
-                println!(
-                    "Error: failed to enter interactive mode: {}",
-                    err.to_string()
-                )
+            if let Err(err) = enter_interactive_mode(application.registry, profiles) {
+                println!("Error: failed to enter interactive mode: {}", err)
             };
         }
-        _ => println!("Error: subcommand not found"),
+        Some((keyword, values_matches)) => {
+            // Values of the external subcommand are kept under the empty name
+            let values = get_multiple_values(values_matches, "").unwrap_or_default();
+            application.open(keyword, &values);
+        }
+    }
+}
+
+/// Reads bookmarks from the registry file of any known schema version
+fn read_records(path: &str) -> Result<Vec<URLRecord>, Error> {
+    let content = fs::read_to_string(path)?;
+    Ok(load_registry(&content, Format::detect(path, &content))?
+        .urls
+        .items)
+}
+
+/// Returns the profile whose bookmarks should be used, or None if the file is specified directly
+fn get_profile(matches: &ArgMatches, profiles: &Profiles) -> Result<Option<String>, Error> {
+    if matches.contains_id("file") && matches.get_one::<String>("file").is_some() {
+        return Ok(None);
+    }
+
+    let profile = match matches.get_one::<String>("profile") {
+        Some(profile) => profile.to_string(),
+        None => profiles.current()?,
+    };
+    if !profiles.exists(&profile) {
+        return Err(Error::ProfileNotFound(profile));
     }
+
+    Ok(Some(profile))
 }
 
-fn get_default_registry_file_path() -> Option<String> {
-    path_with_homedir(URLS_DEFAULT_FILE_PATH)
+fn get_registry_file_path(
+    matches: &ArgMatches,
+    profiles: &Profiles,
+    profile: &Option<String>,
+    file_name: &str,
+) -> String {
+    if let Some(path) = matches.get_one::<String>("file") {
+        return path.to_string();
+    }
+
+    let profile = profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+    match profiles.registry_path(profile, file_name) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(why) => panic!("Failed to get file path of profile '{}': {}", profile, why),
+    }
+}
+
+fn profile_file_path(profile: &str, file_name: &str) -> Result<String, Error> {
+    let config_dir = path_with_homedir(CONFIG_DIR)
+        .ok_or_else(|| Error::Storage("failed to get config dir".to_string()))?;
+    let path = Profiles::new(config_dir).registry_path(profile, file_name)?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Profiles can be switched in the interactive mode only if the file was not specified directly
+fn profile_switcher(profile: Option<String>, opener: RegistryOpener) -> Option<ProfileSwitcher> {
+    let config_dir = path_with_homedir(CONFIG_DIR)?;
+    profile.map(|p| ProfileSwitcher::new(Profiles::new(config_dir), &p, opener))
 }
 
 fn path_with_homedir(path: &str) -> Option<String> {
@@ -255,34 +806,348 @@ fn path_with_homedir(path: &str) -> Option<String> {
     }
 }
 
+/// Takes passphrase from the environment variable or asks for it.
+/// If the registry does not exist yet, the passphrase has to be confirmed.
+fn get_passphrase(file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => return Ok(passphrase),
+        _ => {}
+    }
+
+    ask_for_passphrase(!std::path::Path::new(file_path).exists())
+}
+
+fn get_format(matches: &ArgMatches) -> Option<Format> {
+    matches
+        .get_one::<String>("format")
+        .and_then(|f| Format::from_str(f).ok())
+}
+
+/// Backups are opt-in, so that commands do not write snapshots unless asked to
+fn get_backup_policy(matches: &ArgMatches) -> BackupPolicy {
+    BackupPolicy::new(
+        *matches.get_one::<usize>("backups").unwrap_or(&0),
+        *matches.get_one::<usize>("daily-backups").unwrap_or(&0),
+    )
+}
+
+fn profile_sub_cmd(profiles: &Profiles, matches: &ArgMatches) {
+    match matches.subcommand() {
+        Some((cmd::PROFILE_LIST_CMD, _)) => {
+            let current = profiles
+                .current()
+                .unwrap_or_else(|_| DEFAULT_PROFILE.to_string());
+            match profiles.list() {
+                Ok(list) => {
+                    for p in list {
+                        let marker = if p == current { "*" } else { " " };
+                        println!("{} {}", marker, p);
+                    }
+                }
+                Err(why) => println!("Error: failed to list profiles: {}", why),
+            }
+        }
+        Some((cmd::PROFILE_CREATE_CMD, create_matches)) => {
+            let name = create_matches
+                .get_one::<String>("name")
+                .expect("Error: profile name not provided");
+            match profiles.create(name) {
+                Ok(_) => println!("Profile '{}' created", name),
+                Err(why) => println!("Error: failed to create profile: {}", why),
+            }
+        }
+        Some((cmd::PROFILE_DELETE_CMD, delete_matches)) => {
+            let name = delete_matches
+                .get_one::<String>("name")
+                .expect("Error: profile name not provided");
+            if name == DEFAULT_PROFILE {
+                println!("Error: default profile cannot be deleted");
+                return;
+            }
+            if !profiles.exists(name) {
+                println!("Error: profile '{}' not found", name);
+                return;
+            }
+            if !delete_matches.get_flag("yes") {
+                let request = format!("Delete profile '{}' together with all its bookmarks?", name);
+                match confirm(&request) {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(why) => {
+                        println!("Error: failed to read confirmation: {}", why);
+                        return;
+                    }
+                }
+            }
+            match profiles.delete(name) {
+                Ok(_) => println!("Profile '{}' deleted", name),
+                Err(why) => println!("Error: failed to delete profile: {}", why),
+            }
+        }
+        Some((cmd::PROFILE_USE_CMD, use_matches)) => {
+            let name = use_matches
+                .get_one::<String>("name")
+                .expect("Error: profile name not provided");
+            match profiles.set_current(name) {
+                Ok(_) => println!("Using profile '{}'", name),
+                Err(why) => println!("Error: failed to use profile: {}", why),
+            }
+        }
+        _ => println!("Error: profile subcommand not found"),
+    }
+}
+
+/// Opens the registry file. If it is kept in the git repository with enabled commits,
+/// every change is committed.
+fn file_based_registry(
+    file_path: String,
+    backup_policy: BackupPolicy,
+    format: Option<Format>,
+) -> URLRegistry<FileStorage> {
+    let git = match GitSync::discover(&file_path) {
+        Ok(Some(git)) if git.autocommit().unwrap_or(false) => Some(git),
+        _ => None,
+    };
+
+    let mut registry = URLRegistry::new_file_based(file_path).with_backups(backup_policy);
+    if let Some(format) = format {
+        registry = registry.with_format(format);
+    }
+    match git {
+        Some(git) => registry.with_git(git),
+        None => registry,
+    }
+}
+
+fn sync_sub_cmd(file_path: &str, matches: &ArgMatches) {
+    let remote = matches
+        .get_one::<String>("remote")
+        .map(|r| r.as_str())
+        .unwrap_or(DEFAULT_REMOTE);
+
+    if let Some((cmd::SYNC_INIT_CMD, init_matches)) = matches.subcommand() {
+        let git = match GitSync::init(file_path) {
+            Ok(git) => git.with_remote(remote),
+            Err(why) => {
+                println!("Error: failed to initialize git repository: {}", why);
+                return;
+            }
+        };
+        if let Some(url) = init_matches.get_one::<String>("url") {
+            if let Err(why) = git.set_remote_url(url) {
+                println!("Error: failed to set URL of remote '{}': {}", remote, why);
+                return;
+            }
+        }
+        println!(
+            "Changes of '{}' will be committed to the git repository",
+            file_path
+        );
+        return;
+    }
+
+    let git = match GitSync::discover(file_path) {
+        Ok(Some(git)) => git.with_remote(remote),
+        Ok(None) => {
+            println!(
+                "Error: registry '{}' is not in the git repository, run `bookmark sync init [URL]` first",
+                file_path
+            );
+            return;
+        }
+        Err(why) => {
+            println!("Error: failed to find git repository: {}", why);
+            return;
+        }
+    };
+
+    let report = match git.sync() {
+        Ok(report) => report,
+        Err(why) => {
+            println!("Error: failed to sync bookmarks: {}", why);
+            return;
+        }
+    };
+
+    match report.pull {
+        Pull::FastForward => println!("Pulled changes from '{}'", remote),
+        Pull::Merge => println!("Merged changes from '{}'", remote),
+        Pull::UpToDate | Pull::NoRemoteBranch => {}
+    }
+    if report.pushed {
+        println!("Pushed changes to '{}'", remote);
+    }
+    if !report.pushed && matches!(report.pull, Pull::UpToDate | Pull::NoRemoteBranch) {
+        println!("Bookmarks are up to date");
+    }
+    if !report.conflicts.is_empty() {
+        println!("Conflicting changes, kept local versions:");
+        display::display_conflicts(&report.conflicts);
+    }
+}
+
 struct Application<T: Registry> {
     registry: T,
 }
 
 impl Application<URLRegistry<FileStorage>> {
-    pub fn new_file_based_registry(file_path: String) -> Application<URLRegistry<FileStorage>> {
+    pub fn new_file_based_registry(
+        file_path: String,
+        backup_policy: BackupPolicy,
+        format: Option<Format>,
+    ) -> Application<URLRegistry<FileStorage>> {
         Application {
-            registry: URLRegistry::new_file_based(file_path),
+            registry: file_based_registry(file_path, backup_policy, format),
         }
     }
+
+    pub fn backup_sub_cmd(&self, matches: &ArgMatches) {
+        match matches.subcommand() {
+            Some((cmd::BACKUP_LIST_CMD, list_matches)) => self.list_backups_cmd(list_matches),
+            Some((cmd::BACKUP_RESTORE_CMD, restore_matches)) => {
+                self.restore_backup_cmd(restore_matches)
+            }
+            _ => println!("Error: backup subcommand not found"),
+        }
+    }
+
+    fn list_backups_cmd(&self, _matches: &ArgMatches) {
+        let snapshots = match self.registry.list_backups() {
+            Ok(snapshots) => snapshots,
+            Err(why) => {
+                println!("Error: failed to list backups: {}", why);
+                return;
+            }
+        };
+
+        let current = match self.registry.list_urls(None, None) {
+            Ok(urls) => urls,
+            Err(why) => {
+                println!("Error getting URLs: {}", why);
+                return;
+            }
+        };
+
+        let mut rows = vec![];
+        for s in snapshots {
+            match self.registry.read_backup(&s.id) {
+                Ok(records) => {
+                    let changes = diff(&current, &records);
+                    rows.push((s, records.len(), changes))
+                }
+                Err(why) => println!("Error: failed to read snapshot '{}': {}", s.id, why),
+            }
+        }
+
+        display::display_snapshots(rows);
+    }
+
+    fn restore_backup_cmd(&self, matches: &ArgMatches) {
+        let snapshot = matches
+            .get_one::<String>("snapshot")
+            .expect("Error: snapshot not provided");
+
+        let changes = match self.registry.backup_diff(snapshot) {
+            Ok(changes) => changes,
+            Err(Error::SnapshotNotFound(_)) => {
+                println!("Error: snapshot '{}' not found", snapshot);
+                return;
+            }
+            Err(why) => {
+                println!("Error: failed to read snapshot '{}': {}", snapshot, why);
+                return;
+            }
+        };
+
+        if changes.is_empty() {
+            println!("Registry is the same as snapshot '{}'", snapshot);
+            return;
+        }
+
+        println!("Restoring snapshot '{}' will apply changes:", snapshot);
+        display::display_changes(&changes);
+
+        if matches.get_flag("dry-run") {
+            return;
+        }
+        if !matches.get_flag("yes") {
+            match confirm("Restore the snapshot?") {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(why) => {
+                    println!("Error: failed to read confirmation: {}", why);
+                    return;
+                }
+            }
+        }
+
+        match self.registry.restore_backup(snapshot) {
+            Ok(changes) => println!(
+                "Snapshot '{}' restored, {} bookmarks changed",
+                snapshot,
+                changes.len()
+            ),
+            Err(why) => println!("Error: failed to restore snapshot '{}': {}", snapshot, why),
+        }
+    }
+}
+
+impl Application<URLRegistry<SqliteStorage>> {
+    pub fn new_sqlite_registry(
+        file_path: &str,
+    ) -> Result<Application<URLRegistry<SqliteStorage>>, Box<dyn std::error::Error>> {
+        Ok(Application {
+            registry: URLRegistry::new_sqlite(file_path)?,
+        })
+    }
+}
+
+impl Application<URLRegistry<EncryptedStorage>> {
+    pub fn new_encrypted_registry(
+        file_path: &str,
+        passphrase: &str,
+    ) -> Result<Application<URLRegistry<EncryptedStorage>>, Error> {
+        Ok(Application {
+            registry: URLRegistry::new_encrypted(file_path, passphrase)?,
+        })
+    }
 }
 
 impl<T: Registry> Application<T> {
     pub fn group_sub_cmd(&self, matches: &ArgMatches) {
-        self.list_groups_cmd(matches)
+        match matches.subcommand() {
+            Some((cmd::GROUP_MOVE_CMD, move_matches)) => self.move_group_cmd(move_matches),
+            _ => self.list_groups_cmd(matches),
+        }
     }
 
     fn list_groups_cmd(&self, _matches: &ArgMatches) {
-        match self.registry.list_groups() {
-            Ok(groups) => {
-                for g in &groups {
-                    println!("{}", g);
-                }
-            }
+        match self.registry.list_urls(None, None) {
+            Ok(urls) => display::display_group_tree(&groups::group_tree(&urls)),
             Err(why) => println!("Error: failed to list groups: {}", why),
         }
     }
 
+    fn move_group_cmd(&self, matches: &ArgMatches) {
+        let from = matches
+            .get_one::<String>("from")
+            .expect("Error: group to move not provided");
+        let to = matches
+            .get_one::<String>("to")
+            .expect("Error: new group not provided");
+
+        match self.registry.move_group(from, to) {
+            Ok(moved) if moved.is_empty() => println!("No bookmarks in group '{}'", from),
+            Ok(moved) => println!(
+                "Moved {} bookmarks from '{}' to '{}'",
+                moved.len(),
+                from,
+                to
+            ),
+            Err(why) => println!("Error: failed to move group: {}", why),
+        }
+    }
+
     pub fn add_sub_cmd(&self, matches: &ArgMatches) {
         let url_name = matches.get_one::<String>("name");
         let url = matches.get_one::<String>("url");
@@ -308,16 +1173,51 @@ impl<T: Registry> Application<T> {
             add_data = add::interactive_add(add_data).expect("err");
         }
 
-        match self.registry.create(
-            &add_data.name,
-            &add_data.url,
-            Some(&add_data.group),
-            add_data.tags,
-        ) {
+        let notes = get_multiple_values(matches, "note")
+            .unwrap_or_default()
+            .join("\n");
+
+        let keyword = matches.get_one::<String>("keyword");
+
+        let created = if notes.is_empty() && keyword.is_none() {
+            self.registry.create(
+                &add_data.name,
+                &add_data.url,
+                Some(&add_data.group),
+                add_data.tags.clone(),
+            )
+        } else {
+            self.registry.transaction(|tx| {
+                let mut record = tx.create(
+                    &add_data.name,
+                    &add_data.url,
+                    Some(&add_data.group),
+                    add_data.tags.clone(),
+                )?;
+                if !notes.is_empty() {
+                    record = tx.change_notes(&record.id, &notes)?;
+                }
+                match keyword {
+                    Some(keyword) => tx.set_keyword(&record.id, keyword),
+                    None => Ok(record),
+                }
+            })
+        };
+
+        match created {
             Ok(url_record) => println!(
                 "Added url '{}': '{}' to '{}' group",
                 url_record.name, url_record.url, url_record.group
             ),
+            Err(Error::Duplicate { name, group }) => println!(
+                "Error: bookmark with name '{}' already exists in '{}' group, choose a different name",
+                name, group
+            ),
+            Err(Error::EmptyField(field)) => println!("Error: {} of the bookmark is required", field),
+            Err(Error::DuplicateKeyword(keyword)) => println!(
+                "Error: keyword '{}' is already used by other bookmark, choose a different keyword",
+                keyword
+            ),
             Err(why) => println!(
                 "Error adding url '{}' with name '{}': {}",
                 add_data.url, add_data.name, why
@@ -343,18 +1243,29 @@ impl<T: Registry> Application<T> {
             })
             .unwrap_or(group_filter);
 
-        let sort_cfg = matches.get_one::<String>("sort").map(|val| {
-            let sort_by = SortBy::from_str(val).expect("Invalid sort column");
-            SortConfig::new_by(sort_by)
-        });
+        let top = matches.get_one::<usize>("top").copied();
+        let sort_cfg = match top {
+            Some(_) => Some(SortConfig::new_by(SortBy::Frecency)),
+            None => matches.get_one::<String>("sort").map(|val| {
+                let sort_by = SortBy::from_str(val).expect("Invalid sort column");
+                SortConfig::new_by(sort_by)
+            }),
+        };
 
         // TODO: support output as json?
         match self
             .registry
             .list_urls(Some(tags_filter.as_ref()), sort_cfg)
         {
-            Ok(urls) => {
-                display::display_urls(urls);
+            Ok(mut urls) => {
+                if let Some(top) = top {
+                    urls.truncate(top);
+                }
+                display::display_urls(
+                    urls,
+                    matches.get_flag("dates"),
+                    matches.get_flag("keywords"),
+                );
             }
             Err(why) => {
                 println!("Error getting URLs: {}", why);
@@ -370,7 +1281,7 @@ impl<T: Registry> Application<T> {
         match self.registry.delete(id) {
             Ok(deleted) => {
                 if deleted {
-                    println!("URL '{}' removed", id)
+                    println!("URL '{}' moved to trash", id)
                 } else {
                     println!("URL '{}' not found", id)
                 }
@@ -379,25 +1290,347 @@ impl<T: Registry> Application<T> {
         }
     }
 
+    pub fn notes_sub_cmd(&self, matches: &ArgMatches) {
+        let id = matches
+            .get_one::<String>("id")
+            .expect("Error: id not provided");
+
+        let record = match self.registry.get_url(id) {
+            Ok(Some(record)) => record,
+            Ok(None) => return println!("URL '{}' not found", id),
+            Err(why) => return println!("Error getting '{}' URL: {}", id, why),
+        };
+
+        if matches.get_flag("show") {
+            return println!("{}", record.notes);
+        }
+
+        let notes = match editor::edit_text(&record.notes) {
+            Ok(notes) => notes,
+            Err(why) => return println!("Error editing notes: {}", why),
+        };
+        if notes == record.notes {
+            return println!("Notes of '{}' not changed", record.name);
+        }
+
+        match self.registry.change_notes(id, &notes) {
+            Ok(record) => println!("Notes of '{}' updated", record.name),
+            Err(why) => println!("Error changing notes of '{}' URL: {}", id, why),
+        }
+    }
+
+    pub fn open_sub_cmd(&self, matches: &ArgMatches) {
+        let id = matches
+            .get_one::<String>("id")
+            .expect("Error: id not provided");
+
+        let values = get_multiple_values(matches, "values").unwrap_or_default();
+
+        self.open(id, &values)
+    }
+
+    /// Opens the bookmark with the id, or with the keyword if there is no such id.
+    /// Placeholders of URL templates are filled with the values.
+    pub fn open(&self, id_or_keyword: &str, values: &[&str]) {
+        let record = match self.registry.get_url(id_or_keyword) {
+            Ok(None) => self.registry.get_by_keyword(id_or_keyword),
+            result => result,
+        };
+        let record = match record {
+            Ok(Some(record)) => record,
+            Ok(None) => return println!("URL with id or keyword '{}' not found", id_or_keyword),
+            Err(why) => return println!("Error getting '{}' URL: {}", id_or_keyword, why),
+        };
+
+        let urls = match self.registry.urls_to_open(&record) {
+            Ok(urls) => urls,
+            Err(Error::NotFound(id)) => {
+                return println!(
+                    "Error: bundle '{}' contains missing bookmark '{}', run `bookmark doctor` to fix it",
+                    record.name, id
+                )
+            }
+            Err(why) => return println!("Error getting URLs of '{}': {}", record.name, why),
+        };
+
+        // All URLs are filled before any is opened, so that wrong values do not open half of the bundle
+        let urls = match template::values_from_args(&urls, values).and_then(|values| {
+            urls.iter()
+                .map(|url| template::fill(url, &values))
+                .collect::<Result<Vec<String>, Error>>()
+        }) {
+            Ok(urls) => urls,
+            Err(Error::UnfilledPlaceholders(names)) => {
+                return println!(
+                    "Error: '{}' contains URL templates, provide values of: {}",
+                    record.name,
+                    names.join(", ")
+                )
+            }
+            Err(why) => return println!("Error: failed to fill URL template: {}", why),
+        };
+
+        for url in urls {
+            if let Err(why) = open::that(&url) {
+                return println!("Error: failed to open URL in the browser: {}", why);
+            }
+        }
+        if let Err(why) = self.registry.mark_opened(&record.id) {
+            println!(
+                "Error saving when '{}' URL was opened: {}",
+                record.name, why
+            )
+        }
+    }
+
     pub fn import_sub_cmd(&self, matches: &ArgMatches) {
-        let version = matches
-            .get_one::<String>("version")
-            .expect("Version from which to import not provided");
         let old_file = matches
             .get_one::<String>("old-file")
             .expect("Old version file path not provided");
 
-        match version.as_str() {
-            VERSION_V0_0_X => match self.registry.import_from_v_0_0_x(old_file) {
-                Ok(_imported) => println!("Successfully imported bookmarks!"),
-                Err(why) => println!(
-                    "Error importing bookmarks from file '{}': {} ",
-                    old_file, why
-                ),
+        match self.registry.import_from_file(old_file) {
+            Ok(imported) => println!("Successfully imported {} bookmarks!", imported.len()),
+            Err(Error::Duplicate { name, group }) => println!(
+                "Error: bookmark '{}' from '{}' group already exists, no bookmarks were imported",
+                name, group
+            ),
+            Err(Error::UnsupportedVersion { version, .. }) => println!(
+                "Error: file '{}' was written by the newer version of Bookmark (schema version {}), upgrade Bookmark to import it",
+                old_file, version
+            ),
+            Err(why) => println!(
+                "Error importing bookmarks from file '{}': {} ",
+                old_file, why
+            ),
+        }
+    }
+
+    pub fn merge_sub_cmd(&self, matches: &ArgMatches) {
+        let other_file = matches
+            .get_one::<String>("other-file")
+            .expect("Error: file to merge not provided");
+
+        let theirs = match read_records(other_file) {
+            Ok(records) => records,
+            Err(why) => {
+                println!("Error: failed to read file '{}': {}", other_file, why);
+                return;
+            }
+        };
+        let base = match matches.get_one::<String>("base") {
+            Some(base_file) => match read_records(base_file) {
+                Ok(records) => records,
+                Err(why) => {
+                    println!("Error: failed to read file '{}': {}", base_file, why);
+                    return;
+                }
             },
-            v => {
-                println!("Error importing bookmarks, version '{}' not recognized. Version have to be one of '{}'", v, VERSION_V0_0_X);
+            None => vec![],
+        };
+        let ours = match self.registry.list_urls(None, None) {
+            Ok(urls) => urls,
+            Err(why) => {
+                println!("Error getting URLs: {}", why);
+                return;
             }
+        };
+
+        let json = matches.get_flag("json");
+        let resolve = matches
+            .get_one::<String>("resolve")
+            .map(|side| match side.as_str() {
+                "theirs" => Side::Theirs,
+                _ => Side::Ours,
+            });
+
+        let mut merge = merge_records(&base, &ours, &theirs, self.registry.now());
+        let resolved = match resolve {
+            Some(side) => {
+                for i in 0..merge.conflicts.len() {
+                    merge.resolve(i, side);
+                }
+                true
+            }
+            None if json => merge.conflicts.is_empty(),
+            None => {
+                for i in 0..merge.conflicts.len() {
+                    display::display_conflicts(&merge.conflicts[i..=i]);
+                    match ask_for_choice("Keep (o)urs or (t)heirs?", &["o", "t"]) {
+                        Ok(answer) if answer == "t" => merge.resolve(i, Side::Theirs),
+                        Ok(_) => merge.resolve(i, Side::Ours),
+                        Err(why) => {
+                            println!("Error: failed to read answer: {}", why);
+                            return;
+                        }
+                    }
+                }
+                true
+            }
+        };
+
+        let changes = diff(&ours, &merge.records);
+        let apply = resolved && !changes.is_empty() && !matches.get_flag("dry-run");
+        let result = if apply {
+            Some(self.registry.apply_merge(&ours, merge.records))
+        } else {
+            None
+        };
+
+        if json {
+            let report = serde_json::json!({
+                "applied": matches!(result, Some(Ok(_))),
+                "changes": changes,
+                "conflicts": merge.conflicts,
+            });
+            println!("{}", report);
+        } else if changes.is_empty() {
+            println!("Bookmarks are the same as in '{}'", other_file);
+        } else {
+            println!("Merging '{}' applies changes:", other_file);
+            display::display_changes(&changes);
+        }
+
+        match result {
+            Some(Ok(_)) if !json => println!("Merged {} changes", changes.len()),
+            Some(Err(Error::Duplicate { name, group })) => println!(
+                "Error: bookmark '{}' would not be unique in '{}' group, no changes were merged",
+                name, group
+            ),
+            Some(Err(Error::Conflict(id))) => println!(
+                "Error: bookmark '{}' was changed during the merge, no changes were merged",
+                id
+            ),
+            Some(Err(why)) => println!("Error: failed to merge bookmarks: {}", why),
+            _ => {}
+        }
+    }
+
+    pub fn undo_sub_cmd(&self, _matches: &ArgMatches) {
+        match self.registry.undo() {
+            Ok(Some(entry)) => {
+                println!("Undone '{}':", entry.operation);
+                display::display_changes(&entry.reverted());
+            }
+            Ok(None) => println!("Nothing to undo"),
+            Err(Error::Conflict(id)) => println!(
+                "Error: bookmark '{}' was changed since the last operation, it cannot be undone",
+                id
+            ),
+            Err(why) => println!("Error: failed to undo the last operation: {}", why),
+        }
+    }
+
+    pub fn redo_sub_cmd(&self, _matches: &ArgMatches) {
+        match self.registry.redo() {
+            Ok(Some(entry)) => {
+                println!("Redone '{}':", entry.operation);
+                display::display_changes(&entry.changes);
+            }
+            Ok(None) => println!("Nothing to redo"),
+            Err(Error::Conflict(id)) => println!(
+                "Error: bookmark '{}' was changed since the operation was undone, it cannot be redone",
+                id
+            ),
+            Err(why) => println!("Error: failed to redo the operation: {}", why),
+        }
+    }
+
+    pub fn doctor_sub_cmd(&self, matches: &ArgMatches) {
+        let fix = matches.get_flag("fix");
+        let result = if fix {
+            self.registry.repair()
+        } else {
+            self.registry.diagnose()
+        };
+
+        let problems = match result {
+            Ok(problems) => problems,
+            Err(why) => {
+                println!("Error: failed to check bookmarks: {}", why);
+                return;
+            }
+        };
+        if problems.is_empty() {
+            println!("No problems found");
+            return;
+        }
+
+        display::display_problems(&problems, fix);
+        let fixable = problems.iter().filter(|p| p.fix.is_some()).count();
+        if fix {
+            println!("\nFixed {} of {} problems", fixable, problems.len());
+        } else if fixable > 0 {
+            println!(
+                "\nFound {} problems, {} of them can be fixed with --fix",
+                problems.len(),
+                fixable
+            );
+        } else {
+            println!("\nFound {} problems", problems.len());
+        }
+    }
+
+    pub fn trash_sub_cmd(&self, matches: &ArgMatches) {
+        match matches.subcommand() {
+            Some((cmd::TRASH_LIST_CMD, list_matches)) => self.list_trash_cmd(list_matches),
+            Some((cmd::TRASH_RESTORE_CMD, restore_matches)) => {
+                self.restore_from_trash_cmd(restore_matches)
+            }
+            Some((cmd::TRASH_PURGE_CMD, purge_matches)) => self.purge_trash_cmd(purge_matches),
+            _ => println!("Error: trash subcommand not found"),
+        }
+    }
+
+    fn list_trash_cmd(&self, _matches: &ArgMatches) {
+        match self.registry.list_trash() {
+            Ok(trash) => display::display_trash(trash),
+            Err(why) => println!("Error: failed to list trash: {}", why),
+        }
+    }
+
+    fn restore_from_trash_cmd(&self, matches: &ArgMatches) {
+        let id = matches
+            .get_one::<String>("id")
+            .expect("Error: bookmark id not provided");
+
+        match self.registry.restore(id) {
+            Ok(r) => println!(
+                "Restored url '{}': '{}' to '{}' group",
+                r.name, r.url, r.group
+            ),
+            Err(Error::NotFound(_)) => {
+                println!("Error: bookmark with id '{}' not found in trash", id)
+            }
+            Err(Error::Duplicate { name, group }) => println!(
+                "Error: bookmark with name '{}' already exists in '{}' group, rename it before restoring",
+                name, group
+            ),
+            Err(why) => println!("Error: failed to restore bookmark '{}': {}", id, why),
+        }
+    }
+
+    fn purge_trash_cmd(&self, matches: &ArgMatches) {
+        let older_than = matches.get_one::<chrono::Duration>("older-than");
+
+        if !matches.get_flag("yes") {
+            let request = match older_than {
+                Some(_) => "Permanently remove bookmarks deleted before the given time?",
+                None => "Permanently remove all bookmarks from the trash?",
+            };
+            match confirm(request) {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(why) => {
+                    println!("Error: failed to read confirmation: {}", why);
+                    return;
+                }
+            }
+        }
+
+        let deleted_before = older_than.map(|d| chrono::Utc::now() - *d);
+        match self.registry.purge(deleted_before) {
+            Ok(purged) => println!("Removed {} bookmarks from trash", purged.len()),
+            Err(why) => println!("Error: failed to purge trash: {}", why),
         }
     }
 
@@ -410,10 +1643,8 @@ impl<T: Registry> Application<T> {
             .expect("Error: tag not provided");
 
         match self.registry.tag(id, tag) {
-            Ok(record) => match record {
-                Some(r) => println!("Bookmark '{}' tagged with '{}'", r.id, tag),
-                None => println!("Error: bookmark with id '{}' not found", id),
-            },
+            Ok(r) => println!("Bookmark '{}' tagged with '{}'", r.id, tag),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
             Err(why) => println!("Error: failed to tag bookmark '{}': {} ", id, why),
         }
     }
@@ -427,14 +1658,84 @@ impl<T: Registry> Application<T> {
             .expect("Error: tag not provided");
 
         match self.registry.untag(id, tag) {
-            Ok(record) => match record {
-                Some(r) => println!("Tag '{}' removed from bookmark '{}'", tag, r.id),
-                None => println!("Error: bookmark with id '{}' not found", id),
-            },
+            Ok(r) => println!("Tag '{}' removed from bookmark '{}'", tag, r.id),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
             Err(why) => println!("Error: failed to untag bookmark '{}': {} ", id, why),
         }
     }
 
+    pub fn pin_sub_cmd(&self, matches: &ArgMatches, pin: bool) {
+        let id = matches
+            .get_one::<String>("id")
+            .expect("Error: bookmark id not provided");
+
+        let (result, action) = if pin {
+            (self.registry.pin(id), "pinned")
+        } else {
+            (self.registry.unpin(id), "unpinned")
+        };
+        match result {
+            Ok(r) => println!("Bookmark '{}' {}", r.name, action),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
+            Err(why) => println!("Error: failed to change bookmark '{}': {} ", id, why),
+        }
+    }
+
+    pub fn bundle_sub_cmd(&self, matches: &ArgMatches) {
+        let name = matches
+            .get_one::<String>("name")
+            .expect("Error: bundle name not provided");
+        let group = matches
+            .get_one::<String>("group")
+            .map(|g| g.to_string())
+            .unwrap_or(DEFAULT_GROUP.to_string());
+        let tags: Vec<String> = get_multiple_values(matches, "tag")
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let members: Vec<String> = get_multiple_values(matches, "members")
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        match self
+            .registry
+            .create_bundle(name, Some(&group), tags, members)
+        {
+            Ok(bundle) => println!(
+                "Added bundle '{}' of {} URLs to '{}' group",
+                bundle.name,
+                bundle.bundle.len(),
+                bundle.group
+            ),
+            Err(Error::Duplicate { name, group }) => println!(
+                "Error: bookmark with name '{}' already exists in '{}' group, choose a different name",
+                name, group
+            ),
+            Err(Error::EmptyField(field)) => println!("Error: {} of the bundle is required", field),
+            Err(why) => println!("Error: failed to add bundle '{}': {}", name, why),
+        }
+    }
+
+    pub fn keyword_sub_cmd(&self, matches: &ArgMatches) {
+        let id = matches
+            .get_one::<String>("id")
+            .expect("Error: bookmark id not provided");
+
+        let result = match matches.get_one::<String>("keyword") {
+            Some(keyword) => self.registry.set_keyword(id, keyword),
+            None => self.registry.remove_keyword(id),
+        };
+        match result {
+            Ok(r) if r.keyword.is_empty() => println!("Keyword of '{}' removed", r.name),
+            Ok(r) => println!("Bookmark '{}' can be opened with '{}'", r.name, r.keyword),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
+            Err(why) => println!("Error: failed to change keyword of '{}': {}", id, why),
+        }
+    }
+
     pub fn change_group_sub_cmd(&self, matches: &ArgMatches) {
         let id = matches
             .get_one::<String>("id")
@@ -444,10 +1745,8 @@ impl<T: Registry> Application<T> {
             .expect("Error: group not provided");
 
         match self.registry.change_group(id, group) {
-            Ok(record) => match record {
-                Some(r) => println!("Bookmark '{}' group change to '{}'", r.id, group),
-                None => println!("Error: bookmark with id '{}' not found", id),
-            },
+            Ok(r) => println!("Bookmark '{}' group change to '{}'", r.id, group),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
             Err(why) => println!(
                 "Error: failed to change group of bookmark '{}': {} ",
                 id, why
@@ -464,10 +1763,8 @@ impl<T: Registry> Application<T> {
             .expect("Error: name not provided");
 
         match self.registry.change_name(id, name) {
-            Ok(record) => match record {
-                Some(r) => println!("Bookmark '{}' name change to '{}'", r.id, name),
-                None => println!("Error: bookmark with id '{}' not found", id),
-            },
+            Ok(r) => println!("Bookmark '{}' name change to '{}'", r.id, name),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
             Err(why) => println!(
                 "Error: failed to change name of bookmark '{}': {} ",
                 id, why
@@ -484,10 +1781,8 @@ impl<T: Registry> Application<T> {
             .expect("Error: url not provided");
 
         match self.registry.change_url(id, url) {
-            Ok(record) => match record {
-                Some(r) => println!("Bookmark '{}' url change to '{}'", r.id, url),
-                None => println!("Error: bookmark with id '{}' not found", id),
-            },
+            Ok(r) => println!("Bookmark '{}' url change to '{}'", r.id, url),
+            Err(Error::NotFound(_)) => println!("Error: bookmark with id '{}' not found", id),
             Err(why) => println!("Error: failed to change url of bookmark '{}': {} ", id, why),
         }
     }
@@ -502,3 +1797,36 @@ fn get_multiple_values<'a>(matches: &'a ArgMatches, name: &str) -> Option<Vec<&'
             .collect()
     })
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{build_cli, get_matches};
+    use clap::error::ErrorKind;
+
+    #[test]
+    fn test_file_and_profile() {
+        let mut cmd = build_cli("urls.json".to_string());
+
+        println!("Should fail if both the file and the profile are given as arguments...");
+        let result = get_matches(&mut cmd, ["bookmark", "-f", "x.json", "-p", "work", "ls"]);
+        assert_eq!(
+            result.expect_err("Expected conflict").kind(),
+            ErrorKind::ArgumentConflict
+        );
+
+        println!("Should let the file take precedence over the profile from the environment...");
+        std::env::set_var("BOOKMARK_PROFILE", "work");
+        let mut cmd = build_cli("urls.json".to_string());
+        std::env::remove_var("BOOKMARK_PROFILE");
+        let result = get_matches(&mut cmd, ["bookmark", "-f", "x.json", "ls"]);
+        let matches = result.expect("Failed to parse arguments");
+        assert_eq!(
+            matches.get_one::<String>("file").map(String::as_str),
+            Some("x.json")
+        );
+        assert_eq!(
+            matches.get_one::<String>("profile").map(String::as_str),
+            Some("work")
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::Error;
+use crate::storage::read_registry_file;
+use crate::types::URLRegistry;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_KEEP_LAST: usize = 10;
+pub const DEFAULT_KEEP_DAILY: usize = 7;
+
+const BACKUPS_DIR_SUFFIX: &str = ".backups";
+const SNAPSHOT_EXTENSION: &str = "json";
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// BackupPolicy specifies which snapshots of the registry file are retained.
+/// `keep_last` most recent snapshots are kept, together with the oldest snapshot
+/// of each of the `keep_daily` most recent days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackupPolicy {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        BackupPolicy {
+            keep_last: DEFAULT_KEEP_LAST,
+            keep_daily: DEFAULT_KEEP_DAILY,
+        }
+    }
+}
+
+impl BackupPolicy {
+    pub fn new(keep_last: usize, keep_daily: usize) -> BackupPolicy {
+        BackupPolicy {
+            keep_last,
+            keep_daily,
+        }
+    }
+
+    pub fn disabled() -> BackupPolicy {
+        BackupPolicy::new(0, 0)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last > 0 || self.keep_daily > 0
+    }
+}
+
+/// Snapshot of the registry file taken before it was modified
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub path: PathBuf,
+}
+
+/// Backups keeps rotating snapshots of the registry file in the `<FILE>.backups` directory
+pub struct Backups {
+    dir: PathBuf,
+    policy: BackupPolicy,
+}
+
+impl Backups {
+    pub fn new(registry_path: &str, policy: BackupPolicy) -> Backups {
+        Backups {
+            dir: PathBuf::from(format!("{}{}", registry_path, BACKUPS_DIR_SUFFIX)),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> BackupPolicy {
+        self.policy
+    }
+
+    /// Copies the file as a new snapshot and removes snapshots not retained by the policy.
+    /// Nothing is done if backups are disabled or the file is empty.
+    pub(crate) fn snapshot(&self, source: &Path) -> Result<Option<Snapshot>, Error> {
+        self.snapshot_at(source, Utc::now())
+    }
+
+    fn snapshot_at(&self, source: &Path, time: DateTime<Utc>) -> Result<Option<Snapshot>, Error> {
+        if !self.policy.is_enabled() || !source.exists() || fs::metadata(source)?.len() == 0 {
+            return Ok(None);
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        // Make sure that snapshots taken in the same millisecond do not override each other
+        let mut time = time;
+        let mut path = self.snapshot_path(&snapshot_id(time));
+        while path.exists() {
+            time += Duration::milliseconds(1);
+            path = self.snapshot_path(&snapshot_id(time));
+        }
+
+        fs::copy(source, &path)?;
+        self.rotate()?;
+
+        Ok(Some(Snapshot {
+            id: snapshot_id(time),
+            created_at: time,
+            path,
+        }))
+    }
+
+    /// Lists snapshots starting from the most recent one
+    pub fn list(&self) -> Result<Vec<Snapshot>, Error> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut snapshots = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SNAPSHOT_EXTENSION) {
+                continue;
+            }
+
+            let id = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+            if let Ok(time) = NaiveDateTime::parse_from_str(&id, SNAPSHOT_ID_FORMAT) {
+                snapshots.push(Snapshot {
+                    id,
+                    created_at: time.and_utc(),
+                    path,
+                });
+            }
+        }
+
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+        Ok(snapshots)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Snapshot>, Error> {
+        Ok(self.list()?.into_iter().find(|s| s.id == id))
+    }
+
+    /// Reads the registry from the snapshot, migrating it to the current schema version
+    pub fn load(&self, snapshot: &Snapshot) -> Result<URLRegistry, Error> {
+        match snapshot.path.to_str() {
+            Some(path) => read_registry_file(path),
+            None => Err(Error::Storage(format!(
+                "invalid snapshot path: {:?}",
+                snapshot.path
+            ))),
+        }
+    }
+
+    fn rotate(&self) -> Result<(), Error> {
+        let snapshots = self.list()?;
+        let retained = retained_snapshots(&snapshots, self.policy);
+
+        for s in snapshots.iter().filter(|s| !retained.contains(&s.id)) {
+            fs::remove_file(&s.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn snapshot_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, SNAPSHOT_EXTENSION))
+    }
+}
+
+fn snapshot_id(time: DateTime<Utc>) -> String {
+    time.format(SNAPSHOT_ID_FORMAT).to_string()
+}
+
+/// Returns ids of snapshots retained by the policy. Snapshots have to be sorted from the most recent.
+fn retained_snapshots(snapshots: &[Snapshot], policy: BackupPolicy) -> HashSet<String> {
+    let mut retained: HashSet<String> = snapshots
+        .iter()
+        .take(policy.keep_last)
+        .map(|s| s.id.clone())
+        .collect();
+
+    let mut days: Vec<NaiveDate> = vec![];
+    for s in snapshots.iter().rev() {
+        let day = s.created_at.with_timezone(&Local).date_naive();
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    let recent_days: Vec<&NaiveDate> = days.iter().rev().take(policy.keep_daily).collect();
+
+    // Iterate from the oldest to keep the first snapshot of each day
+    let mut kept_days: HashSet<NaiveDate> = HashSet::new();
+    for s in snapshots.iter().rev() {
+        let day = s.created_at.with_timezone(&Local).date_naive();
+        if recent_days.contains(&&day) && kept_days.insert(day) {
+            retained.insert(s.id.clone());
+        }
+    }
+
+    retained
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backup::{BackupPolicy, Backups};
+    use crate::util::create_temp_file;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::fs;
+
+    #[test]
+    fn rotate_snapshots_test() {
+        let file_path =
+            create_temp_file("backup_rotate_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+        fs::write(&file_path, r#"{"version":1,"urls":{"items":[]}}"#)
+            .expect("Failed to write file");
+
+        let backups = Backups::new(&path, BackupPolicy::new(2, 2));
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+
+        println!("Should keep last snapshots and the first one of each recent day...");
+        let mut ids = vec![];
+        for day in 0..3 {
+            for hour in 0..3 {
+                let time = start + Duration::days(day) + Duration::hours(hour);
+                let snapshot = backups
+                    .snapshot_at(&file_path, time)
+                    .expect("Failed to take snapshot")
+                    .expect("Snapshot is None");
+                ids.push(snapshot.id);
+            }
+        }
+
+        let listed: Vec<String> = backups
+            .list()
+            .expect("Failed to list snapshots")
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ids[8].clone(),
+                ids[7].clone(),
+                ids[6].clone(),
+                ids[3].clone()
+            ]
+        );
+
+        println!("Should not override snapshot taken at the same time...");
+        let time = start + Duration::days(5);
+        let first = backups
+            .snapshot_at(&file_path, time)
+            .expect("Failed to take snapshot")
+            .expect("Snapshot is None");
+        let second = backups
+            .snapshot_at(&file_path, time)
+            .expect("Failed to take snapshot")
+            .expect("Snapshot is None");
+        assert_ne!(first.id, second.id);
+
+        println!("Should load snapshot...");
+        let registry = backups.load(&second).expect("Failed to load snapshot");
+        assert!(registry.urls.items.is_empty());
+
+        println!("Should not take snapshots when disabled...");
+        let disabled = Backups::new(&path, BackupPolicy::disabled());
+        assert!(disabled
+            .snapshot_at(&file_path, time)
+            .expect("Failed to take snapshot")
+            .is_none());
+
+        println!("Cleanup...");
+        fs::remove_dir_all(&backups.dir).expect("Failed to remove backups");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+}
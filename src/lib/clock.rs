@@ -0,0 +1,51 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::{Arc, Mutex};
+
+/// Source of the current time, which can be replaced to make time-dependent results,
+/// such as frecency scores, deterministic
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct SystemClock {}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that stays at the given time until it is moved.
+/// Clones share the time, so the clock can be moved after passing it to the registry.
+#[derive(Clone)]
+pub struct FixedClock {
+    time: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl FixedClock {
+    pub fn new(time: DateTime<Utc>) -> FixedClock {
+        FixedClock {
+            time: Arc::new(Mutex::new(time)),
+        }
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.lock() = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self.lock();
+        *time += duration;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DateTime<Utc>> {
+        self.time.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.lock()
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::Error;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const CIPHER_NAME: &str = "xchacha20poly1305";
+const KDF_NAME: &str = "argon2id";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Cost parameters of the Argon2id key derivation, stored together with the encrypted content
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct KdfHeader {
+    algorithm: String,
+    salt: String,
+    #[serde(flatten)]
+    params: KdfParams,
+}
+
+/// Encrypted content together with everything, except the passphrase, needed to decrypt it
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    cipher: String,
+    kdf: KdfHeader,
+    nonce: String,
+    data: String,
+}
+
+/// Cipher encrypts content with XChaCha20-Poly1305 using the key derived from the passphrase
+/// with Argon2id. Every encryption uses a new random nonce, while the salt and the key stay
+/// the same for the whole file. KDF parameters are authenticated together with the content.
+#[derive(Clone)]
+pub struct Cipher {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    kdf: KdfHeader,
+}
+
+impl Cipher {
+    /// Derives key with a new random salt
+    pub fn new(passphrase: &str, params: KdfParams) -> Result<Cipher, Error> {
+        let salt = rand::thread_rng().gen::<[u8; SALT_LEN]>();
+        let kdf = KdfHeader {
+            algorithm: KDF_NAME.to_string(),
+            salt: hex::encode(salt),
+            params,
+        };
+
+        Cipher::derive(passphrase, kdf)
+    }
+
+    /// Derives key for the content encrypted earlier, verifying that the passphrase is correct
+    pub fn unlock(passphrase: &str, content: &str) -> Result<Cipher, Error> {
+        let envelope = parse_envelope(content)?;
+        let cipher = Cipher::derive(passphrase, envelope.kdf.clone())?;
+        cipher.decrypt_envelope(&envelope)?;
+
+        Ok(cipher)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, Error> {
+        let nonce = rand::thread_rng().gen::<[u8; NONCE_LEN]>();
+        let aad = serde_json::to_vec(&self.kdf)?;
+
+        let data = self
+            .aead()?
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| Error::Storage("failed to encrypt content".to_string()))?;
+
+        let envelope = Envelope {
+            cipher: CIPHER_NAME.to_string(),
+            kdf: self.kdf.clone(),
+            nonce: hex::encode(nonce),
+            data: hex::encode(data),
+        };
+
+        Ok(serde_json::to_string(&envelope)?)
+    }
+
+    pub fn decrypt(&self, content: &str) -> Result<String, Error> {
+        let envelope = parse_envelope(content)?;
+        if envelope.kdf != self.kdf {
+            // Content was encrypted with a different key
+            return Err(Error::InvalidPassphrase);
+        }
+
+        self.decrypt_envelope(&envelope)
+    }
+
+    fn decrypt_envelope(&self, envelope: &Envelope) -> Result<String, Error> {
+        let nonce = decode_hex(&envelope.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(Error::Parse("invalid nonce length".to_string()));
+        }
+        let data = decode_hex(&envelope.data)?;
+        let aad = serde_json::to_vec(&envelope.kdf)?;
+
+        let plaintext = self
+            .aead()?
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &data,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| Error::InvalidPassphrase)?;
+
+        String::from_utf8(plaintext).map_err(|err| Error::Parse(err.to_string()))
+    }
+
+    fn derive(passphrase: &str, kdf: KdfHeader) -> Result<Cipher, Error> {
+        if kdf.algorithm != KDF_NAME {
+            return Err(Error::Parse(format!(
+                "unsupported key derivation function '{}'",
+                kdf.algorithm
+            )));
+        }
+
+        let params = Params::new(
+            kdf.params.m_cost,
+            kdf.params.t_cost,
+            kdf.params.p_cost,
+            Some(KEY_LEN),
+        )
+        .map_err(|err| Error::Parse(format!("invalid key derivation parameters: {}", err)))?;
+        let salt = decode_hex(&kdf.salt)?;
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|err| Error::Storage(format!("failed to derive key: {}", err)))?;
+
+        Ok(Cipher { key, kdf })
+    }
+
+    fn aead(&self) -> Result<XChaCha20Poly1305, Error> {
+        XChaCha20Poly1305::new_from_slice(self.key.as_ref())
+            .map_err(|_| Error::Storage("invalid key length".to_string()))
+    }
+}
+
+/// Checks if the content was encrypted by the Cipher
+pub fn is_encrypted(content: &str) -> bool {
+    parse_envelope(content).is_ok()
+}
+
+fn parse_envelope(content: &str) -> Result<Envelope, Error> {
+    let envelope: Envelope = serde_json::from_str(content)?;
+    if envelope.cipher != CIPHER_NAME {
+        return Err(Error::Parse(format!(
+            "unsupported cipher '{}'",
+            envelope.cipher
+        )));
+    }
+
+    Ok(envelope)
+}
+
+fn decode_hex(val: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(val).map_err(|err| Error::Parse(err.to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::crypto::{is_encrypted, Cipher, KdfParams};
+    use crate::error::Error;
+
+    /// Cheap parameters, so that tests do not spend time on the key derivation
+    pub(crate) fn test_kdf_params() -> KdfParams {
+        KdfParams {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 1,
+        }
+    }
+
+    #[test]
+    fn cipher_test() {
+        let cipher = Cipher::new("secret", test_kdf_params()).expect("Failed to create cipher");
+        let plaintext = r#"{"urls":{"items":[{"url":"https://dashboard.com/?token=abcd"}]}}"#;
+
+        println!("Should not contain plaintext...");
+        let encrypted = cipher.encrypt(plaintext).expect("Failed to encrypt");
+        assert!(!encrypted.contains("token"));
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(plaintext));
+
+        println!("Should use new nonce for every encryption...");
+        assert_ne!(
+            encrypted,
+            cipher.encrypt(plaintext).expect("Failed to encrypt")
+        );
+
+        println!("Should decrypt with the same passphrase...");
+        let unlocked = Cipher::unlock("secret", &encrypted).expect("Failed to unlock");
+        assert_eq!(
+            unlocked.decrypt(&encrypted).expect("Failed to decrypt"),
+            plaintext
+        );
+
+        println!("Should fail with invalid passphrase...");
+        assert!(matches!(
+            Cipher::unlock("wrong", &encrypted),
+            Err(Error::InvalidPassphrase)
+        ));
+
+        println!("Should detect tampered content...");
+        let mut envelope: serde_json::Value =
+            serde_json::from_str(&encrypted).expect("Failed to parse");
+        envelope["kdf"]["t_cost"] = serde_json::json!(2);
+        let tampered = envelope.to_string();
+        assert!(matches!(
+            cipher.decrypt(&tampered),
+            Err(Error::InvalidPassphrase)
+        ));
+    }
+}
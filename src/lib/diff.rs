@@ -0,0 +1,118 @@
+use crate::types::URLRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Change of a single record between two states of the registry
+// Changes are short-lived, so keeping records inline is simpler than boxing them
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(URLRecord),
+    Removed(URLRecord),
+    Modified { before: URLRecord, after: URLRecord },
+}
+
+impl Change {
+    /// Returns the id of the changed record, which for modified records is the id
+    /// they have before the change
+    pub fn id(&self) -> &str {
+        match self {
+            Change::Added(r) | Change::Removed(r) => &r.id,
+            Change::Modified { before, .. } => &before.id,
+        }
+    }
+
+    /// Returns the record expected to be in the registry before the change is applied
+    pub fn expected(&self) -> Option<&URLRecord> {
+        match self {
+            Change::Added(_) => None,
+            Change::Removed(r) | Change::Modified { before: r, .. } => Some(r),
+        }
+    }
+
+    /// Checks if the current record differs from the expected one.
+    /// Opening the bookmark in the meantime is not considered a conflict.
+    pub fn conflicts_with(&self, current: Option<&URLRecord>) -> bool {
+        match (current, self.expected()) {
+            (Some(c), Some(e)) => !c.same_content(e),
+            (c, e) => c.is_some() || e.is_some(),
+        }
+    }
+
+    /// Returns the change reverting this one
+    pub fn reverted(&self) -> Change {
+        match self {
+            Change::Added(r) => Change::Removed(r.clone()),
+            Change::Removed(r) => Change::Added(r.clone()),
+            Change::Modified { before, after } => Change::Modified {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+}
+
+/// Returns changes that transform `before` into `after`. Records are matched by ids.
+/// Removed and modified records keep the order from `before`, added ones from `after`.
+pub fn diff(before: &[URLRecord], after: &[URLRecord]) -> Vec<Change> {
+    let after_by_id: HashMap<&str, &URLRecord> = after.iter().map(|r| (r.id.as_str(), r)).collect();
+    let before_by_id: HashMap<&str, &URLRecord> =
+        before.iter().map(|r| (r.id.as_str(), r)).collect();
+
+    let mut changes = vec![];
+    for b in before {
+        match after_by_id.get(b.id.as_str()) {
+            None => changes.push(Change::Removed(b.clone())),
+            Some(a) if *a != b => changes.push(Change::Modified {
+                before: b.clone(),
+                after: (*a).clone(),
+            }),
+            _ => {}
+        }
+    }
+
+    for a in after {
+        if !before_by_id.contains_key(a.id.as_str()) {
+            changes.push(Change::Added(a.clone()));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::{diff, Change};
+    use crate::types::URLRecord;
+
+    #[test]
+    fn diff_test() {
+        let one = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let two = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+        let three = URLRecord::new("https://three.com", "three", "dev", Vec::<String>::new());
+
+        let mut two_modified = two.clone();
+        two_modified.group = "dev".to_string();
+
+        let before = vec![one.clone(), two.clone()];
+        let after = vec![two_modified.clone(), three.clone()];
+
+        println!("Should return added, removed and modified records...");
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Removed(one.clone()),
+                Change::Modified {
+                    before: two.clone(),
+                    after: two_modified
+                },
+                Change::Added(three),
+            ]
+        );
+        assert_eq!(changes[0].id(), one.id);
+
+        println!("Should return no changes for the same records...");
+        assert!(diff(&before, &before).is_empty());
+    }
+}
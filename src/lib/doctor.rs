@@ -0,0 +1,468 @@
+use crate::registry::DEFAULT_GROUP;
+use crate::types::{format_tag, new_id, BundleMember, URLRecord};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+const UNNAMED: &str = "unnamed";
+
+/// Problem with the record, which the registry itself would not allow,
+/// but which can be introduced by editing the file by hand or importing old files
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProblemKind {
+    /// Record has the same id as one of the previous records, or no id at all
+    DuplicateId,
+    EmptyName,
+    EmptyGroup,
+    /// Name is already used by the previous record in the group
+    NameClash {
+        name: String,
+        group: String,
+    },
+    /// Keyword is already used by the previous record
+    KeywordClash {
+        keyword: String,
+    },
+    MalformedUrl {
+        url: String,
+    },
+    /// Bundle contains the bookmark that does not exist
+    MissingBundleMember {
+        id: String,
+    },
+    /// Tag key is empty, or its key or value is surrounded with whitespaces
+    MalformedTag {
+        tag: String,
+    },
+}
+
+/// Problem found in the record together with the description of the automatic fix,
+/// which is missing if the problem has to be fixed by hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub id: String,
+    pub kind: ProblemKind,
+    pub fix: Option<String>,
+}
+
+impl Problem {
+    fn new(id: &str, kind: ProblemKind, fix: Option<String>) -> Problem {
+        Problem {
+            id: id.to_string(),
+            kind,
+            fix,
+        }
+    }
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemKind::DuplicateId => write!(f, "id is missing or used by other bookmark"),
+            ProblemKind::EmptyName => write!(f, "name is empty"),
+            ProblemKind::EmptyGroup => write!(f, "group is empty"),
+            ProblemKind::NameClash { name, group } => write!(
+                f,
+                "name '{}' is used by other bookmark in '{}' group",
+                name, group
+            ),
+            ProblemKind::KeywordClash { keyword } => {
+                write!(f, "keyword '{}' is used by other bookmark", keyword)
+            }
+            ProblemKind::MalformedUrl { url } => write!(f, "URL '{}' is malformed", url),
+            ProblemKind::MissingBundleMember { id } => {
+                write!(f, "bundle contains missing bookmark '{}'", id)
+            }
+            ProblemKind::MalformedTag { tag } => write!(f, "tag '{}' is malformed", tag),
+        }
+    }
+}
+
+/// Finds problems with the records, describing fixes that `repair` would apply
+pub fn diagnose(records: &[URLRecord]) -> Vec<Problem> {
+    repair(records).1
+}
+
+/// Fixes problems that can be repaired safely:
+/// - duplicated ids are generated again,
+/// - empty names are replaced with the host of the URL and empty groups with the default group,
+/// - clashing names are suffixed with the number, e.g. `GitHub (2)`,
+/// - clashing keywords are removed,
+/// - missing bookmarks are removed from bundles, unless no other members are left,
+/// - URLs are trimmed and prefixed with `https://` if they are missing the scheme,
+/// - tag keys and values are trimmed and the tags with empty keys are removed.
+///
+/// The first record keeps its id or name, and only the following ones are changed.
+/// Returns repaired records together with all found problems, which refer to the original ids.
+pub fn repair(records: &[URLRecord]) -> (Vec<URLRecord>, Vec<Problem>) {
+    let mut problems = vec![];
+    let mut repaired = records.to_vec();
+
+    for r in repaired.iter_mut() {
+        problems.extend(repair_fields(r));
+    }
+
+    let mut names: HashSet<(String, String)> = HashSet::new();
+    for r in repaired.iter_mut() {
+        if names.contains(&(r.group.clone(), r.name.clone())) {
+            let name = (2..)
+                .map(|i| format!("{} ({})", r.name, i))
+                .find(|n| !names.contains(&(r.group.clone(), n.clone())))
+                .expect("Failed to find unique name");
+            problems.push(Problem::new(
+                &r.id,
+                ProblemKind::NameClash {
+                    name: r.name.clone(),
+                    group: r.group.clone(),
+                },
+                Some(format!("rename to '{}'", name)),
+            ));
+            r.name = name;
+        }
+        names.insert((r.group.clone(), r.name.clone()));
+    }
+
+    let mut keywords: HashSet<String> = HashSet::new();
+    for r in repaired.iter_mut().filter(|r| !r.keyword.is_empty()) {
+        if !keywords.insert(r.keyword.clone()) {
+            problems.push(Problem::new(
+                &r.id,
+                ProblemKind::KeywordClash {
+                    keyword: r.keyword.clone(),
+                },
+                Some("remove keyword".to_string()),
+            ));
+            r.keyword.clear();
+        }
+    }
+
+    let existing: HashSet<&str> = records.iter().map(|r| r.id.as_str()).collect();
+    for r in repaired.iter_mut() {
+        let is_missing = |m: &BundleMember| matches!(m, BundleMember::Bookmark(id) if !existing.contains(id.as_str()));
+        let missing: Vec<BundleMember> =
+            r.bundle.iter().filter(|m| is_missing(m)).cloned().collect();
+        let repairable = missing.len() < r.bundle.len();
+        for member in missing {
+            if let BundleMember::Bookmark(id) = member {
+                problems.push(Problem::new(
+                    &r.id,
+                    ProblemKind::MissingBundleMember { id },
+                    repairable.then(|| "remove from bundle".to_string()),
+                ));
+            }
+        }
+        if repairable {
+            r.bundle.retain(|m| !is_missing(m));
+        }
+    }
+
+    let mut ids: HashSet<String> = HashSet::new();
+    for r in repaired.iter_mut() {
+        if r.id.is_empty() || ids.contains(&r.id) {
+            let mut id = new_id();
+            while ids.contains(&id) || records.iter().any(|o| o.id == id) {
+                id = new_id();
+            }
+            problems.push(Problem::new(
+                &r.id,
+                ProblemKind::DuplicateId,
+                Some(format!("generate new id {}", id)),
+            ));
+            r.id = id;
+        }
+        ids.insert(r.id.clone());
+    }
+
+    (repaired, problems)
+}
+
+fn repair_fields(record: &mut URLRecord) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    let url = record.url.trim();
+    if record.is_bundle() {
+        // Bundles open URLs of their members instead of their own
+    } else if !is_valid_url(url) {
+        let fixed = format!("https://{}", url);
+        let fix = if !url.contains("://") && is_valid_url(&fixed) && url.contains('.') {
+            Some(fixed)
+        } else {
+            None
+        };
+        problems.push(Problem::new(
+            &record.id,
+            ProblemKind::MalformedUrl {
+                url: record.url.clone(),
+            },
+            fix.as_ref().map(|u| format!("change to '{}'", u)),
+        ));
+        if let Some(url) = fix {
+            record.url = url;
+        }
+    } else if url != record.url {
+        problems.push(Problem::new(
+            &record.id,
+            ProblemKind::MalformedUrl {
+                url: record.url.clone(),
+            },
+            Some("remove whitespaces".to_string()),
+        ));
+        record.url = url.to_string();
+    }
+
+    if record.name.trim().is_empty() {
+        let name = host(&record.url).unwrap_or(UNNAMED).to_string();
+        problems.push(Problem::new(
+            &record.id,
+            ProblemKind::EmptyName,
+            Some(format!("rename to '{}'", name)),
+        ));
+        record.name = name;
+    }
+
+    if record.group.trim().is_empty() {
+        problems.push(Problem::new(
+            &record.id,
+            ProblemKind::EmptyGroup,
+            Some(format!("move to '{}' group", DEFAULT_GROUP)),
+        ));
+        record.group = DEFAULT_GROUP.to_string();
+    }
+
+    let mut tags = BTreeMap::new();
+    for (key, value) in &record.tags {
+        let tag = format_tag(key, value);
+        let (trimmed_key, trimmed_value) = (key.trim(), value.trim());
+        if trimmed_key.is_empty() {
+            problems.push(Problem::new(
+                &record.id,
+                ProblemKind::MalformedTag { tag },
+                Some("remove tag".to_string()),
+            ));
+        } else {
+            if trimmed_key != key || trimmed_value != value {
+                problems.push(Problem::new(
+                    &record.id,
+                    ProblemKind::MalformedTag { tag },
+                    Some(format!(
+                        "change to '{}'",
+                        format_tag(trimmed_key, trimmed_value)
+                    )),
+                ));
+            }
+            tags.insert(trimmed_key.to_string(), trimmed_value.to_string());
+        }
+    }
+    record.tags = tags;
+
+    problems
+}
+
+/// Checks that URL has the scheme followed by a non-empty part without whitespaces,
+/// and that web URLs have the host
+fn is_valid_url(url: &str) -> bool {
+    let (scheme, rest) = match url.split_once(':') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !valid_scheme || rest.is_empty() || rest.contains(char::is_whitespace) {
+        return false;
+    }
+
+    match scheme.to_lowercase().as_str() {
+        "http" | "https" => host(url).is_some(),
+        _ => true,
+    }
+}
+
+fn host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::doctor::{diagnose, is_valid_url, repair, ProblemKind};
+    use crate::types::{BundleMember, URLRecord};
+    use chrono::Utc;
+
+    fn record(id: &str, name: &str, url: &str, group: &str, tags: Vec<(&str, &str)>) -> URLRecord {
+        let mut record = URLRecord::new(url, name, group, Vec::<String>::new());
+        record.id = id.to_string();
+        for (tag, value) in tags {
+            record.tags.insert(tag.to_string(), value.to_string());
+        }
+        record
+    }
+
+    #[test]
+    fn repair_test() {
+        let mut records = vec![
+            record(
+                "1",
+                "GitHub",
+                "https://github.com",
+                "dev",
+                vec![("git", "")],
+            ),
+            record("1", "GitHub", "https://gitlab.com", "dev", vec![]),
+            record("2", "", "https://reddit.com/r/rust", "", vec![]),
+            record(
+                "3",
+                "Docs",
+                " docs.rs ",
+                "dev",
+                vec![("rust", " 1.80 "), (" api ", ""), (" ", "")],
+            ),
+            record("4", "Broken", "not a url", "dev", vec![]),
+        ];
+        records[0].keyword = "gh".to_string();
+        records[1].keyword = "gh".to_string();
+
+        println!("Should report problems without changing records...");
+        let problems = diagnose(&records);
+        let kinds: Vec<(&str, &ProblemKind)> =
+            problems.iter().map(|p| (p.id.as_str(), &p.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("2", &ProblemKind::EmptyName),
+                ("2", &ProblemKind::EmptyGroup),
+                (
+                    "3",
+                    &ProblemKind::MalformedUrl {
+                        url: " docs.rs ".to_string()
+                    }
+                ),
+                (
+                    "3",
+                    &ProblemKind::MalformedTag {
+                        tag: " ".to_string()
+                    }
+                ),
+                (
+                    "3",
+                    &ProblemKind::MalformedTag {
+                        tag: " api ".to_string()
+                    }
+                ),
+                (
+                    "3",
+                    &ProblemKind::MalformedTag {
+                        tag: "rust= 1.80 ".to_string()
+                    }
+                ),
+                (
+                    "4",
+                    &ProblemKind::MalformedUrl {
+                        url: "not a url".to_string()
+                    }
+                ),
+                (
+                    "1",
+                    &ProblemKind::NameClash {
+                        name: "GitHub".to_string(),
+                        group: "dev".to_string()
+                    }
+                ),
+                (
+                    "1",
+                    &ProblemKind::KeywordClash {
+                        keyword: "gh".to_string()
+                    }
+                ),
+                ("1", &ProblemKind::DuplicateId),
+            ]
+        );
+        assert!(problems[6].fix.is_none());
+
+        println!("Should repair records...");
+        let (repaired, _) = repair(&records);
+        assert_eq!(repaired[0], records[0]);
+        assert_ne!(repaired[1].id, "1");
+        assert_eq!(repaired[1].name, "GitHub (2)");
+        assert!(repaired[1].keyword.is_empty());
+        assert_eq!(repaired[2].name, "reddit.com");
+        assert_eq!(repaired[2].group, "default");
+        assert_eq!(repaired[3].url, "https://docs.rs");
+        assert_eq!(repaired[3].tags_as_string(), "api, rust=1.80");
+        assert_eq!(repaired[4], records[4]);
+
+        println!("Should not find problems after repair...");
+        let problems = diagnose(&repaired);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].id, "4");
+    }
+
+    #[test]
+    fn repair_bundles_test() {
+        let grafana = record("1", "Grafana", "https://grafana.com", "ops", vec![]);
+        let members = vec![
+            BundleMember::Bookmark("1".to_string()),
+            BundleMember::Bookmark("2".to_string()),
+        ];
+        let mut morning =
+            URLRecord::new_bundle("Morning", "ops", Vec::<String>::new(), members, Utc::now());
+        morning.id = "3".to_string();
+        let mut broken = URLRecord::new_bundle(
+            "Broken",
+            "ops",
+            Vec::<String>::new(),
+            vec![BundleMember::Bookmark("2".to_string())],
+            Utc::now(),
+        );
+        broken.id = "4".to_string();
+        let records = vec![grafana, morning, broken];
+
+        println!("Should remove missing bookmarks only if other members are left...");
+        let (repaired, problems) = repair(&records);
+        let found: Vec<(&str, bool)> = problems
+            .iter()
+            .map(|p| (p.id.as_str(), p.fix.is_some()))
+            .collect();
+        assert_eq!(found, vec![("3", true), ("4", false)]);
+        assert_eq!(
+            problems[0].kind,
+            ProblemKind::MissingBundleMember {
+                id: "2".to_string()
+            }
+        );
+        assert_eq!(
+            repaired[1].bundle,
+            vec![BundleMember::Bookmark("1".to_string())]
+        );
+        assert_eq!(repaired[2], records[2]);
+    }
+
+    #[test]
+    fn valid_url_test() {
+        for url in [
+            "https://github.com",
+            "http://localhost:8080/path?q=1",
+            "file:///home/user/notes.txt",
+            "mailto:someone@example.com",
+        ] {
+            assert!(is_valid_url(url), "{} should be valid", url);
+        }
+        for url in [
+            "",
+            "github.com",
+            "https://",
+            "https:///path",
+            "http://a b.com",
+        ] {
+            assert!(!is_valid_url(url), "{} should be invalid", url);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::crypto::{Cipher, KdfParams};
+use crate::diff::Change;
+use crate::error::Error;
+use crate::memory::MemoryStorage;
+use crate::storage::{lock_file, read_content, write_content};
+use crate::types::{TrashedRecord, URLRecord};
+use crate::Repository;
+use chrono::{DateTime, Utc};
+
+/// EncryptedStorage keeps URLs in a file encrypted with the key derived from the passphrase.
+/// The file is decrypted only in memory, where records are handled by the MemoryStorage, and
+/// encrypted back after every change. Access to the file is guarded by the same advisory lock
+/// as for the FileStorage.
+pub struct EncryptedStorage {
+    file_path: String,
+    cipher: Cipher,
+}
+
+impl EncryptedStorage {
+    /// Opens the encrypted file, creating it if it does not exist.
+    /// Fails with `Error::InvalidPassphrase` if the file cannot be decrypted.
+    pub fn open(file_path: &str, passphrase: &str) -> Result<EncryptedStorage, Error> {
+        EncryptedStorage::open_with_params(file_path, passphrase, KdfParams::default())
+    }
+
+    /// Opens the encrypted file. KDF parameters are used only if the file is created.
+    pub fn open_with_params(
+        file_path: &str,
+        passphrase: &str,
+        params: KdfParams,
+    ) -> Result<EncryptedStorage, Error> {
+        let _lock = lock_file(file_path, true)?;
+
+        let content = read_content(file_path)?;
+        let cipher = match content {
+            Some(content) => Cipher::unlock(passphrase, &content)?,
+            None => {
+                let cipher = Cipher::new(passphrase, params)?;
+                write_content(
+                    file_path,
+                    &cipher.encrypt(&MemoryStorage::default().to_json()?)?,
+                )?;
+                cipher
+            }
+        };
+
+        Ok(EncryptedStorage {
+            file_path: file_path.to_string(),
+            cipher,
+        })
+    }
+
+    pub(crate) fn cipher(&self) -> &Cipher {
+        &self.cipher
+    }
+
+    /// Reads the registry while holding a shared lock
+    fn read<F, T>(&self, read: F) -> Result<T, Error>
+    where
+        F: FnOnce(&MemoryStorage) -> Result<T, Error>,
+    {
+        let _lock = lock_file(&self.file_path, false)?;
+        read(&self.load()?)
+    }
+
+    /// Runs read-modify-write cycle while holding an exclusive lock.
+    /// The file is encrypted and written back only if the registry changed.
+    fn modify<F, T>(&self, modify: F) -> Result<T, Error>
+    where
+        F: FnOnce(&MemoryStorage) -> Result<T, Error>,
+    {
+        let _lock = lock_file(&self.file_path, true)?;
+        let storage = self.load()?;
+        let before = storage.to_json()?;
+
+        let result = modify(&storage)?;
+
+        let after = storage.to_json()?;
+        if after != before {
+            write_content(&self.file_path, &self.cipher.encrypt(&after)?)?;
+        }
+
+        Ok(result)
+    }
+
+    fn load(&self) -> Result<MemoryStorage, Error> {
+        match read_content(&self.file_path)? {
+            Some(content) => MemoryStorage::from_json(&self.cipher.decrypt(&content)?),
+            None => Ok(MemoryStorage::default()),
+        }
+    }
+}
+
+impl Repository for EncryptedStorage {
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+        self.modify(|s| s.add(record))
+    }
+
+    fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|s| s.add_batch(records))
+    }
+
+    fn delete_by_id(&self, id: &str) -> Result<bool, Error> {
+        self.modify(|s| s.delete_by_id(id))
+    }
+
+    fn list(&self) -> Result<Vec<URLRecord>, Error> {
+        self.read(|s| s.list())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.read(|s| s.get(id))
+    }
+
+    fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error> {
+        self.read(|s| s.get_by_keyword(keyword))
+    }
+
+    fn list_groups(&self) -> Result<Vec<String>, Error> {
+        self.read(|s| s.list_groups())
+    }
+
+    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error> {
+        self.modify(|s| s.update(id, record))
+    }
+
+    fn record_open(&self, id: &str, opened_at: DateTime<Utc>) -> Result<Option<URLRecord>, Error> {
+        self.modify(|s| s.record_open(id, opened_at))
+    }
+
+    fn trash(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<Option<TrashedRecord>, Error> {
+        self.modify(|s| s.trash(id, deleted_at))
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+        self.read(|s| s.list_trash())
+    }
+
+    fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.modify(|s| s.restore(id))
+    }
+
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error> {
+        self.modify(|s| s.purge(deleted_before))
+    }
+
+    fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|s| s.replace_all(records))
+    }
+
+    fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify(|s| s.apply(changes, deleted_at))
+    }
+
+    fn apply_unchecked(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify(|s| s.apply_unchecked(changes, deleted_at))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crypto::test::test_kdf_params;
+    use crate::encrypted::EncryptedStorage;
+    use crate::error::Error;
+    use crate::types::URLRecord;
+    use crate::util::create_temp_file;
+    use crate::Repository;
+    use std::fs;
+
+    #[test]
+    fn encrypted_storage_test() {
+        let file_path =
+            create_temp_file("encrypted_storage_test.enc").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path");
+
+        let storage = EncryptedStorage::open_with_params(path, "secret", test_kdf_params())
+            .expect("Failed to open storage");
+        let record = URLRecord::new(
+            "https://dashboard.com/?token=abcd",
+            "dashboard",
+            "default",
+            vec!["internal"],
+        );
+
+        println!("Should add URL...");
+        storage.add(record.clone()).expect("Failed to add URL");
+        assert!(storage
+            .add(URLRecord::new(
+                "https://dup.com",
+                "dashboard",
+                "default",
+                Vec::<String>::new()
+            ))
+            .is_err());
+
+        println!("Should not store plaintext...");
+        let content = fs::read_to_string(&file_path).expect("Failed to read file");
+        assert!(!content.contains("token"));
+        assert!(!content.contains("dashboard"));
+
+        println!("Should read URLs with the same passphrase...");
+        let reopened = EncryptedStorage::open(path, "secret").expect("Failed to open storage");
+        let urls = reopened.list().expect("Failed to list URLs");
+        assert_eq!(urls, vec![record.clone()]);
+
+        println!("Should fail with invalid passphrase...");
+        assert!(matches!(
+            EncryptedStorage::open(path, "wrong"),
+            Err(Error::InvalidPassphrase)
+        ));
+
+        println!("Should see changes made by other instance...");
+        reopened
+            .trash(&record.id, chrono::Utc::now())
+            .expect("Failed to trash URL")
+            .expect("URL is None");
+        assert!(storage.list().expect("Failed to list URLs").is_empty());
+        assert_eq!(storage.list_trash().expect("Failed to list trash").len(), 1);
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+}
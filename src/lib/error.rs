@@ -0,0 +1,175 @@
+use std::fmt;
+use std::io;
+
+/// Error returned by the registry and all the storages
+#[derive(Debug)]
+pub enum Error {
+    /// Bookmark with the given id does not exist
+    NotFound(String),
+    /// Bookmark with the same name already exists in the group
+    Duplicate { name: String, group: String },
+    /// Keyword is already used by other bookmark
+    DuplicateKeyword(String),
+    /// Keyword is not allowed, e.g. because it contains whitespaces or starts with `-`
+    InvalidKeyword(String),
+    /// Keyword is the name of the subcommand, which would be run instead of opening the bookmark
+    ReservedKeyword(String),
+    /// Bundle member is neither the URL nor the id of the bookmark other than the bundle
+    InvalidBundleMember(String),
+    /// URL template is opened without values of the placeholders
+    UnfilledPlaceholders(Vec<String>),
+    /// Bookmark was changed since it was read by the operation being applied,
+    /// e.g. a transaction or the undo or redo of a history entry
+    Conflict(String),
+    /// Required field was empty
+    EmptyField(&'static str),
+    /// Registry was written by the newer version of Bookmark
+    UnsupportedVersion { version: u32, supported: u32 },
+    /// Reading or writing the storage failed
+    Io(io::Error),
+    /// Stored or provided data is malformed
+    Parse(String),
+    /// Storage failed for reasons other than I/O, e.g. database error
+    Storage(String),
+    /// Encrypted registry could not be decrypted with the passphrase
+    InvalidPassphrase,
+    /// Profile with the given name does not exist
+    ProfileNotFound(String),
+    /// Profile with the given name already exists
+    ProfileExists(String),
+    /// Profile name is not allowed
+    InvalidProfileName(String),
+    /// Backup snapshot with the given id does not exist
+    SnapshotNotFound(String),
+    /// Git command used to synchronize the registry failed
+    Git(String),
+}
+
+impl Error {
+    pub(crate) fn duplicate(name: &str, group: &str) -> Error {
+        Error::Duplicate {
+            name: name.to_string(),
+            group: group.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(id) => write!(f, "bookmark with id '{}' not found", id),
+            Error::Duplicate { name, group } => write!(
+                f,
+                "URL with name '{}' already exists in '{}' group",
+                name, group
+            ),
+            Error::DuplicateKeyword(keyword) => {
+                write!(f, "keyword '{}' is already used by other bookmark", keyword)
+            }
+            Error::InvalidKeyword(keyword) => write!(
+                f,
+                "invalid keyword '{}', keywords cannot contain whitespaces or start with '-'",
+                keyword
+            ),
+            Error::ReservedKeyword(keyword) => write!(
+                f,
+                "keyword '{}' is the name of a command, choose a different keyword",
+                keyword
+            ),
+            Error::InvalidBundleMember(member) => write!(
+                f,
+                "invalid bundle member '{}', use URLs starting with the scheme, e.g. https://, or ids of bookmarks other than bundles",
+                member
+            ),
+            Error::UnfilledPlaceholders(names) => write!(
+                f,
+                "missing values of URL placeholders: {}",
+                names.join(", ")
+            ),
+            Error::Conflict(id) => write!(
+                f,
+                "bookmark with id '{}' was changed since the operation",
+                id
+            ),
+            Error::EmptyField(field) => write!(f, "{} cannot be an empty string", field),
+            Error::UnsupportedVersion { version, supported } => write!(
+                f,
+                "registry schema version {} is newer than supported version {}, upgrade Bookmark",
+                version, supported
+            ),
+            Error::Io(why) => write!(f, "I/O error: {}", why),
+            Error::Parse(why) => write!(f, "parse error: {}", why),
+            Error::Storage(why) => write!(f, "storage error: {}", why),
+            Error::InvalidPassphrase => write!(
+                f,
+                "failed to decrypt registry, invalid passphrase or corrupted file"
+            ),
+            Error::ProfileNotFound(name) => write!(f, "profile '{}' not found", name),
+            Error::ProfileExists(name) => write!(f, "profile '{}' already exists", name),
+            Error::InvalidProfileName(name) => write!(
+                f,
+                "invalid profile name '{}', use only letters, digits, '-' and '_'",
+                name
+            ),
+            Error::SnapshotNotFound(id) => write!(f, "snapshot '{}' not found", id),
+            Error::Git(why) => write!(f, "git error: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Storage(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use std::io;
+
+    #[test]
+    fn error_display_test() {
+        assert_eq!(
+            Error::duplicate("name", "group").to_string(),
+            "URL with name 'name' already exists in 'group' group"
+        );
+        assert_eq!(
+            Error::EmptyField("Tag").to_string(),
+            "Tag cannot be an empty string"
+        );
+        assert_eq!(
+            Error::NotFound("abcd".to_string()).to_string(),
+            "bookmark with id 'abcd' not found"
+        );
+        assert_eq!(
+            Error::SnapshotNotFound("1".to_string()).to_string(),
+            "snapshot '1' not found"
+        );
+
+        println!("Should convert I/O errors...");
+        let err: Error = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -1,8 +1,34 @@
-use crate::types::URLRecord;
+use crate::groups;
+use crate::types::{format_tag, parse_tag, URLRecord, TAG_VALUE_SEPARATOR};
 
 pub trait Filter {
     fn matches(&self, record: &URLRecord) -> bool;
     fn chain(self, filter: Box<dyn Filter>) -> Box<dyn Filter>;
+
+    /// Returns the query with which the storage can narrow down the records before
+    /// they are matched by the filter, e.g. using indexes of the database
+    fn storage_query(&self) -> Option<StorageQuery> {
+        None
+    }
+}
+
+/// Query the storage can evaluate on its own. All records matching the filter
+/// which returned the query also match the query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageQuery {
+    /// Bookmarks in the group and all groups nested in it
+    Group(String),
+    /// Bookmarks having a tag with any of the keys
+    TagKeys(Vec<String>),
+}
+
+impl StorageQuery {
+    pub fn matches(&self, record: &URLRecord) -> bool {
+        match self {
+            StorageQuery::Group(group) => groups::is_in_subtree(&record.group, group),
+            StorageQuery::TagKeys(keys) => keys.iter().any(|k| record.tags.contains_key(k)),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -25,7 +51,7 @@ pub struct UnorderedWordSetFilter {
 
 impl Filter for UnorderedWordSetFilter {
     fn matches(&self, record: &URLRecord) -> bool {
-        if self.phrase == "" {
+        if self.phrase.is_empty() {
             return true;
         }
 
@@ -36,7 +62,9 @@ impl Filter for UnorderedWordSetFilter {
             let matches = record.name.to_lowercase().contains(&word)
                 || record.url.to_lowercase().contains(&word)
                 || record.group.to_lowercase().contains(&word)
-                || tag_matches(record, &word);
+                || record.keyword.to_lowercase().contains(&word)
+                || tag_matches(record, &word)
+                || record.notes.to_lowercase().contains(&word);
 
             if !matches {
                 return false;
@@ -73,6 +101,7 @@ impl FilterSet {
                 Box::new(PhraseFilter::new_url_filter(phrase)),
                 Box::new(PhraseFilter::new_group_filter(phrase)),
                 Box::new(PhraseFilter::new_tag_filter(phrase)),
+                Box::new(PhraseFilter::new_notes_filter(phrase)),
             ],
         }
     }
@@ -97,35 +126,49 @@ impl Filter for FilterSet {
     }
 }
 
+/// Matches bookmarks in the group and all groups nested in it
 pub struct GroupFilter {
     group: String,
 }
 
 impl Filter for GroupFilter {
     fn matches(&self, record: &URLRecord) -> bool {
-        record.group == self.group
+        groups::is_in_subtree(&record.group, &self.group)
     }
     fn chain(self, filter: Box<dyn Filter>) -> Box<dyn Filter> {
         Box::new(FilterSet::new_combined(vec![Box::new(self), filter]))
     }
+    fn storage_query(&self) -> Option<StorageQuery> {
+        Some(StorageQuery::Group(self.group.clone()))
+    }
 }
 
 impl GroupFilter {
     pub fn new(group: &str) -> GroupFilter {
         GroupFilter {
-            group: group.to_string(),
+            group: groups::normalize(group),
         }
     }
 }
 
+/// Matches bookmarks having at least one of the tags. Tags are matched by their keys,
+/// `key=value` matches only the tag with the value and `key=*` the tag with any value.
 pub struct TagsFilter {
-    tags: Vec<String>,
+    tags: Vec<(String, Option<String>)>,
 }
 
+const ANY_TAG_VALUE: &str = "*";
+
 impl Filter for TagsFilter {
     fn matches(&self, record: &URLRecord) -> bool {
-        for t in &self.tags {
-            if record.tags.contains_key(t) {
+        for (key, value) in &self.tags {
+            let matches = match (record.tags.get(key), value.as_deref()) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(v), Some(ANY_TAG_VALUE)) => !v.is_empty(),
+                (Some(v), Some(value)) => v == value,
+            };
+            if matches {
                 return true;
             }
         }
@@ -134,21 +177,36 @@ impl Filter for TagsFilter {
     fn chain(self, filter: Box<dyn Filter>) -> Box<dyn Filter> {
         Box::new(FilterSet::new_combined(vec![Box::new(self), filter]))
     }
+    fn storage_query(&self) -> Option<StorageQuery> {
+        Some(StorageQuery::TagKeys(
+            self.tags.iter().map(|(key, _)| key.clone()).collect(),
+        ))
+    }
 }
 
 impl TagsFilter {
     pub fn new(tags: Vec<&str>) -> TagsFilter {
         TagsFilter {
-            tags: tags.iter().map(|t| t.to_string()).collect(),
+            tags: tags
+                .iter()
+                .map(|t| match t.contains(TAG_VALUE_SEPARATOR) {
+                    true => {
+                        let (key, value) = parse_tag(t);
+                        (key, Some(value))
+                    }
+                    false => (t.trim().to_string(), None),
+                })
+                .collect(),
         }
     }
 }
 
 enum SearchElement {
     Name,
-    URL,
+    Url,
     Group,
     Tag,
+    Notes,
 }
 
 /// Phrase filter filters Bookmarks by specific element
@@ -162,9 +220,10 @@ impl Filter for PhraseFilter {
     fn matches(&self, record: &URLRecord) -> bool {
         match &self.element {
             SearchElement::Name => record.name.to_lowercase().contains(&self.phrase),
-            SearchElement::URL => record.url.to_lowercase().contains(&self.phrase),
+            SearchElement::Url => record.url.to_lowercase().contains(&self.phrase),
             SearchElement::Group => record.group.to_lowercase().contains(&self.phrase),
             SearchElement::Tag => tag_matches(record, &self.phrase),
+            SearchElement::Notes => record.notes.to_lowercase().contains(&self.phrase),
         }
     }
 
@@ -184,7 +243,7 @@ impl PhraseFilter {
     pub fn new_url_filter(phrase: &str) -> PhraseFilter {
         PhraseFilter {
             phrase: phrase.to_lowercase(),
-            element: SearchElement::URL,
+            element: SearchElement::Url,
         }
     }
 
@@ -201,11 +260,18 @@ impl PhraseFilter {
             element: SearchElement::Tag,
         }
     }
+
+    pub fn new_notes_filter(phrase: &str) -> PhraseFilter {
+        PhraseFilter {
+            phrase: phrase.to_lowercase(),
+            element: SearchElement::Notes,
+        }
+    }
 }
 
 fn tag_matches(record: &URLRecord, word: &str) -> bool {
-    for t in record.tags.keys() {
-        if t.to_lowercase().contains(word) {
+    for (k, v) in &record.tags {
+        if format_tag(k, v).to_lowercase().contains(word) {
             return true;
         }
     }
@@ -214,12 +280,12 @@ fn tag_matches(record: &URLRecord, word: &str) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::filters::{Filter, FilterSet, UnorderedWordSetFilter};
+    use crate::filters::{Filter, FilterSet, GroupFilter, TagsFilter, UnorderedWordSetFilter};
     use crate::types::URLRecord;
 
     #[test]
     fn test_unordered_word_ser_filter() {
-        let test_set = vec![
+        let mut test_set = [
             URLRecord::new(
                 "http://urlAbcd.com",
                 "first url",
@@ -234,6 +300,7 @@ mod test {
             ),
             URLRecord::new("http://another.com", "poppy", "group", Vec::<String>::new()),
         ];
+        test_set[2].notes = "Use staging creds\nfor the demo".to_string();
 
         struct TestCase {
             phrase: String,
@@ -249,6 +316,10 @@ mod test {
                 phrase: "pop http com".to_string(),
                 matches: vec![true, true, true],
             },
+            TestCase {
+                phrase: "staging DEMO".to_string(),
+                matches: vec![false, false, true],
+            },
             TestCase {
                 phrase: "http complicated".to_string(),
                 matches: vec![false, false, false],
@@ -260,16 +331,16 @@ mod test {
 
             let filter: UnorderedWordSetFilter = UnorderedWordSetFilter::new(test.phrase.as_str());
 
-            for i in 0..test_set.len() {
-                println!("URL: {}", &test_set[i]);
-                assert_eq!(filter.matches(&test_set[i]), test.matches[i])
+            for (i, record) in test_set.iter().enumerate() {
+                println!("URL: {}", record);
+                assert_eq!(filter.matches(record), test.matches[i])
             }
         }
     }
 
     #[test]
     fn test_combined_phrase_filters() {
-        let test_set = vec![
+        let mut test_set = [
             URLRecord::new(
                 "http://urlAbcd.com",
                 "first url",
@@ -296,6 +367,7 @@ mod test {
                 vec!["pop", "another"],
             ),
         ];
+        test_set[4].notes = "Use staging creds".to_string();
 
         struct TestCase {
             phrase: String,
@@ -323,6 +395,10 @@ mod test {
                 phrase: "with space".to_string(),
                 matches: vec![true, false, true, false, true],
             },
+            TestCase {
+                phrase: "STAGING creds".to_string(),
+                matches: vec![false, false, false, false, true],
+            },
             TestCase {
                 phrase: "non existent".to_string(),
                 matches: vec![false, false, false, false, false],
@@ -335,9 +411,62 @@ mod test {
             let combined_filter: FilterSet =
                 FilterSet::new_combined_for_phrase(test.phrase.as_str());
 
-            for i in 0..test_set.len() {
-                println!("URL: {}", &test_set[i]);
-                assert_eq!(combined_filter.matches(&test_set[i]), test.matches[i])
+            for (i, record) in test_set.iter().enumerate() {
+                println!("URL: {}", record);
+                assert_eq!(combined_filter.matches(record), test.matches[i])
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_filter() {
+        let test_set: Vec<URLRecord> = ["work", "work/infra", "work/infra/grafana", "workshop"]
+            .iter()
+            .map(|g| URLRecord::new("http://a.com", "a", g, Vec::<String>::new()))
+            .collect();
+
+        let test_cases = vec![
+            ("work", vec![true, true, true, false]),
+            ("work/infra/", vec![false, true, true, false]),
+            ("work/infra/grafana", vec![false, false, true, false]),
+            ("infra", vec![false, false, false, false]),
+        ];
+
+        for (group, matches) in test_cases {
+            println!("Should match subtree of group: {}", group);
+
+            let filter = GroupFilter::new(group);
+
+            for (i, record) in test_set.iter().enumerate() {
+                assert_eq!(filter.matches(record), matches[i])
+            }
+        }
+    }
+
+    #[test]
+    fn test_tags_filter() {
+        let test_set = [
+            URLRecord::new("http://a.com", "a", "default", vec!["env=prod", "git"]),
+            URLRecord::new("http://b.com", "b", "default", vec!["env=staging"]),
+            URLRecord::new("http://c.com", "c", "default", vec!["env"]),
+            URLRecord::new("http://d.com", "d", "default", Vec::<String>::new()),
+        ];
+
+        let test_cases = vec![
+            (vec!["env"], vec![true, true, true, false]),
+            (vec!["env=prod"], vec![true, false, false, false]),
+            (vec!["env=*"], vec![true, true, false, false]),
+            (vec!["env=dev", "git"], vec![true, false, false, false]),
+            (vec!["owner=*"], vec![false, false, false, false]),
+        ];
+
+        for (tags, matches) in test_cases {
+            println!("Should match tags: {:?}", tags);
+
+            let filter = TagsFilter::new(tags);
+
+            for (i, record) in test_set.iter().enumerate() {
+                assert_eq!(filter.matches(record), matches[i])
             }
         }
     }
@@ -0,0 +1,203 @@
+use crate::error::Error;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER_PREFIX: &str = "# format:";
+
+/// Format in which the registry file is written.
+/// All formats share the same serde model, so the file can be converted between them
+/// without losing anything.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Compact, single line JSON
+    Json,
+    /// JSON with every record and field on separate lines
+    PrettyJson,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    pub fn names() -> Vec<&'static str> {
+        vec!["json", "pretty-json", "toml", "yaml"]
+    }
+
+    /// Detects the format of the registry file, in the following order:
+    /// - the `# format: <NAME>` header in the first line,
+    /// - the JSON object, keeping its layout,
+    /// - the file extension (`.toml`, `.yaml` or `.yml`),
+    /// - the compact JSON otherwise.
+    pub fn detect(path: &str, content: &str) -> Format {
+        if let Some(format) = Format::from_header(content) {
+            return format;
+        }
+
+        let content = content.trim();
+        if content.starts_with('{') {
+            if content.contains('\n') {
+                return Format::PrettyJson;
+            }
+            return Format::Json;
+        }
+
+        Format::from_extension(path).unwrap_or(Format::Json)
+    }
+
+    fn from_header(content: &str) -> Option<Format> {
+        let first_line = content.lines().next()?;
+        let name = first_line.strip_prefix(HEADER_PREFIX)?;
+        Format::from_str(name.trim()).ok()
+    }
+
+    fn from_extension(path: &str) -> Option<Format> {
+        match Path::new(path).extension()?.to_str()? {
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Parses the content to the JSON value, which can be migrated before deserializing
+    pub fn parse(&self, content: &str) -> Result<Value, Error> {
+        match self {
+            Format::Json | Format::PrettyJson => Ok(serde_json::from_str(content)?),
+            Format::Toml => toml::from_str(content).map_err(|why| Error::Parse(why.to_string())),
+            Format::Yaml => {
+                serde_norway::from_str(content).map_err(|why| Error::Parse(why.to_string()))
+            }
+        }
+    }
+
+    /// Serializes the value, prefixing TOML and YAML with the header,
+    /// so that the format is detected regardless of the file extension
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String, Error> {
+        match self {
+            Format::Json => Ok(serde_json::to_string(value)?),
+            Format::PrettyJson => Ok(format!("{}\n", serde_json::to_string_pretty(value)?)),
+            Format::Toml => {
+                let content =
+                    toml::to_string(value).map_err(|why| Error::Parse(why.to_string()))?;
+                Ok(format!("{} {}\n{}", HEADER_PREFIX, self, content))
+            }
+            Format::Yaml => {
+                let content =
+                    serde_norway::to_string(value).map_err(|why| Error::Parse(why.to_string()))?;
+                Ok(format!("{} {}\n{}", HEADER_PREFIX, self, content))
+            }
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "json" => Ok(Format::Json),
+            "pretty-json" => Ok(Format::PrettyJson),
+            "toml" => Ok(Format::Toml),
+            "yaml" => Ok(Format::Yaml),
+            _ => Err(Error::Parse(format!(
+                "unknown format '{}', expected one of: {}",
+                name,
+                Format::names().join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Json => "json",
+            Format::PrettyJson => "pretty-json",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::format::Format;
+    use crate::migration::load_registry;
+    use crate::types::{TrashedRecord, URLRecord, URLRegistry};
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    #[test]
+    fn round_trip_test() {
+        let mut registry = URLRegistry::new(vec![
+            URLRecord::new("https://github.com", "GitHub", "dev", vec!["git", "code"]),
+            URLRecord::new("https://reddit.com", "Reddit", "fun", Vec::<String>::new()),
+        ]);
+        registry.trash = vec![TrashedRecord::new(
+            URLRecord::new("https://old.com", "Old", "default", vec!["old"]),
+            Utc::now(),
+        )];
+
+        for name in Format::names() {
+            let format = Format::from_str(name).expect("Failed to parse format");
+
+            println!("Should write and read {} registry...", format);
+            let content = format
+                .serialize(&registry)
+                .expect("Failed to serialize registry");
+            let loaded = load_registry(&content, format).expect("Failed to load registry");
+            assert_eq!(loaded.urls.items, registry.urls.items);
+            assert_eq!(loaded.trash, registry.trash);
+
+            println!("Should detect {} format from content...", format);
+            assert_eq!(Format::detect("urls.json", &content), format);
+        }
+
+        println!("Should keep every record on separate lines...");
+        let content = Format::Toml
+            .serialize(&registry)
+            .expect("Failed to serialize registry");
+        assert_eq!(content.matches("[[urls.items]]").count(), 2);
+        let content = Format::PrettyJson
+            .serialize(&registry)
+            .expect("Failed to serialize registry");
+        assert!(content.contains("\n        \"name\": \"GitHub\",\n"));
+    }
+
+    #[test]
+    fn detect_test() {
+        println!("Should detect format from extension...");
+        assert_eq!(Format::detect("urls.toml", ""), Format::Toml);
+        assert_eq!(Format::detect("urls.yml", ""), Format::Yaml);
+        assert_eq!(Format::detect("urls.yaml", "version: 1"), Format::Yaml);
+        assert_eq!(Format::detect("urls.json", ""), Format::Json);
+        assert_eq!(Format::detect("urls", ""), Format::Json);
+
+        println!("Should prefer header over extension...");
+        assert_eq!(
+            Format::detect("urls.toml", "# format: yaml\nversion: 1\n"),
+            Format::Yaml
+        );
+
+        println!("Should prefer JSON content over extension...");
+        assert_eq!(
+            Format::detect("urls.toml", "{\n  \"urls\": {\"items\": []}\n}\n"),
+            Format::PrettyJson
+        );
+
+        println!("Should keep layout of JSON file...");
+        assert_eq!(
+            Format::detect("urls.json", "{\"urls\":{\"items\":[]}}"),
+            Format::Json
+        );
+        assert_eq!(
+            Format::detect("urls.json", "{\n  \"urls\": {\"items\": []}\n}\n"),
+            Format::PrettyJson
+        );
+
+        println!("Should not accept unknown format...");
+        assert!(Format::from_str("xml").is_err());
+    }
+}
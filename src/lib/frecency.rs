@@ -0,0 +1,75 @@
+use crate::types::URLRecord;
+use chrono::{DateTime, Duration, Utc};
+
+/// Weights of opens depending on how many days ago they happened, following Firefox.
+/// Older opens weigh the least.
+const BUCKETS: [(i64, f64); 4] = [(4, 100.0), (14, 70.0), (31, 50.0), (90, 30.0)];
+const OLDEST_WEIGHT: f64 = 10.0;
+
+/// Returns the frecency score of the record, combining how often and how recently it was opened.
+///
+/// Similarly to Firefox, the score is the number of opens multiplied by the average weight
+/// of the most recent ones, so it decays as the opens get older.
+/// Records that were never opened have the score of 0.
+pub fn frecency(record: &URLRecord, now: DateTime<Utc>) -> f64 {
+    let last_opened: Vec<DateTime<Utc>> = record.last_opened_at.into_iter().collect();
+    let opens = if record.open_history.is_empty() {
+        &last_opened
+    } else {
+        &record.open_history
+    };
+    if opens.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = opens.iter().map(|t| weight(now - *t)).sum();
+    let count = (record.open_count as usize).max(opens.len());
+
+    count as f64 * total / opens.len() as f64
+}
+
+fn weight(age: Duration) -> f64 {
+    BUCKETS
+        .iter()
+        .find(|(days, _)| age <= Duration::days(*days))
+        .map(|(_, weight)| *weight)
+        .unwrap_or(OLDEST_WEIGHT)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::frecency::frecency;
+    use crate::types::{URLRecord, OPEN_HISTORY_LEN};
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn frecency_test() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut record = URLRecord::new("https://one.com", "one", "default", Vec::<String>::new());
+
+        println!("Should score never opened record with 0...");
+        assert_eq!(frecency(&record, now), 0.0);
+
+        println!("Should score recent opens...");
+        record.record_open(now - Duration::days(1));
+        record.record_open(now - Duration::days(20));
+        assert_eq!(record.open_count, 2);
+        assert_eq!(frecency(&record, now), 2.0 * (100.0 + 50.0) / 2.0);
+
+        println!("Should decay over time...");
+        let later = now + Duration::days(100);
+        assert_eq!(frecency(&record, later), 2.0 * 10.0);
+
+        println!("Should keep counting opens dropped from the history...");
+        for i in 0..OPEN_HISTORY_LEN as i64 {
+            record.record_open(now - Duration::hours(i));
+        }
+        assert_eq!(record.open_history.len(), OPEN_HISTORY_LEN);
+        assert_eq!(frecency(&record, now), 12.0 * 100.0);
+
+        println!("Should use last open time of records without history...");
+        let mut record = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+        record.last_opened_at = Some(now - Duration::days(10));
+        assert_eq!(frecency(&record, now), 70.0);
+    }
+}
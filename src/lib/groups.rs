@@ -0,0 +1,141 @@
+use crate::types::URLRecord;
+use std::collections::BTreeMap;
+
+/// Separator of the nested group path, e.g. `work/infra/grafana`
+pub const GROUP_SEPARATOR: char = '/';
+
+/// Normalizes the group path by trimming its segments and removing the empty ones,
+/// e.g. ` work//infra/ ` becomes `work/infra`
+pub fn normalize(path: &str) -> String {
+    path.split(GROUP_SEPARATOR)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join(&GROUP_SEPARATOR.to_string())
+}
+
+/// Checks if the group is the root group itself or any of the groups nested in it
+pub fn is_in_subtree(group: &str, root: &str) -> bool {
+    match group.strip_prefix(root) {
+        Some(rest) => rest.is_empty() || rest.starts_with(GROUP_SEPARATOR),
+        None => false,
+    }
+}
+
+/// Returns the group moved from the `from` subtree to `to`, keeping its nested path,
+/// or None if the group is not in the subtree
+pub fn moved(group: &str, from: &str, to: &str) -> Option<String> {
+    if !is_in_subtree(group, from) {
+        return None;
+    }
+
+    Some(normalize(&format!("{}{}", to, &group[from.len()..])))
+}
+
+/// Returns paths of the group and all groups it is nested in, starting from the top one,
+/// e.g. `work`, `work/infra` and `work/infra/grafana` for `work/infra/grafana`
+pub fn ancestors(path: &str) -> Vec<&str> {
+    path.match_indices(GROUP_SEPARATOR)
+        .map(|(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+        .collect()
+}
+
+/// Node of the tree of nested groups
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupNode {
+    /// Last segment of the path
+    pub name: String,
+    pub path: String,
+    /// Number of bookmarks in the group and all nested groups
+    pub count: usize,
+    pub children: Vec<GroupNode>,
+}
+
+/// Builds the tree of groups of the records, sorted by names
+pub fn group_tree(records: &[URLRecord]) -> Vec<GroupNode> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for r in records {
+        for path in ancestors(&r.group) {
+            *counts.entry(path).or_insert(0) += 1;
+        }
+    }
+
+    children_of(None, &counts)
+}
+
+fn children_of(parent: Option<&str>, counts: &BTreeMap<&str, usize>) -> Vec<GroupNode> {
+    counts
+        .iter()
+        .filter(|(path, _)| match parent {
+            Some(parent) => path
+                .strip_prefix(parent)
+                .and_then(|rest| rest.strip_prefix(GROUP_SEPARATOR))
+                .map(|name| !name.contains(GROUP_SEPARATOR))
+                .unwrap_or(false),
+            None => !path.contains(GROUP_SEPARATOR),
+        })
+        .map(|(path, count)| GroupNode {
+            name: path
+                .rsplit(GROUP_SEPARATOR)
+                .next()
+                .unwrap_or(path)
+                .to_string(),
+            path: path.to_string(),
+            count: *count,
+            children: children_of(Some(path), counts),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::groups::{ancestors, group_tree, is_in_subtree, moved, normalize};
+    use crate::types::URLRecord;
+
+    #[test]
+    fn group_paths_test() {
+        assert_eq!(normalize(" work//infra/ "), "work/infra");
+        assert_eq!(normalize("/"), "");
+
+        assert!(is_in_subtree("work", "work"));
+        assert!(is_in_subtree("work/infra/grafana", "work/infra"));
+        assert!(!is_in_subtree("workshop", "work"));
+        assert!(!is_in_subtree("work", "work/infra"));
+
+        assert_eq!(
+            moved("work/infra/grafana", "work/infra", "ops"),
+            Some("ops/grafana".to_string())
+        );
+        assert_eq!(
+            moved("work", "work", "old/work"),
+            Some("old/work".to_string())
+        );
+        assert_eq!(moved("workshop", "work", "ops"), None);
+
+        assert_eq!(ancestors("a/b/c"), vec!["a", "a/b", "a/b/c"]);
+        assert_eq!(ancestors("a"), vec!["a"]);
+    }
+
+    #[test]
+    fn group_tree_test() {
+        let records: Vec<URLRecord> = ["work/infra/grafana", "work/infra", "work/docs", "home"]
+            .iter()
+            .enumerate()
+            .map(|(i, g)| URLRecord::new("https://a.com", &i.to_string(), g, Vec::<String>::new()))
+            .collect();
+
+        let tree = group_tree(&records);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!((tree[0].path.as_str(), tree[0].count), ("home", 1));
+        let work = &tree[1];
+        assert_eq!((work.name.as_str(), work.count), ("work", 3));
+        let names: Vec<&str> = work.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "infra"]);
+        let infra = &work.children[1];
+        assert_eq!(infra.count, 2);
+        assert_eq!(infra.children[0].path, "work/infra/grafana");
+        assert_eq!(infra.children[0].count, 1);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::crypto::Cipher;
+use crate::diff::Change;
+use crate::error::Error;
+use crate::storage::{lock_file, read_content, write_content};
+use crate::types::{format_tag, URLRecord};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Maximum number of operations that can be undone
+pub const HISTORY_LIMIT: usize = 100;
+
+const HISTORY_FILE_SUFFIX: &str = ".history.json";
+
+/// Single operation on the registry together with the changes it made to the records
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub operation: String,
+    pub changes: Vec<Change>,
+}
+
+impl HistoryEntry {
+    pub fn new(operation: &str, changes: Vec<Change>) -> HistoryEntry {
+        HistoryEntry {
+            operation: operation.to_string(),
+            changes,
+        }
+    }
+
+    /// Returns changes reverting the operation, in the order in which they should be applied
+    pub fn reverted(&self) -> Vec<Change> {
+        self.changes.iter().rev().map(Change::reverted).collect()
+    }
+
+    /// Returns human readable description of the operation, e.g. "add GitHub to default"
+    pub fn describe(&self) -> String {
+        match self.changes.as_slice() {
+            [Change::Added(r)] if self.operation == "add" => {
+                format!("add {} to {}", r.name, r.group)
+            }
+            [Change::Removed(r)] if self.operation == "delete" => {
+                format!("delete {} from {}", r.name, r.group)
+            }
+            [Change::Modified { before, after }] => describe_modification(before, after),
+            [Change::Added(r)] | [Change::Removed(r)] => {
+                format!("{} {} in {}", self.operation, r.name, r.group)
+            }
+            changes => format!("{} {} bookmarks", self.operation, changes.len()),
+        }
+    }
+}
+
+fn describe_modification(before: &URLRecord, after: &URLRecord) -> String {
+    if before.group != after.group {
+        return format!(
+            "move {} from {} to {}",
+            after.name, before.group, after.group
+        );
+    }
+    if before.name != after.name {
+        return format!(
+            "rename {} to {} in {}",
+            before.name, after.name, after.group
+        );
+    }
+    if before.url != after.url {
+        return format!("change URL of {} in {}", after.name, after.group);
+    }
+
+    let added: Vec<String> = after
+        .tags
+        .iter()
+        .filter(|(k, v)| before.tags.get(*k) != Some(*v))
+        .map(|(k, v)| format_tag(k, v))
+        .collect();
+    if !added.is_empty() {
+        return format!("tag {} with {}", after.name, added.join(", "));
+    }
+    let removed: Vec<&str> = before
+        .tags
+        .keys()
+        .filter(|t| !after.tags.contains_key(*t))
+        .map(String::as_str)
+        .collect();
+    if !removed.is_empty() {
+        return format!("untag {} from {}", removed.join(", "), after.name);
+    }
+
+    if before.pinned != after.pinned {
+        let operation = if after.pinned { "pin" } else { "unpin" };
+        return format!("{} {} in {}", operation, after.name, after.group);
+    }
+
+    if before.keyword != after.keyword {
+        if after.keyword.is_empty() {
+            return format!("remove keyword of {} in {}", after.name, after.group);
+        }
+        return format!(
+            "set keyword {} of {} in {}",
+            after.keyword, after.name, after.group
+        );
+    }
+
+    if before.notes != after.notes {
+        return format!("change notes of {} in {}", after.name, after.group);
+    }
+
+    format!("update {} in {}", after.name, after.group)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct HistoryLog {
+    pub undo: Vec<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+}
+
+/// Journal keeps the history of operations either in the `<REGISTRY>.history.json` file
+/// or in memory. For the encrypted registry the history file is encrypted as well.
+pub enum Journal {
+    File(String),
+    Encrypted(String, Cipher),
+    Memory(RwLock<HistoryLog>),
+}
+
+impl Journal {
+    /// Creates journal stored next to the registry file
+    pub fn for_registry(registry_path: &str) -> Journal {
+        Journal::File(format!("{}{}", registry_path, HISTORY_FILE_SUFFIX))
+    }
+
+    /// Creates journal stored next to the registry file, encrypted with the cipher of the registry
+    pub fn encrypted_for_registry(registry_path: &str, cipher: Cipher) -> Journal {
+        Journal::Encrypted(format!("{}{}", registry_path, HISTORY_FILE_SUFFIX), cipher)
+    }
+
+    pub fn in_memory() -> Journal {
+        Journal::Memory(RwLock::new(HistoryLog::default()))
+    }
+
+    /// Records new operation. Operations that were undone can no longer be redone.
+    pub(crate) fn record(&self, entry: HistoryEntry) -> Result<(), Error> {
+        if entry.changes.is_empty() {
+            return Ok(());
+        }
+
+        self.modify(|log| {
+            log.undo.push(entry);
+            if log.undo.len() > HISTORY_LIMIT {
+                let overflow = log.undo.len() - HISTORY_LIMIT;
+                log.undo.drain(0..overflow);
+            }
+            log.redo.clear();
+            Ok(())
+        })
+    }
+
+    /// Runs read-modify-write cycle on the history log.
+    /// The log is saved only if `modify` succeeds.
+    pub(crate) fn modify<F, T>(&self, modify: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut HistoryLog) -> Result<T, Error>,
+    {
+        match self {
+            Journal::File(path) => {
+                let _lock = lock_file(path, true)?;
+                let mut log = match read_content(path)? {
+                    Some(content) => serde_json::from_str(&content)?,
+                    None => HistoryLog::default(),
+                };
+
+                let result = modify(&mut log)?;
+                write_content(path, &serde_json::to_string(&log)?)?;
+
+                Ok(result)
+            }
+            Journal::Encrypted(path, cipher) => {
+                let _lock = lock_file(path, true)?;
+                let mut log = match read_content(path)? {
+                    Some(content) => serde_json::from_str(&cipher.decrypt(&content)?)?,
+                    None => HistoryLog::default(),
+                };
+
+                let result = modify(&mut log)?;
+                write_content(path, &cipher.encrypt(&serde_json::to_string(&log)?)?)?;
+
+                Ok(result)
+            }
+            Journal::Memory(log) => {
+                let mut log = log.write().map_err(|_| {
+                    Error::Storage("failed to modify history, lock poisoned".to_string())
+                })?;
+                modify(&mut log)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::Change;
+    use crate::history::{HistoryEntry, Journal, HISTORY_LIMIT};
+    use crate::types::URLRecord;
+    use crate::util::create_temp_file;
+    use std::fs;
+
+    #[test]
+    fn journal_test() {
+        let file_path = create_temp_file("history_test.json").expect("Failed to create file");
+        let journal = Journal::for_registry(file_path.to_str().expect("Invalid path"));
+        let record = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+
+        println!("Should keep limited number of operations...");
+        for _ in 0..HISTORY_LIMIT + 5 {
+            journal
+                .record(HistoryEntry::new(
+                    "add",
+                    vec![Change::Added(record.clone())],
+                ))
+                .expect("Failed to record operation");
+        }
+        journal
+            .modify(|log| {
+                assert_eq!(log.undo.len(), HISTORY_LIMIT);
+                log.redo.push(log.undo.pop().expect("History is empty"));
+                Ok(())
+            })
+            .expect("Failed to modify history");
+
+        println!("Should clear redo after new operation...");
+        journal
+            .record(HistoryEntry::new("delete", vec![Change::Removed(record)]))
+            .expect("Failed to record operation");
+        journal
+            .modify(|log| {
+                assert!(log.redo.is_empty());
+                assert_eq!(
+                    log.undo.last().expect("History is empty").operation,
+                    "delete"
+                );
+                Ok(())
+            })
+            .expect("Failed to modify history");
+
+        println!("Cleanup...");
+        if let Journal::File(path) = journal {
+            fs::remove_file(path).expect("Failed to remove file");
+        }
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn describe_test() {
+        let record = URLRecord::new("https://github.com", "GitHub", "default", vec!["git"]);
+        let mut moved = record.clone();
+        moved.group = "dev".to_string();
+        let mut tagged = record.clone();
+        tagged.tags.insert("rust".to_string(), "".to_string());
+        let mut valued = record.clone();
+        valued.set_tag("git=hub");
+        let mut with_keyword = record.clone();
+        with_keyword.keyword = "gh".to_string();
+
+        let test_cases = vec![
+            (
+                HistoryEntry::new("add", vec![Change::Added(record.clone())]),
+                "add GitHub to default",
+            ),
+            (
+                HistoryEntry::new("delete", vec![Change::Removed(record.clone())]),
+                "delete GitHub from default",
+            ),
+            (
+                HistoryEntry::new(
+                    "change group",
+                    vec![Change::Modified {
+                        before: record.clone(),
+                        after: moved,
+                    }],
+                ),
+                "move GitHub from default to dev",
+            ),
+            (
+                HistoryEntry::new(
+                    "tag",
+                    vec![Change::Modified {
+                        before: record.clone(),
+                        after: tagged.clone(),
+                    }],
+                ),
+                "tag GitHub with rust",
+            ),
+            (
+                HistoryEntry::new(
+                    "tag",
+                    vec![Change::Modified {
+                        before: record.clone(),
+                        after: valued,
+                    }],
+                ),
+                "tag GitHub with git=hub",
+            ),
+            (
+                HistoryEntry::new(
+                    "untag",
+                    vec![Change::Modified {
+                        before: tagged,
+                        after: record.clone(),
+                    }],
+                ),
+                "untag rust from GitHub",
+            ),
+            (
+                HistoryEntry::new(
+                    "set keyword",
+                    vec![Change::Modified {
+                        before: record.clone(),
+                        after: with_keyword.clone(),
+                    }],
+                ),
+                "set keyword gh of GitHub in default",
+            ),
+            (
+                HistoryEntry::new(
+                    "remove keyword",
+                    vec![Change::Modified {
+                        before: with_keyword,
+                        after: record.clone(),
+                    }],
+                ),
+                "remove keyword of GitHub in default",
+            ),
+            (
+                HistoryEntry::new(
+                    "import",
+                    vec![Change::Added(record.clone()), Change::Added(record)],
+                ),
+                "import 2 bookmarks",
+            ),
+        ];
+
+        for (entry, expected) in test_cases {
+            assert_eq!(entry.describe(), expected);
+        }
+    }
+}
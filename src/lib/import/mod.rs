@@ -1 +0,0 @@
-pub mod v0_0_x;
@@ -1,15 +1,37 @@
-use crate::filters::Filter;
-use crate::import::v0_0_x;
+use crate::diff::Change;
+use crate::doctor::Problem;
+use crate::error::Error;
+use crate::filters::{Filter, StorageQuery};
+use crate::history::HistoryEntry;
 use crate::sort::SortConfig;
-use crate::types::URLRecord;
-
+use crate::transaction::Transaction;
+use crate::types::{TrashedRecord, URLRecord};
+use chrono::{DateTime, Utc};
+
+pub mod backup;
+pub mod clock;
+pub mod crypto;
+pub mod diff;
+pub mod doctor;
+pub mod encrypted;
+pub mod error;
 pub mod filters;
+pub mod format;
+pub mod frecency;
+pub mod groups;
+pub mod history;
+pub mod memory;
+pub mod merge;
+pub mod migration;
+pub mod profile;
 pub mod registry;
+pub mod sqlite;
 pub mod storage;
+pub mod sync;
+pub mod template;
+pub mod transaction;
 pub mod types;
 
-pub mod import;
-
 pub mod sort;
 mod util;
 
@@ -20,35 +42,109 @@ pub trait Registry: RegistryReader + Importer {
         url: &str,
         group: Option<&str>,
         tags: Vec<String>,
-    ) -> Result<URLRecord, Box<dyn std::error::Error>>;
+    ) -> Result<URLRecord, Error>;
 
-    fn add(&self, record: URLRecord) -> Result<URLRecord, Box<dyn std::error::Error>>;
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error>;
 
-    fn delete(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>>;
+    /// Creates the bundle opening all members at once. Members are either ids of other
+    /// bookmarks, which cannot be bundles themselves, or URLs with the scheme followed
+    /// by `://`, e.g. `https://grafana.com`. Any other member fails with
+    /// `Error::InvalidBundleMember`.
+    fn create_bundle(
+        &self,
+        name: &str,
+        group: Option<&str>,
+        tags: Vec<String>,
+        members: Vec<String>,
+    ) -> Result<URLRecord, Error>;
 
-    fn list_groups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+    /// Moves bookmark to the trash. Returns false if bookmark does not exist.
+    fn delete(&self, id: &str) -> Result<bool, Error>;
 
-    fn tag(&self, id: &str, tag: &str) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+    /// Lists bookmarks in the trash
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error>;
 
-    fn untag(&self, id: &str, tag: &str) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+    /// Moves bookmark from the trash back to the registry.
+    /// Fails if the bookmark with the same name was added to the group in the meantime.
+    fn restore(&self, id: &str) -> Result<URLRecord, Error>;
 
-    fn change_group(
-        &self,
-        id: &str,
-        group: &str,
-    ) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+    /// Permanently removes bookmarks moved to the trash before `deleted_before`,
+    /// or all of them if not specified.
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error>;
 
-    fn change_name(
-        &self,
-        id: &str,
-        name: &str,
-    ) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+    fn list_groups(&self) -> Result<Vec<String>, Error>;
+
+    /// Adds the tag in the `key` or `key=value` format, replacing the value of the existing one
+    fn tag(&self, id: &str, tag: &str) -> Result<URLRecord, Error>;
+
+    /// Removes the tag with the key. Tag in the `key=value` format is removed only
+    /// if it has the value.
+    fn untag(&self, id: &str, tag: &str) -> Result<URLRecord, Error>;
+
+    fn change_group(&self, id: &str, group: &str) -> Result<URLRecord, Error>;
+
+    /// Moves all bookmarks from the group and groups nested in it to the other group,
+    /// keeping the nested paths, e.g. `work/infra/grafana` moved from `work` to `old`
+    /// becomes `old/infra/grafana`. Returns the moved bookmarks.
+    fn move_group(&self, from: &str, to: &str) -> Result<Vec<URLRecord>, Error>;
+
+    /// Pins the bookmark, so that it is listed before all others, whatever the order
+    fn pin(&self, id: &str) -> Result<URLRecord, Error>;
 
-    fn change_url(
+    fn unpin(&self, id: &str) -> Result<URLRecord, Error>;
+
+    /// Sets the keyword with which the bookmark can be opened, replacing the previous one.
+    /// Fails if the keyword is used by other bookmark.
+    fn set_keyword(&self, id: &str, keyword: &str) -> Result<URLRecord, Error>;
+
+    fn remove_keyword(&self, id: &str) -> Result<URLRecord, Error>;
+
+    fn change_name(&self, id: &str, name: &str) -> Result<URLRecord, Error>;
+
+    fn change_url(&self, id: &str, url: &str) -> Result<URLRecord, Error>;
+
+    /// Replaces notes of the bookmark. Empty notes remove them.
+    fn change_notes(&self, id: &str, notes: &str) -> Result<URLRecord, Error>;
+
+    /// Records that the bookmark was opened. Opening is not an update of the bookmark,
+    /// so it is not recorded in the history and does not change `updated_at`.
+    fn mark_opened(&self, id: &str) -> Result<URLRecord, Error>;
+
+    /// Replaces bookmarks with the merged ones, applying only the differences from `ours`,
+    /// the bookmarks the merge started from, which are recorded as a single operation.
+    /// Removed bookmarks are moved to the trash.
+    /// Fails if any name is not unique in its group or any keyword is used more than once,
+    /// and with `Error::Conflict` if any of the changed bookmarks was changed since.
+    fn apply_merge(
         &self,
-        id: &str,
-        url: &str,
-    ) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+        ours: &[URLRecord],
+        records: Vec<URLRecord>,
+    ) -> Result<Vec<Change>, Error>;
+
+    /// Returns the current time of the clock used by the registry
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Finds problems with bookmarks which could be introduced by editing the file by hand,
+    /// such as duplicated ids or names, together with the fixes `repair` would apply
+    fn diagnose(&self) -> Result<Vec<Problem>, Error>;
+
+    /// Fixes problems that can be repaired automatically and returns all found problems.
+    /// The repair is recorded in the history as a single operation, so it can be undone.
+    fn repair(&self) -> Result<Vec<Problem>, Error>;
+
+    /// Runs all operations of the transaction and saves their results in a single write,
+    /// which is recorded as a single operation. If any operation fails, or any of the
+    /// modified bookmarks was changed in the meantime, the registry is left untouched.
+    fn transaction<F, R>(&self, operations: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut Transaction) -> Result<R, Error>,
+        Self: Sized;
+
+    /// Reverts the last operation. Returns None if there is nothing to undo.
+    fn undo(&self) -> Result<Option<HistoryEntry>, Error>;
+
+    /// Applies again the last undone operation. Returns None if there is nothing to redo.
+    fn redo(&self) -> Result<Option<HistoryEntry>, Error>;
 }
 
 pub trait RegistryReader {
@@ -56,36 +152,58 @@ pub trait RegistryReader {
         &self,
         filter: Option<&dyn Filter>,
         sort: Option<SortConfig>,
-    ) -> Result<Vec<URLRecord>, Box<dyn std::error::Error>>;
+    ) -> Result<Vec<URLRecord>, Error>;
 
-    fn get_url(&self, id: &str) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
-}
+    fn get_url(&self, id: &str) -> Result<Option<URLRecord>, Error>;
 
-pub trait Repository: RepositoryOld {
-    fn add(&self, record: URLRecord) -> Result<URLRecord, Box<dyn std::error::Error>>;
-    fn add_batch(
-        &self,
-        record: Vec<URLRecord>,
-    ) -> Result<Vec<URLRecord>, Box<dyn std::error::Error>>;
-    fn delete_by_id(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>>;
-    fn list(&self) -> Result<Vec<URLRecord>, Box<dyn std::error::Error>>;
-    fn get(&self, id: &str) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
-    fn list_groups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
-    fn update(
-        &self,
-        id: &str,
-        record: URLRecord,
-    ) -> Result<Option<URLRecord>, Box<dyn std::error::Error>>;
+    /// Returns the bookmark with the keyword, or None if no bookmark uses it
+    fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error>;
+
+    /// Returns URLs opened by the bookmark, which are URLs of all members for bundles.
+    /// Fails if any bookmark in the bundle does not exist.
+    fn urls_to_open(&self, record: &URLRecord) -> Result<Vec<String>, Error>;
 }
 
-pub trait RepositoryOld {
-    fn list_v_0_0_x(
-        &self,
-        path: &str,
-    ) -> Result<Vec<v0_0_x::URLRecord>, Box<dyn std::error::Error>>;
+pub trait Repository {
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error>;
+    fn add_batch(&self, record: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error>;
+    fn delete_by_id(&self, id: &str) -> Result<bool, Error>;
+    fn list(&self) -> Result<Vec<URLRecord>, Error>;
+    /// Lists records matching the query, in the same order as `list`
+    fn list_matching(&self, query: &StorageQuery) -> Result<Vec<URLRecord>, Error> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|r| query.matches(r))
+            .collect())
+    }
+    fn get(&self, id: &str) -> Result<Option<URLRecord>, Error>;
+    /// Returns the record with the keyword, or None if the keyword is empty or not used
+    fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error> {
+        if keyword.is_empty() {
+            return Ok(None);
+        }
+        Ok(self.list()?.into_iter().find(|r| r.keyword == keyword))
+    }
+    fn list_groups(&self) -> Result<Vec<String>, Error>;
+    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error>;
+    /// Records that the bookmark was opened, keeping changes made to it in the meantime
+    fn record_open(&self, id: &str, opened_at: DateTime<Utc>) -> Result<Option<URLRecord>, Error>;
+    fn trash(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<Option<TrashedRecord>, Error>;
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error>;
+    fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error>;
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error>;
+    /// Replaces all records, returning the previous ones
+    fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error>;
+    /// Applies all changes in a single write, or none of them if any record was changed
+    /// in the meantime or any name would not be unique in its group
+    fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error>;
+    /// Applies changes like `apply`, without checking that names and keywords stay unique.
+    /// Used to bring back problems fixed by the repair when it is undone.
+    fn apply_unchecked(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error>;
 }
 
 pub trait Importer {
-    fn import_from_v_0_0_x(&self, path: &str)
-        -> Result<Vec<URLRecord>, Box<dyn std::error::Error>>;
+    /// Imports URLs from the registry file of any known schema version
+    fn import_from_file(&self, path: &str) -> Result<Vec<URLRecord>, Error>;
 }
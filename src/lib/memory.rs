@@ -0,0 +1,335 @@
+use crate::diff::Change;
+use crate::error::Error;
+use crate::format::Format;
+use crate::migration::load_registry;
+use crate::storage::{
+    apply_changes, apply_changes_unchecked, ensure_keyword_unique, is_purged, is_same, is_unique,
+};
+use crate::types::{TrashedRecord, URLRecord, URLRegistry};
+use crate::Repository;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// MemoryStorage keeps URLs only in memory.
+/// It follows the same rules as the FileStorage and can be loaded from
+/// and dumped to the same JSON format.
+pub struct MemoryStorage {
+    urls: RwLock<Vec<URLRecord>>,
+    trash: RwLock<Vec<TrashedRecord>>,
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        MemoryStorage::new(vec![])
+    }
+}
+
+impl MemoryStorage {
+    pub fn new(urls: Vec<URLRecord>) -> MemoryStorage {
+        MemoryStorage {
+            urls: RwLock::new(urls),
+            trash: RwLock::new(vec![]),
+        }
+    }
+
+    /// Loads URLs from the JSON of any known schema version
+    pub fn from_json(json: &str) -> Result<MemoryStorage, Error> {
+        let registry = load_registry(json, Format::Json)?;
+        Ok(MemoryStorage {
+            urls: RwLock::new(registry.urls.items),
+            trash: RwLock::new(registry.trash),
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        let mut registry = URLRegistry::new(self.list()?);
+        registry.trash = self.list_trash()?;
+
+        Ok(serde_json::to_string(&registry)?)
+    }
+
+    fn read<F, T>(&self, read: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Vec<URLRecord>) -> Result<T, Error>,
+    {
+        let urls = self
+            .urls
+            .read()
+            .map_err(|_| Error::Storage("failed to read URLs, lock poisoned".to_string()))?;
+        read(&urls)
+    }
+
+    fn modify<F, T>(&self, modify: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Vec<URLRecord>) -> Result<T, Error>,
+    {
+        let mut urls = self
+            .urls
+            .write()
+            .map_err(|_| Error::Storage("failed to modify URLs, lock poisoned".to_string()))?;
+        modify(&mut urls)
+    }
+
+    /// Modifies URLs together with the trash. Locks are always taken in the same order.
+    fn modify_with_trash<F, T>(&self, modify: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Vec<URLRecord>, &mut Vec<TrashedRecord>) -> Result<T, Error>,
+    {
+        self.modify(|urls| {
+            let mut trash = self
+                .trash
+                .write()
+                .map_err(|_| Error::Storage("failed to modify trash, lock poisoned".to_string()))?;
+            modify(urls, &mut trash)
+        })
+    }
+}
+
+impl Repository for MemoryStorage {
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+        self.modify(|urls| {
+            if !is_unique(urls, &record) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+            ensure_keyword_unique(urls, &record)?;
+
+            urls.push(record.clone());
+            Ok(record)
+        })
+    }
+
+    /// Adds all records to the registry as long as all of them are unique
+    /// If at least one name-group pair or keyword is not unique, none of the URLs is saved
+    fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|urls| {
+            let mut new_urls = urls.clone();
+            for r in &records {
+                if !is_unique(&new_urls, r) {
+                    return Err(Error::duplicate(&r.name, &r.group));
+                }
+                ensure_keyword_unique(&new_urls, r)?;
+                new_urls.push(r.clone());
+            }
+
+            *urls = new_urls;
+            Ok(urls.clone())
+        })
+    }
+
+    fn delete_by_id(&self, id: &str) -> Result<bool, Error> {
+        self.modify(|urls| match urls.iter().position(|u| u.id == id) {
+            Some(index) => {
+                urls.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        })
+    }
+
+    fn list(&self) -> Result<Vec<URLRecord>, Error> {
+        self.read(|urls| Ok(urls.clone()))
+    }
+
+    fn get(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.read(|urls| Ok(urls.iter().find(|u| u.id == id).cloned()))
+    }
+
+    fn list_groups(&self) -> Result<Vec<String>, Error> {
+        self.read(|urls| {
+            let mut distinct: HashMap<&str, bool> = HashMap::new();
+            for u in urls {
+                distinct.insert(u.group.as_str(), false);
+            }
+
+            Ok(distinct.keys().map(|k| k.to_string()).collect())
+        })
+    }
+
+    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error> {
+        self.modify(|urls| {
+            if urls.iter().any(|u| is_same(u, &record)) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+            ensure_keyword_unique(urls, &record)?;
+
+            match urls.iter_mut().find(|u| u.id == id) {
+                Some(u) => {
+                    *u = record.clone();
+                    Ok(Some(record))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn record_open(&self, id: &str, opened_at: DateTime<Utc>) -> Result<Option<URLRecord>, Error> {
+        self.modify(|urls| match urls.iter_mut().find(|u| u.id == id) {
+            Some(u) => {
+                u.record_open(opened_at);
+                Ok(Some(u.clone()))
+            }
+            None => Ok(None),
+        })
+    }
+
+    fn trash(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<Option<TrashedRecord>, Error> {
+        self.modify_with_trash(|urls, trash| {
+            let index = match urls.iter().position(|u| u.id == id) {
+                Some(index) => index,
+                None => return Ok(None),
+            };
+
+            let trashed = TrashedRecord::new(urls.remove(index), deleted_at);
+            trash.retain(|t| t.record.id != id);
+            trash.push(trashed.clone());
+
+            Ok(Some(trashed))
+        })
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+        let trash = self
+            .trash
+            .read()
+            .map_err(|_| Error::Storage("failed to read trash, lock poisoned".to_string()))?;
+        Ok(trash.clone())
+    }
+
+    fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.modify_with_trash(|urls, trash| {
+            let index = match trash.iter().position(|t| t.record.id == id) {
+                Some(index) => index,
+                None => return Ok(None),
+            };
+
+            let record = &trash[index].record;
+            if !is_unique(urls, record) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+            ensure_keyword_unique(urls, record)?;
+
+            let record = trash.remove(index).record;
+            urls.push(record.clone());
+
+            Ok(Some(record))
+        })
+    }
+
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error> {
+        self.modify_with_trash(|_, trash| {
+            let (purged, kept) = trash.drain(..).partition(|t| is_purged(t, deleted_before));
+            *trash = kept;
+
+            Ok(purged)
+        })
+    }
+
+    fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|urls| Ok(std::mem::replace(urls, records)))
+    }
+
+    fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify_with_trash(|urls, trash| {
+            let mut new_urls = urls.clone();
+            let mut new_trash = trash.clone();
+            apply_changes(&mut new_urls, &mut new_trash, changes, deleted_at)?;
+
+            *urls = new_urls;
+            *trash = new_trash;
+            Ok(())
+        })
+    }
+
+    fn apply_unchecked(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify_with_trash(|urls, trash| {
+            let mut new_urls = urls.clone();
+            let mut new_trash = trash.clone();
+            apply_changes_unchecked(&mut new_urls, &mut new_trash, changes, deleted_at)?;
+
+            *urls = new_urls;
+            *trash = new_trash;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::memory::MemoryStorage;
+    use crate::types::URLRecord;
+    use crate::Repository;
+
+    #[test]
+    fn memory_storage_test() {
+        let storage = MemoryStorage::default();
+
+        let records = vec![
+            URLRecord::new("https://one.com", "one", "default", vec!["tag"]),
+            URLRecord::new("https://two.com", "two", "default", Vec::<String>::new()),
+        ];
+
+        println!("Should add URLs...");
+        storage
+            .add_batch(records.clone())
+            .expect("Failed to add URLs");
+        assert_eq!(storage.list().expect("Failed to list URLs").len(), 2);
+
+        println!("Should not add duplicated name in group...");
+        let result = storage.add(URLRecord::new(
+            "https://dup.com",
+            "one",
+            "default",
+            Vec::<String>::new(),
+        ));
+        assert!(result.is_err());
+
+        println!("Should not add any URL from batch if one is not unique...");
+        let result = storage.add_batch(vec![
+            URLRecord::new(
+                "https://three.com",
+                "three",
+                "default",
+                Vec::<String>::new(),
+            ),
+            URLRecord::new("https://dup.com", "two", "default", Vec::<String>::new()),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(storage.list().expect("Failed to list URLs").len(), 2);
+
+        println!("Should not update to duplicated name in group...");
+        let mut duplicate = records[1].clone();
+        duplicate.name = "one".to_string();
+        assert!(storage.update(&duplicate.id, duplicate.clone()).is_err());
+
+        println!("Should update URL...");
+        let mut updated = records[1].clone();
+        updated.group = "other".to_string();
+        storage
+            .update(&updated.id, updated.clone())
+            .expect("Failed to update URL")
+            .expect("URL is None");
+        let record = storage
+            .get(&updated.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert_eq!(record.group, "other");
+
+        println!("Should dump and load JSON...");
+        let json = storage.to_json().expect("Failed to dump JSON");
+        let loaded = MemoryStorage::from_json(&json).expect("Failed to load JSON");
+        let urls = loaded.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].id, records[0].id);
+        assert_eq!(urls[1].group, "other");
+
+        println!("Should delete URL...");
+        assert!(loaded
+            .delete_by_id(&records[0].id)
+            .expect("Failed to delete"));
+        assert!(!loaded
+            .delete_by_id(&records[0].id)
+            .expect("Failed to delete"));
+        assert_eq!(loaded.list().expect("Failed to list URLs").len(), 1);
+    }
+}
@@ -0,0 +1,706 @@
+use crate::types::{TrashedRecord, URLRecord, URLRegistry, OPEN_HISTORY_LEN};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Side of the merge whose version should be kept
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Both sides changed the same fields of the record differently
+    Fields { fields: Vec<String> },
+    /// Record was deleted on one side and changed on the other
+    Deleted,
+    /// Records with different ids have the same name in the group
+    Duplicate,
+    /// Records with different ids have the same keyword
+    DuplicateKeyword { keyword: String },
+}
+
+/// Conflicting changes together with the record that is kept if the conflict is resolved
+/// in favour of each side. Missing record means that it is deleted.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: ConflictKind,
+    pub ours: Option<URLRecord>,
+    pub theirs: Option<URLRecord>,
+}
+
+/// Result of merging two sets of records.
+/// Conflicts are initially resolved in favour of our side, except for records deleted on one
+/// side and changed on the other, which are kept so that no change is lost.
+/// Records dropped from the result are moved to the trash. Records clashing only by the keyword
+/// are both kept, and the one from the other side loses the keyword.
+pub struct Merge {
+    pub records: Vec<URLRecord>,
+    pub trash: Vec<TrashedRecord>,
+    pub conflicts: Vec<Conflict>,
+    /// Time recorded as the deletion time of the dropped records
+    deleted_at: DateTime<Utc>,
+}
+
+impl Merge {
+    /// Resolves the conflict with given index, keeping the version from the given side
+    pub fn resolve(&mut self, conflict: usize, side: Side) {
+        let conflict = self.conflicts[conflict].clone();
+        let (keep, drop) = match side {
+            Side::Ours => (conflict.ours, conflict.theirs),
+            Side::Theirs => (conflict.theirs, conflict.ours),
+        };
+
+        if let ConflictKind::DuplicateKeyword { .. } = conflict.kind {
+            if let Some(mut drop) = drop {
+                drop.keyword.clear();
+                self.keep(drop);
+            }
+            if let Some(keep) = keep {
+                self.keep(keep);
+            }
+            return;
+        }
+
+        if let Some(drop) = drop {
+            if keep.as_ref().map(|k| k.id != drop.id).unwrap_or(true) {
+                self.remove(&drop.id);
+            }
+        }
+        if let Some(keep) = keep {
+            self.keep(keep);
+        }
+    }
+
+    /// Returns registry with merged records and the trash
+    pub fn into_registry(self) -> URLRegistry {
+        let ids: HashSet<&str> = self.records.iter().map(|r| r.id.as_str()).collect();
+        let trash = self
+            .trash
+            .into_iter()
+            .filter(|t| !ids.contains(t.record.id.as_str()))
+            .collect();
+
+        let mut registry = URLRegistry::new(self.records);
+        registry.trash = trash;
+        registry
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(index) = self.records.iter().position(|r| r.id == id) {
+            let record = self.records.remove(index);
+            if !self.trash.iter().any(|t| t.record.id == id) {
+                self.trash.push(TrashedRecord::new(record, self.deleted_at));
+            }
+        }
+    }
+
+    fn keep(&mut self, record: URLRecord) {
+        self.trash.retain(|t| t.record.id != record.id);
+        match self.records.iter().position(|r| r.id == record.id) {
+            Some(index) => self.records[index] = record,
+            None => self.records.push(record),
+        }
+    }
+}
+
+/// Merges registries, including their trash
+pub fn merge_registries(
+    base: &URLRegistry,
+    ours: &URLRegistry,
+    theirs: &URLRegistry,
+    now: DateTime<Utc>,
+) -> Merge {
+    let mut merge = merge_records(&base.urls.items, &ours.urls.items, &theirs.urls.items, now);
+    let mut trash = merge_trash(&base.trash, &ours.trash, &theirs.trash);
+    trash.append(&mut merge.trash);
+    merge.trash = trash;
+
+    merge
+}
+
+/// Merges records changed since `base`, matching them by ids. Changes of different fields
+/// are applied from both sides and added tags are joined. Without the base, records are
+/// merged as if both sides added them.
+/// Our records keep their order, records added on their side are appended.
+/// Records dropped from the result are trashed at `now`.
+pub fn merge_records(
+    base: &[URLRecord],
+    ours: &[URLRecord],
+    theirs: &[URLRecord],
+    now: DateTime<Utc>,
+) -> Merge {
+    let base_by_id = by_id(base);
+    let ours_by_id = by_id(ours);
+    let theirs_by_id = by_id(theirs);
+
+    let mut ids: Vec<&str> = ours.iter().map(|r| r.id.as_str()).collect();
+    ids.extend(
+        theirs
+            .iter()
+            .map(|r| r.id.as_str())
+            .filter(|id| !ours_by_id.contains_key(id)),
+    );
+
+    let mut records = vec![];
+    let mut conflicts = vec![];
+    for id in ids {
+        let b = base_by_id.get(id).copied();
+        let o = ours_by_id.get(id).copied();
+        let t = theirs_by_id.get(id).copied();
+
+        match (b, o, t) {
+            (_, Some(o), Some(t)) if o == t => records.push(o.clone()),
+            (b, Some(o), Some(t)) => {
+                let (merged_ours, merged_theirs, fields) = merge_fields(b, o, t);
+                if !fields.is_empty() {
+                    conflicts.push(Conflict {
+                        id: id.to_string(),
+                        kind: ConflictKind::Fields { fields },
+                        ours: Some(merged_ours.clone()),
+                        theirs: Some(merged_theirs),
+                    });
+                }
+                records.push(merged_ours);
+            }
+            (Some(b), Some(o), None) if o.same_content(b) => {}
+            (Some(b), None, Some(t)) if t.same_content(b) => {}
+            (None, Some(o), None) => records.push(o.clone()),
+            (None, None, Some(t)) => records.push(t.clone()),
+            (_, o, t) => {
+                conflicts.push(Conflict {
+                    id: id.to_string(),
+                    kind: ConflictKind::Deleted,
+                    ours: o.cloned(),
+                    theirs: t.cloned(),
+                });
+                if let Some(record) = o.or(t) {
+                    records.push(record.clone());
+                }
+            }
+        }
+    }
+
+    let mut merge = Merge {
+        records,
+        trash: vec![],
+        conflicts,
+        deleted_at: now,
+    };
+    resolve_duplicates(&mut merge, ours);
+    resolve_duplicate_keywords(&mut merge, ours);
+
+    merge
+}
+
+/// Reports records that ended up with the same name in the group because of the changes
+/// on their side, and keeps only our record
+fn resolve_duplicates(merge: &mut Merge, ours: &[URLRecord]) {
+    let ours_keys: HashMap<&str, (&str, &str)> = ours
+        .iter()
+        .map(|r| (r.id.as_str(), (r.group.as_str(), r.name.as_str())))
+        .collect();
+    let from_ours =
+        |r: &URLRecord| ours_keys.get(r.id.as_str()) == Some(&(r.group.as_str(), r.name.as_str()));
+
+    let mut first_by_key: HashMap<(&str, &str), &URLRecord> = HashMap::new();
+    let mut duplicates = vec![];
+    for record in &merge.records {
+        let key = (record.group.as_str(), record.name.as_str());
+        match first_by_key.get(&key) {
+            None => {
+                first_by_key.insert(key, record);
+            }
+            Some(first) if from_ours(first) && from_ours(record) => {}
+            Some(first) => {
+                let (our, their) = if from_ours(record) {
+                    (record, *first)
+                } else {
+                    (*first, record)
+                };
+                duplicates.push(Conflict {
+                    id: their.id.clone(),
+                    kind: ConflictKind::Duplicate,
+                    ours: Some(our.clone()),
+                    theirs: Some(their.clone()),
+                });
+            }
+        }
+    }
+
+    for conflict in duplicates {
+        merge.conflicts.push(conflict);
+        merge.resolve(merge.conflicts.len() - 1, Side::Ours);
+    }
+}
+
+/// Reports records that ended up with the same keyword because of the changes on their side,
+/// and keeps the keyword only for our record
+fn resolve_duplicate_keywords(merge: &mut Merge, ours: &[URLRecord]) {
+    let ours_keywords: HashMap<&str, &str> = ours
+        .iter()
+        .map(|r| (r.id.as_str(), r.keyword.as_str()))
+        .collect();
+    let from_ours = |r: &URLRecord| ours_keywords.get(r.id.as_str()) == Some(&r.keyword.as_str());
+
+    let mut first_by_keyword: HashMap<&str, &URLRecord> = HashMap::new();
+    let mut duplicates = vec![];
+    for record in merge.records.iter().filter(|r| !r.keyword.is_empty()) {
+        match first_by_keyword.get(record.keyword.as_str()) {
+            None => {
+                first_by_keyword.insert(&record.keyword, record);
+            }
+            Some(first) if from_ours(first) && from_ours(record) => {}
+            Some(first) => {
+                let (our, their) = if from_ours(record) {
+                    (record, *first)
+                } else {
+                    (*first, record)
+                };
+                duplicates.push(Conflict {
+                    id: their.id.clone(),
+                    kind: ConflictKind::DuplicateKeyword {
+                        keyword: their.keyword.clone(),
+                    },
+                    ours: Some(our.clone()),
+                    theirs: Some(their.clone()),
+                });
+            }
+        }
+    }
+
+    for conflict in duplicates {
+        merge.conflicts.push(conflict);
+        merge.resolve(merge.conflicts.len() - 1, Side::Ours);
+    }
+}
+
+/// Returns records with the fields merged in favour of each side, together with names of
+/// the fields changed differently on both sides
+fn merge_fields(
+    base: Option<&URLRecord>,
+    ours: &URLRecord,
+    theirs: &URLRecord,
+) -> (URLRecord, URLRecord, Vec<String>) {
+    let values = [
+        (
+            "name",
+            base.map(|b| b.name.as_str()),
+            ours.name.as_str(),
+            theirs.name.as_str(),
+        ),
+        (
+            "url",
+            base.map(|b| b.url.as_str()),
+            ours.url.as_str(),
+            theirs.url.as_str(),
+        ),
+        (
+            "group",
+            base.map(|b| b.group.as_str()),
+            ours.group.as_str(),
+            theirs.group.as_str(),
+        ),
+        (
+            "notes",
+            base.map(|b| b.notes.as_str()),
+            ours.notes.as_str(),
+            theirs.notes.as_str(),
+        ),
+        (
+            "keyword",
+            base.map(|b| b.keyword.as_str()),
+            ours.keyword.as_str(),
+            theirs.keyword.as_str(),
+        ),
+    ];
+
+    let mut merged_ours = [
+        ours.name.clone(),
+        ours.url.clone(),
+        ours.group.clone(),
+        ours.notes.clone(),
+        ours.keyword.clone(),
+    ];
+    let mut merged_theirs = merged_ours.clone();
+    let mut conflicting = vec![];
+    for (i, (field, b, o, t)) in values.iter().enumerate() {
+        if o == t || *b == Some(*t) {
+            continue;
+        }
+        merged_theirs[i] = t.to_string();
+        if *b == Some(*o) {
+            merged_ours[i] = t.to_string();
+        } else {
+            conflicting.push(field.to_string());
+        }
+    }
+
+    // Bundle members are merged as a whole, the same way as the fields above
+    let base_bundle = base.map(|b| &b.bundle);
+    let (bundle_ours, bundle_theirs) =
+        if ours.bundle == theirs.bundle || base_bundle == Some(&theirs.bundle) {
+            (ours.bundle.clone(), ours.bundle.clone())
+        } else if base_bundle == Some(&ours.bundle) {
+            (theirs.bundle.clone(), theirs.bundle.clone())
+        } else {
+            conflicting.push("bundle".to_string());
+            (ours.bundle.clone(), theirs.bundle.clone())
+        };
+
+    let tags = merge_tags(base.map(|b| &b.tags), &ours.tags, &theirs.tags);
+    // Pinning is only a preference, so instead of a conflict the side that changed it wins
+    let pinned = match base {
+        Some(b) if b.pinned == ours.pinned => theirs.pinned,
+        _ => ours.pinned,
+    };
+    let with_fields = |[name, url, group, notes, keyword]: [String; 5], bundle| URLRecord {
+        name,
+        url,
+        group,
+        notes,
+        keyword,
+        bundle,
+        tags: tags.clone(),
+        pinned,
+        created_at: earliest(ours.created_at, theirs.created_at),
+        updated_at: ours.updated_at.max(theirs.updated_at),
+        last_opened_at: ours.last_opened_at.max(theirs.last_opened_at),
+        open_count: ours.open_count.max(theirs.open_count),
+        open_history: merge_open_history(&ours.open_history, &theirs.open_history),
+        ..ours.clone()
+    };
+
+    (
+        with_fields(merged_ours, bundle_ours),
+        with_fields(merged_theirs, bundle_theirs),
+        conflicting,
+    )
+}
+
+/// Returns the earlier of the times, ignoring the missing ones
+fn earliest(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Keeps the most recent opens from both sides
+fn merge_open_history(ours: &[DateTime<Utc>], theirs: &[DateTime<Utc>]) -> Vec<DateTime<Utc>> {
+    let mut history: Vec<DateTime<Utc>> = ours.iter().chain(theirs).copied().collect();
+    history.sort();
+    history.dedup();
+    let excess = history.len().saturating_sub(OPEN_HISTORY_LEN);
+    history.drain(..excess);
+    history
+}
+
+/// Keeps tags present on both sides or added on any side since the base.
+/// If values of the tag differ, the one changed since the base wins, preferring ours.
+fn merge_tags(
+    base: Option<&BTreeMap<String, String>>,
+    ours: &BTreeMap<String, String>,
+    theirs: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let base_value = |tag: &String| base.and_then(|b| b.get(tag));
+
+    let mut tags: BTreeMap<String, String> = ours
+        .iter()
+        .filter(|(tag, _)| theirs.contains_key(*tag) || base_value(tag).is_none())
+        .map(|(tag, value)| match theirs.get(tag) {
+            Some(their_value) if base_value(tag) == Some(value) => {
+                (tag.clone(), their_value.clone())
+            }
+            _ => (tag.clone(), value.clone()),
+        })
+        .collect();
+    for (tag, value) in theirs {
+        if !ours.contains_key(tag) && base_value(tag).is_none() {
+            tags.insert(tag.clone(), value.clone());
+        }
+    }
+
+    tags
+}
+
+/// Keeps records trashed on any side, unless they were restored or purged on the other one
+fn merge_trash(
+    base: &[TrashedRecord],
+    ours: &[TrashedRecord],
+    theirs: &[TrashedRecord],
+) -> Vec<TrashedRecord> {
+    let base_ids: HashSet<&str> = base.iter().map(|t| t.record.id.as_str()).collect();
+    let ours_ids: HashSet<&str> = ours.iter().map(|t| t.record.id.as_str()).collect();
+    let theirs_ids: HashSet<&str> = theirs.iter().map(|t| t.record.id.as_str()).collect();
+
+    ours.iter()
+        .chain(
+            theirs
+                .iter()
+                .filter(|t| !ours_ids.contains(t.record.id.as_str())),
+        )
+        .filter(|t| {
+            let id = t.record.id.as_str();
+            !(base_ids.contains(id) && !(ours_ids.contains(id) && theirs_ids.contains(id)))
+        })
+        .cloned()
+        .collect()
+}
+
+fn by_id(records: &[URLRecord]) -> HashMap<&str, &URLRecord> {
+    records.iter().map(|r| (r.id.as_str(), r)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::merge::{merge_records, merge_registries, ConflictKind, Side};
+    use crate::types::{BundleMember, TrashedRecord, URLRecord, URLRegistry};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn merge_records_test() {
+        let one = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let two = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+        let three = URLRecord::new("https://three.com", "three", "dev", vec!["old"]);
+        let four = URLRecord::new("https://four.com", "four", "dev", Vec::<String>::new());
+        let added_ours = URLRecord::new("https://ours.com", "ours", "dev", Vec::<String>::new());
+        let added_theirs =
+            URLRecord::new("https://theirs.com", "theirs", "dev", Vec::<String>::new());
+
+        let base = vec![one.clone(), two.clone(), three.clone(), four.clone()];
+
+        let mut one_ours = one.clone();
+        one_ours.name = "one-ours".to_string();
+        let mut two_theirs = two.clone();
+        two_theirs.group = "other".to_string();
+        two_theirs.pinned = true;
+        let mut three_ours = three.clone();
+        three_ours.url = "https://three-ours.com".to_string();
+        three_ours.tags.insert("ours".to_string(), "".to_string());
+        let mut three_theirs = three.clone();
+        three_theirs.name = "three-theirs".to_string();
+        three_theirs.tags.clear();
+        three_theirs
+            .tags
+            .insert("theirs".to_string(), "".to_string());
+        three_theirs.notes = "Notes\nfrom theirs".to_string();
+
+        let ours = vec![
+            one_ours.clone(),
+            two.clone(),
+            three_ours.clone(),
+            added_ours.clone(),
+        ];
+        let theirs = vec![
+            one.clone(),
+            two_theirs.clone(),
+            three_theirs.clone(),
+            four.clone(),
+            added_theirs.clone(),
+        ];
+
+        println!("Should merge changes of different fields and join added tags...");
+        let merge = merge_records(&base, &ours, &theirs, Utc::now());
+        let mut three_merged = three_ours.clone();
+        three_merged.name = "three-theirs".to_string();
+        three_merged.tags.remove("old");
+        three_merged
+            .tags
+            .insert("theirs".to_string(), "".to_string());
+        three_merged.notes = "Notes\nfrom theirs".to_string();
+        assert_eq!(
+            merge.records,
+            vec![
+                one_ours,
+                two_theirs,
+                three_merged.clone(),
+                added_ours,
+                added_theirs
+            ]
+        );
+        assert!(merge.conflicts.is_empty());
+
+        println!("Should report fields changed differently on both sides...");
+        let mut three_other = three.clone();
+        three_other.url = "https://three-other.com".to_string();
+        let theirs = vec![one.clone(), two.clone(), three_other, four.clone()];
+        let mut merge = merge_records(&base, &ours, &theirs, Utc::now());
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(
+            merge.conflicts[0].kind,
+            ConflictKind::Fields {
+                fields: vec!["url".to_string()]
+            }
+        );
+        assert!(merge.records.contains(&three_ours));
+
+        println!("Should resolve conflict in favour of their side...");
+        merge.resolve(0, Side::Theirs);
+        let mut three_resolved = three_ours.clone();
+        three_resolved.url = "https://three-other.com".to_string();
+        assert!(merge.records.contains(&three_resolved));
+
+        println!("Should keep record modified on one side and deleted on the other...");
+        let theirs = vec![three_theirs.clone(), four.clone()];
+        let merge = merge_records(&base, &[], &theirs, Utc::now());
+        assert!(merge.records.contains(&three_theirs));
+        assert!(!merge.records.contains(&four));
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].kind, ConflictKind::Deleted);
+        assert_eq!(merge.conflicts[0].ours, None);
+    }
+
+    #[test]
+    fn merge_duplicates_test() {
+        let one = URLRecord::new("https://one.com", "one", "default", Vec::<String>::new());
+        let other_one = URLRecord::new("https://other.com", "one", "default", Vec::<String>::new());
+
+        println!("Should keep only our record with the same name in the group...");
+        let ours = vec![one.clone()];
+        let theirs = vec![other_one.clone()];
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut merge = merge_records(&[], &ours, &theirs, now);
+        assert_eq!(merge.records, vec![one.clone()]);
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].kind, ConflictKind::Duplicate);
+        assert_eq!(merge.trash[0].record, other_one);
+        assert_eq!(merge.trash[0].deleted_at, now);
+
+        println!("Should swap records when resolved in favour of their side...");
+        merge.resolve(0, Side::Theirs);
+        assert_eq!(merge.records, vec![other_one]);
+        assert_eq!(merge.trash.len(), 1);
+        assert_eq!(merge.trash[0].record, one);
+    }
+
+    #[test]
+    fn merge_bundle_test() {
+        let url = |u: &str| BundleMember::Url(u.to_string());
+        let base = URLRecord::new_bundle(
+            "daily",
+            "default",
+            Vec::<String>::new(),
+            vec![url("https://one.com")],
+            Utc::now(),
+        );
+
+        println!("Should take their members if ours did not change...");
+        let mut ours = base.clone();
+        ours.name = "morning".to_string();
+        let mut theirs = base.clone();
+        theirs.bundle.push(url("https://two.com"));
+        let merge = merge_records(
+            std::slice::from_ref(&base),
+            &[ours.clone()],
+            &[theirs.clone()],
+            Utc::now(),
+        );
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.records[0].name, "morning");
+        assert_eq!(merge.records[0].bundle, theirs.bundle);
+
+        println!("Should keep our members if theirs did not change...");
+        let merge = merge_records(
+            std::slice::from_ref(&base),
+            &[theirs.clone()],
+            &[ours.clone()],
+            Utc::now(),
+        );
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.records[0].bundle, theirs.bundle);
+
+        println!("Should report conflict if both sides changed members...");
+        let mut ours = base.clone();
+        ours.bundle = vec![url("https://ours.com")];
+        let merge = merge_records(
+            std::slice::from_ref(&base),
+            &[ours.clone()],
+            &[theirs.clone()],
+            Utc::now(),
+        );
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(
+            merge.conflicts[0].kind,
+            ConflictKind::Fields {
+                fields: vec!["bundle".to_string()]
+            }
+        );
+        assert_eq!(merge.records[0].bundle, ours.bundle);
+        let conflict_theirs = merge.conflicts[0].theirs.as_ref().expect("Missing theirs");
+        assert_eq!(conflict_theirs.bundle, theirs.bundle);
+    }
+
+    #[test]
+    fn merge_duplicate_keywords_test() {
+        let mut one = URLRecord::new("https://one.com", "one", "default", Vec::<String>::new());
+        one.keyword = "gh".to_string();
+        let mut two = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+        let base = vec![one.clone(), two.clone()];
+        two.keyword = "gh".to_string();
+
+        println!("Should keep both records and the keyword only for ours...");
+        let ours = vec![one.clone(), base[1].clone()];
+        let theirs = vec![base[0].clone(), two.clone()];
+        let mut merge = merge_records(&base, &ours, &theirs, Utc::now());
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(
+            merge.conflicts[0].kind,
+            ConflictKind::DuplicateKeyword {
+                keyword: "gh".to_string()
+            }
+        );
+        assert_eq!(merge.conflicts[0].id, two.id);
+        assert_eq!(merge.records[0], one);
+        assert_eq!(merge.records[1].id, two.id);
+        assert!(merge.records[1].keyword.is_empty());
+        assert!(merge.trash.is_empty());
+
+        println!("Should move the keyword when resolved in favour of their side...");
+        merge.resolve(0, Side::Theirs);
+        assert!(merge.records[0].keyword.is_empty());
+        assert_eq!(merge.records[1], two);
+        assert!(merge.trash.is_empty());
+
+        println!("Should not report keywords clashing already on our side...");
+        let mut three = URLRecord::new("https://three.com", "three", "dev", Vec::<String>::new());
+        three.keyword = "gh".to_string();
+        let ours = vec![one.clone(), three.clone()];
+        let merge = merge_records(&[], &ours, &ours, Utc::now());
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_registries_test() {
+        let one = URLRecord::new("https://one.com", "one", "default", vec!["tag"]);
+        let two = URLRecord::new("https://two.com", "two", "default", Vec::<String>::new());
+        let trashed = TrashedRecord::new(
+            URLRecord::new("https://old.com", "old", "default", Vec::<String>::new()),
+            Utc::now(),
+        );
+
+        let mut base = URLRegistry::new(vec![one.clone(), two.clone()]);
+        base.trash = vec![trashed.clone()];
+
+        println!("Should move record deleted on their side to the trash...");
+        let ours = URLRegistry::new(vec![one.clone(), two.clone()]);
+        let mut theirs = URLRegistry::new(vec![one.clone()]);
+        theirs.trash = vec![trashed.clone(), TrashedRecord::new(two.clone(), Utc::now())];
+
+        let merge = merge_registries(&base, &ours, &theirs, Utc::now());
+        assert!(merge.conflicts.is_empty());
+
+        let registry = merge.into_registry();
+        assert_eq!(registry.urls.items, vec![one]);
+
+        println!("Should not bring back record purged on our side...");
+        assert_eq!(registry.trash.len(), 1);
+        assert_eq!(registry.trash[0].record.id, two.id);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::error::Error;
+use crate::format::Format;
+use crate::types::{new_id, URLRegistry};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Version of the registry schema written by this version of the library.
+/// Every change of the stored format requires bumping it and registering
+/// a migration from the previous version.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Migration upgrades the registry from `source_version` to the next schema version.
+/// Migrations operate on the raw JSON so that they do not depend on the current
+/// shape of the `URLRecord`.
+pub trait Migration {
+    fn source_version(&self) -> u32;
+    fn migrate(&self, registry: &mut Value) -> Result<(), Error>;
+}
+
+/// Chain of migrations applied one after another until the registry reaches the `SCHEMA_VERSION`
+pub struct Migrations {
+    migrations: Vec<Box<dyn Migration>>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Migrations::backfilled_at(Utc::now())
+    }
+}
+
+impl Migrations {
+    pub fn new(migrations: Vec<Box<dyn Migration>>) -> Migrations {
+        Migrations { migrations }
+    }
+
+    /// Default migrations, which use given time for the fields that were not tracked before
+    pub fn backfilled_at(time: DateTime<Utc>) -> Migrations {
+        Migrations::new(vec![
+            Box::new(AddRecordIds {}),
+            Box::new(AddTimestamps { time }),
+            Box::new(AddTagValues {}),
+        ])
+    }
+
+    /// Default migrations for the registry stored in the file.
+    /// All records existed when the file was last modified, so its modification time
+    /// is the best guess for the fields that were not tracked before.
+    pub fn for_file<P: AsRef<Path>>(path: P) -> Migrations {
+        let time = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        Migrations::backfilled_at(time)
+    }
+
+    /// Migrates the registry to the `SCHEMA_VERSION`.
+    /// Returns the version from which the registry was migrated.
+    pub fn migrate(&self, registry: &mut Value) -> Result<u32, Error> {
+        let from_version = schema_version(registry);
+        if from_version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedVersion {
+                version: from_version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let mut version = from_version;
+        while version < SCHEMA_VERSION {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.source_version() == version)
+                .ok_or_else(|| {
+                    Error::Storage(format!("no migration registered from version {}", version))
+                })?;
+
+            migration.migrate(registry)?;
+            version += 1;
+        }
+
+        if let Value::Object(map) = registry {
+            map.insert("version".to_string(), Value::from(SCHEMA_VERSION));
+        }
+
+        Ok(from_version)
+    }
+}
+
+/// Returns schema version of the registry.
+/// Registries written before the version was introduced are recognized by their content.
+pub fn schema_version(registry: &Value) -> u32 {
+    if let Some(version) = registry.get("version").and_then(Value::as_u64) {
+        return version as u32;
+    }
+
+    let missing_ids = items(registry)
+        .map(|items| items.iter().any(|i| i.get("id").is_none()))
+        .unwrap_or(false);
+
+    if missing_ids {
+        0
+    } else {
+        1
+    }
+}
+
+/// Parses the registry of any known schema version and migrates it to the current one
+pub fn load_registry(content: &str, format: Format) -> Result<URLRegistry, Error> {
+    if content.trim().is_empty() {
+        return Ok(URLRegistry::new(vec![]));
+    }
+
+    let mut registry: Value = format.parse(content)?;
+    Migrations::default().migrate(&mut registry)?;
+
+    Ok(serde_json::from_value(registry)?)
+}
+
+fn items(registry: &Value) -> Option<&Vec<Value>> {
+    registry
+        .get("urls")
+        .and_then(|urls| urls.get("items"))
+        .and_then(Value::as_array)
+}
+
+fn items_mut(registry: &mut Value) -> Option<&mut Vec<Value>> {
+    registry
+        .get_mut("urls")
+        .and_then(|urls| urls.get_mut("items"))
+        .and_then(Value::as_array_mut)
+}
+
+/// Returns all records of the registry, including the ones in the trash
+fn records_mut(registry: &mut Value) -> Vec<&mut serde_json::Map<String, Value>> {
+    let mut records = vec![];
+    let map = match registry {
+        Value::Object(map) => map,
+        _ => return records,
+    };
+
+    for (key, value) in map.iter_mut() {
+        match (key.as_str(), value) {
+            ("urls", urls) => {
+                if let Some(items) = urls.get_mut("items").and_then(Value::as_array_mut) {
+                    records.extend(items.iter_mut().filter_map(Value::as_object_mut));
+                }
+            }
+            ("trash", Value::Array(trash)) => records.extend(
+                trash
+                    .iter_mut()
+                    .filter_map(|t| t.get_mut("record"))
+                    .filter_map(Value::as_object_mut),
+            ),
+            _ => {}
+        }
+    }
+
+    records
+}
+
+/// Migrates registry from v0.0.x, which did not have record ids
+struct AddRecordIds {}
+
+impl Migration for AddRecordIds {
+    fn source_version(&self) -> u32 {
+        0
+    }
+
+    fn migrate(&self, registry: &mut Value) -> Result<(), Error> {
+        if let Some(items) = items_mut(registry) {
+            for item in items.iter_mut().filter_map(Value::as_object_mut) {
+                if !item.contains_key("id") {
+                    item.insert("id".to_string(), Value::from(new_id()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Migrates registry from v0.2.x, which did not track when records were created, updated
+/// and opened. Creation and update times are set to the given time, while records are
+/// left as never opened.
+struct AddTimestamps {
+    time: DateTime<Utc>,
+}
+
+impl Migration for AddTimestamps {
+    fn source_version(&self) -> u32 {
+        1
+    }
+
+    fn migrate(&self, registry: &mut Value) -> Result<(), Error> {
+        let time = Value::from(self.time.to_rfc3339());
+        for record in records_mut(registry) {
+            for field in ["created_at", "updated_at"] {
+                if !record.contains_key(field) {
+                    record.insert(field.to_string(), time.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Migrates registry from the schema version 2, which stored tags as keys with the `true` value.
+/// Tags become plain tags with empty values and the ones set to `false` are removed.
+struct AddTagValues {}
+
+impl Migration for AddTagValues {
+    fn source_version(&self) -> u32 {
+        2
+    }
+
+    fn migrate(&self, registry: &mut Value) -> Result<(), Error> {
+        for record in records_mut(registry) {
+            if let Some(Value::Object(tags)) = record.get_mut("tags") {
+                tags.retain(|_, value| value.as_bool() != Some(false));
+                for value in tags.values_mut() {
+                    *value = match &*value {
+                        Value::String(_) => continue,
+                        Value::Bool(_) | Value::Null => Value::from(""),
+                        other => Value::from(other.to_string()),
+                    };
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::format::Format;
+    use crate::migration::{load_registry, schema_version, Migration, Migrations};
+    use crate::types::URLRegistry;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn schema_version_test() {
+        assert_eq!(
+            schema_version(&json!({"version": 7, "urls": {"items": []}})),
+            7
+        );
+        assert_eq!(schema_version(&json!({"urls": {"items": []}})), 1);
+        assert_eq!(
+            schema_version(&json!({"urls": {"items": [{"id": "abcd", "name": "a"}]}})),
+            1
+        );
+        assert_eq!(
+            schema_version(&json!({"urls": {"items": [{"name": "a"}]}})),
+            0
+        );
+    }
+
+    #[test]
+    fn load_v0_0_x_registry_test() {
+        let content = r#"{"urls": {"items": [
+            {"url": "https://github.com", "name": "GitHub", "group": "dev", "tags": {"git": true}}
+        ]}}"#;
+
+        let registry = load_registry(content, Format::Json).expect("Failed to load registry");
+        assert_eq!(registry.version, super::SCHEMA_VERSION);
+        assert_eq!(registry.urls.items.len(), 1);
+        assert_eq!(registry.urls.items[0].id.len(), 16);
+        assert_eq!(registry.urls.items[0].name, "GitHub");
+        assert!(registry.urls.items[0].tags.contains_key("git"));
+    }
+
+    #[test]
+    fn backfill_timestamps_test() {
+        let content = r#"{"version": 1, "urls": {"items": [
+            {"id": "abcd", "url": "https://github.com", "name": "GitHub", "group": "dev", "tags": {}}
+        ]}, "trash": [
+            {"record": {"id": "efgh", "url": "https://gitlab.com", "name": "GitLab", "group": "dev", "tags": {}},
+             "deleted_at": "2021-01-01T00:00:00Z"}
+        ]}"#;
+        let time = Utc.with_ymd_and_hms(2020, 6, 1, 12, 0, 0).unwrap();
+
+        let mut registry = Format::Json
+            .parse(content)
+            .expect("Failed to parse registry");
+        Migrations::backfilled_at(time)
+            .migrate(&mut registry)
+            .expect("Failed to migrate");
+        let registry: URLRegistry =
+            serde_json::from_value(registry).expect("Failed to deserialize registry");
+
+        println!("Should set creation and update time of records...");
+        let record = &registry.urls.items[0];
+        assert_eq!(record.created_at, Some(time));
+        assert_eq!(record.updated_at, Some(time));
+        assert_eq!(record.last_opened_at, None);
+
+        println!("Should set creation and update time of trashed records...");
+        let trashed = &registry.trash[0].record;
+        assert_eq!(trashed.created_at, Some(time));
+        assert_eq!(trashed.updated_at, Some(time));
+    }
+
+    #[test]
+    fn add_tag_values_test() {
+        let content = r#"{"version": 2, "urls": {"items": [
+            {"id": "abcd", "url": "https://github.com", "name": "GitHub", "group": "dev",
+             "tags": {"git": true, "old": false}}
+        ]}, "trash": [
+            {"record": {"id": "efgh", "url": "https://gitlab.com", "name": "GitLab", "group": "dev",
+             "tags": {"git": true}}, "deleted_at": "2021-01-01T00:00:00Z"}
+        ]}"#;
+
+        let registry = load_registry(content, Format::Json).expect("Failed to load registry");
+
+        println!("Should convert boolean tags to plain tags...");
+        let record = &registry.urls.items[0];
+        assert_eq!(record.tags.len(), 1);
+        assert_eq!(record.tags["git"], "");
+
+        println!("Should convert tags of trashed records...");
+        assert_eq!(registry.trash[0].record.tags["git"], "");
+    }
+
+    #[test]
+    fn reject_newer_version_test() {
+        let content = r#"{"version": 999, "urls": {"items": []}}"#;
+        assert!(matches!(
+            load_registry(content, Format::Json),
+            Err(Error::UnsupportedVersion { version: 999, .. })
+        ));
+    }
+
+    struct RenameField {
+        from: u32,
+    }
+
+    impl Migration for RenameField {
+        fn source_version(&self) -> u32 {
+            self.from
+        }
+
+        fn migrate(&self, registry: &mut Value) -> Result<(), Error> {
+            registry["applied"]
+                .as_array_mut()
+                .expect("Not an array")
+                .push(Value::from(self.from));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn migrations_chain_test() {
+        // Register out of order to make sure the chain is applied by versions
+        let migrations = Migrations::new(vec![
+            Box::new(RenameField { from: 1 }),
+            Box::new(RenameField { from: 2 }),
+            Box::new(RenameField { from: 0 }),
+        ]);
+
+        let mut registry = json!({"urls": {"items": [{"name": "no-id"}]}, "applied": []});
+        let from = migrations
+            .migrate(&mut registry)
+            .expect("Failed to migrate");
+        assert_eq!(from, 0);
+        assert_eq!(registry["applied"], json!([0, 1, 2]));
+        assert_eq!(registry["version"], json!(super::SCHEMA_VERSION));
+
+        println!("Should fail when migration is missing...");
+        let migrations = Migrations::new(vec![]);
+        let mut registry = json!({"urls": {"items": [{"name": "no-id"}]}});
+        assert!(migrations.migrate(&mut registry).is_err());
+    }
+}
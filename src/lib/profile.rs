@@ -0,0 +1,255 @@
+use crate::error::Error;
+use crate::storage::{lock_file, read_content, write_content};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Serialize, Deserialize, Default)]
+struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+}
+
+/// Profiles keeps separate registries, e.g. for work and personal bookmarks, in the config directory.
+/// Registries of the default profile are stored directly in the config directory,
+/// so that the files from before profiles were introduced are still used.
+/// Other profiles are stored in the `profiles/<NAME>` subdirectories.
+/// Profile used by default is saved in the `config.json` file.
+pub struct Profiles {
+    config_dir: PathBuf,
+}
+
+impl Profiles {
+    pub fn new<P: Into<PathBuf>>(config_dir: P) -> Profiles {
+        Profiles {
+            config_dir: config_dir.into(),
+        }
+    }
+
+    /// Lists profiles, starting with the default one
+    pub fn list(&self) -> Result<Vec<String>, Error> {
+        let mut profiles = vec![];
+
+        let dir = self.config_dir.join(PROFILES_DIR);
+        if dir.exists() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                if let Some(name) = entry.file_name().to_str() {
+                    if is_valid_name(name) && name != DEFAULT_PROFILE {
+                        profiles.push(name.to_string());
+                    }
+                }
+            }
+        }
+        profiles.sort();
+        profiles.insert(0, DEFAULT_PROFILE.to_string());
+
+        Ok(profiles)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || (is_valid_name(name) && self.profile_dir(name).is_dir())
+    }
+
+    pub fn create(&self, name: &str) -> Result<(), Error> {
+        if !is_valid_name(name) {
+            return Err(Error::InvalidProfileName(name.to_string()));
+        }
+        if self.exists(name) {
+            return Err(Error::ProfileExists(name.to_string()));
+        }
+
+        fs::create_dir_all(self.profile_dir(name))?;
+        Ok(())
+    }
+
+    /// Deletes the profile together with all its registries.
+    /// If the profile was used by default, the default profile is used instead.
+    pub fn delete(&self, name: &str) -> Result<(), Error> {
+        if name == DEFAULT_PROFILE {
+            return Err(Error::InvalidProfileName(name.to_string()));
+        }
+        if !self.exists(name) {
+            return Err(Error::ProfileNotFound(name.to_string()));
+        }
+
+        fs::remove_dir_all(self.profile_dir(name))?;
+        self.modify_config(|config| {
+            if config.profile.as_deref() == Some(name) {
+                config.profile = None;
+            }
+        })
+    }
+
+    /// Returns profile used by default
+    pub fn current(&self) -> Result<String, Error> {
+        let config = self.read_config()?;
+
+        match config.profile {
+            Some(profile) if self.exists(&profile) => Ok(profile),
+            _ => Ok(DEFAULT_PROFILE.to_string()),
+        }
+    }
+
+    /// Sets profile used by default
+    pub fn set_current(&self, name: &str) -> Result<(), Error> {
+        if !self.exists(name) {
+            return Err(Error::ProfileNotFound(name.to_string()));
+        }
+
+        self.modify_config(|config| {
+            config.profile = match name {
+                DEFAULT_PROFILE => None,
+                _ => Some(name.to_string()),
+            }
+        })
+    }
+
+    /// Returns path of the registry file of the profile
+    pub fn registry_path(&self, name: &str, file_name: &str) -> Result<PathBuf, Error> {
+        if !self.exists(name) {
+            return Err(Error::ProfileNotFound(name.to_string()));
+        }
+
+        match name {
+            DEFAULT_PROFILE => Ok(self.config_dir.join(file_name)),
+            _ => Ok(self.profile_dir(name).join(file_name)),
+        }
+    }
+
+    fn profile_dir(&self, name: &str) -> PathBuf {
+        self.config_dir.join(PROFILES_DIR).join(name)
+    }
+
+    fn config_path(&self) -> String {
+        self.config_dir
+            .join(CONFIG_FILE)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn read_config(&self) -> Result<Config, Error> {
+        match read_content(&self.config_path())? {
+            Some(content) => Ok(serde_json::from_str(&content)?),
+            None => Ok(Config::default()),
+        }
+    }
+
+    fn modify_config<F>(&self, modify: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Config),
+    {
+        let path = self.config_path();
+        let _lock = lock_file(&path, true)?;
+
+        let mut config = self.read_config()?;
+        modify(&mut config);
+        write_content(&path, &serde_json::to_string(&config)?)
+    }
+}
+
+/// Profile names are used as directory names, so only letters, digits, '-' and '_' are allowed
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::profile::{Profiles, DEFAULT_PROFILE};
+    use crate::util::create_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn profiles_test() {
+        let dir = create_temp_dir("profiles_test").expect("Failed to create temp dir");
+        let profiles = Profiles::new(&dir);
+
+        println!("Should have only default profile at start...");
+        assert_eq!(
+            profiles.list().expect("Failed to list profiles"),
+            vec![DEFAULT_PROFILE]
+        );
+        assert_eq!(
+            profiles.current().expect("Failed to get profile"),
+            DEFAULT_PROFILE
+        );
+        assert_eq!(
+            profiles
+                .registry_path(DEFAULT_PROFILE, "urls.json")
+                .expect("Failed to get path"),
+            dir.join("urls.json")
+        );
+
+        println!("Should create profiles...");
+        profiles.create("work").expect("Failed to create profile");
+        profiles.create("home-2").expect("Failed to create profile");
+        assert_eq!(
+            profiles.list().expect("Failed to list profiles"),
+            vec![DEFAULT_PROFILE, "home-2", "work"]
+        );
+        assert_eq!(
+            profiles
+                .registry_path("work", "urls.json")
+                .expect("Failed to get path"),
+            dir.join("profiles").join("work").join("urls.json")
+        );
+
+        println!("Should not create invalid or existing profiles...");
+        assert!(matches!(
+            profiles.create("work"),
+            Err(Error::ProfileExists(_))
+        ));
+        assert!(matches!(
+            profiles.create(DEFAULT_PROFILE),
+            Err(Error::ProfileExists(_))
+        ));
+        for name in ["", "../work", "a b", "a/b"] {
+            assert!(matches!(
+                profiles.create(name),
+                Err(Error::InvalidProfileName(_))
+            ));
+        }
+
+        println!("Should switch current profile...");
+        profiles.set_current("work").expect("Failed to set profile");
+        assert_eq!(profiles.current().expect("Failed to get profile"), "work");
+        assert!(matches!(
+            profiles.set_current("other"),
+            Err(Error::ProfileNotFound(_))
+        ));
+        assert!(matches!(
+            profiles.registry_path("other", "urls.json"),
+            Err(Error::ProfileNotFound(_))
+        ));
+
+        println!("Should delete profile and fall back to default one...");
+        profiles.delete("work").expect("Failed to delete profile");
+        assert_eq!(
+            profiles.current().expect("Failed to get profile"),
+            DEFAULT_PROFILE
+        );
+        assert_eq!(
+            profiles.list().expect("Failed to list profiles"),
+            vec![DEFAULT_PROFILE, "home-2"]
+        );
+        assert!(matches!(
+            profiles.delete(DEFAULT_PROFILE),
+            Err(Error::InvalidProfileName(_))
+        ));
+
+        println!("Cleanup...");
+        fs::remove_dir_all(dir).expect("Failed to remove dir");
+    }
+}
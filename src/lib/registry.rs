@@ -1,43 +1,259 @@
+use crate::backup::{BackupPolicy, Snapshot};
+use crate::clock::{Clock, SystemClock};
+use crate::diff::{diff, Change};
+use crate::doctor;
+use crate::doctor::Problem;
+use crate::encrypted::EncryptedStorage;
+use crate::error::Error;
 use crate::filters::{Filter, NoopFilter};
-use crate::sort::{sort_urls, SortConfig};
-use crate::storage::FileStorage;
-use crate::types::URLRecord;
+use crate::format::Format;
+use crate::groups;
+use crate::history::{HistoryEntry, Journal};
+use crate::memory::MemoryStorage;
+use crate::sort::{pinned_first, sort_urls, SortConfig};
+use crate::sqlite::SqliteStorage;
+use crate::storage::{ensure_keyword_unique, is_unique, read_registry_file, FileStorage};
+use crate::sync::GitSync;
+use crate::transaction::Transaction;
+use crate::types::{parse_keyword, parse_tag, BundleMember, TrashedRecord, URLRecord};
 use crate::util::create_temp_file;
 use crate::{Importer, Registry, RegistryReader, Repository};
-use std::error::Error;
+use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 
-// TODO: consider introducing custom errors
-
 pub const DEFAULT_GROUP: &str = "default";
 
+const REPAIR_OPERATION: &str = "repair";
+
+/// Number of times the update is attempted if the record is changed in the meantime
+const UPDATE_ATTEMPTS: usize = 3;
+
 pub struct URLRegistry<T: Repository> {
     storage: T,
     default_filter: Box<dyn Filter>,
+    history: Journal,
+    git: Option<GitSync>,
+    clock: Box<dyn Clock>,
 }
 
 impl URLRegistry<FileStorage> {
     pub fn new_file_based(file_path: String) -> URLRegistry<FileStorage> {
+        let history = Journal::for_registry(&file_path);
         let storage = FileStorage::new_urls_repository(file_path);
 
         URLRegistry {
             storage,
             default_filter: Box::new(NoopFilter::default()),
+            history,
+            git: None,
+            clock: Box::new(SystemClock::default()),
         }
     }
 
-    pub fn with_temp_file(
-        suffix: &str,
-    ) -> Result<(URLRegistry<FileStorage>, PathBuf), Box<dyn std::error::Error>> {
+    pub fn with_temp_file(suffix: &str) -> Result<(URLRegistry<FileStorage>, PathBuf), Error> {
         let file_path = create_temp_file(suffix)?;
 
         match file_path.to_str() {
             Some(path) => Ok((URLRegistry::new_file_based(path.to_string()), file_path)),
-            None => Err(From::from(
-                "failed to initialized registry with temp file, path is None",
+            None => Err(Error::Storage(
+                "failed to initialized registry with temp file, path is None".to_string(),
             )),
         }
     }
+
+    /// Enables rotating snapshots of the registry file, taken before every change
+    pub fn with_backups(self, policy: BackupPolicy) -> URLRegistry<FileStorage> {
+        URLRegistry {
+            storage: self.storage.with_backups(policy),
+            ..self
+        }
+    }
+
+    /// Writes the registry file in the given format, converting it on the next change
+    pub fn with_format(self, format: Format) -> URLRegistry<FileStorage> {
+        URLRegistry {
+            storage: self.storage.with_format(format),
+            ..self
+        }
+    }
+
+    /// Commits the registry file to the git repository after every change
+    pub fn with_git(self, git: GitSync) -> URLRegistry<FileStorage> {
+        URLRegistry {
+            git: Some(git),
+            ..self
+        }
+    }
+
+    /// Lists snapshots of the registry file starting from the most recent one
+    pub fn list_backups(&self) -> Result<Vec<Snapshot>, Error> {
+        self.storage.backups().list()
+    }
+
+    /// Returns changes that restoring the snapshot would apply to the current registry
+    pub fn backup_diff(&self, snapshot_id: &str) -> Result<Vec<Change>, Error> {
+        let snapshot = self.read_backup(snapshot_id)?;
+        Ok(diff(&self.storage.list()?, &snapshot))
+    }
+
+    /// Restores the registry to the state from the snapshot and returns applied changes.
+    /// The changes are recorded in the history, so the restore can be reverted with `undo`.
+    pub fn restore_backup(&self, snapshot_id: &str) -> Result<Vec<Change>, Error> {
+        let snapshot = self.read_backup(snapshot_id)?;
+        let previous = self.storage.replace_all(snapshot.clone())?;
+
+        let changes = diff(&previous, &snapshot);
+        self.record(HistoryEntry::new(
+            &format!("restore snapshot '{}'", snapshot_id),
+            changes.clone(),
+        ))?;
+
+        Ok(changes)
+    }
+
+    /// Reads records from the snapshot
+    pub fn read_backup(&self, snapshot_id: &str) -> Result<Vec<URLRecord>, Error> {
+        let backups = self.storage.backups();
+        let snapshot = backups
+            .get(snapshot_id)?
+            .ok_or_else(|| Error::SnapshotNotFound(snapshot_id.to_string()))?;
+
+        Ok(backups.load(&snapshot)?.urls.items)
+    }
+}
+
+impl URLRegistry<SqliteStorage> {
+    pub fn new_sqlite(file_path: &str) -> Result<URLRegistry<SqliteStorage>, Error> {
+        let storage = SqliteStorage::new_urls_repository(file_path)?;
+
+        Ok(URLRegistry {
+            storage,
+            default_filter: Box::new(NoopFilter::default()),
+            history: Journal::for_registry(file_path),
+            git: None,
+            clock: Box::new(SystemClock::default()),
+        })
+    }
+}
+
+impl URLRegistry<EncryptedStorage> {
+    /// Opens the registry file encrypted with the passphrase, creating it if it does not exist.
+    /// History of operations is encrypted with the same key.
+    pub fn new_encrypted(
+        file_path: &str,
+        passphrase: &str,
+    ) -> Result<URLRegistry<EncryptedStorage>, Error> {
+        let storage = EncryptedStorage::open(file_path, passphrase)?;
+        Ok(URLRegistry::from_encrypted_storage(file_path, storage))
+    }
+
+    fn from_encrypted_storage(
+        file_path: &str,
+        storage: EncryptedStorage,
+    ) -> URLRegistry<EncryptedStorage> {
+        let history = Journal::encrypted_for_registry(file_path, storage.cipher().clone());
+
+        URLRegistry {
+            storage,
+            default_filter: Box::new(NoopFilter::default()),
+            history,
+            git: None,
+            clock: Box::new(SystemClock::default()),
+        }
+    }
+}
+
+impl URLRegistry<MemoryStorage> {
+    pub fn new_in_memory() -> URLRegistry<MemoryStorage> {
+        URLRegistry {
+            storage: MemoryStorage::default(),
+            default_filter: Box::new(NoopFilter::default()),
+            history: Journal::in_memory(),
+            git: None,
+            clock: Box::new(SystemClock::default()),
+        }
+    }
+
+    /// Creates in-memory registry from the JSON in the same format as used by the FileStorage
+    pub fn from_json(json: &str) -> Result<URLRegistry<MemoryStorage>, Error> {
+        Ok(URLRegistry {
+            storage: MemoryStorage::from_json(json)?,
+            default_filter: Box::new(NoopFilter::default()),
+            history: Journal::in_memory(),
+            git: None,
+            clock: Box::new(SystemClock::default()),
+        })
+    }
+
+    /// Dumps registry to the JSON in the same format as used by the FileStorage
+    pub fn to_json(&self) -> Result<String, Error> {
+        self.storage.to_json()
+    }
+}
+
+impl<T: Repository> URLRegistry<T> {
+    /// Records the operation in the history and commits it, if the registry is kept in git
+    fn record(&self, entry: HistoryEntry) -> Result<(), Error> {
+        let message = entry.describe();
+        self.history.record(entry)?;
+        self.commit(&message)
+    }
+
+    fn commit(&self, message: &str) -> Result<(), Error> {
+        if let Some(git) = &self.git {
+            git.commit(message)?;
+        }
+        Ok(())
+    }
+
+    /// Uses the clock for all times recorded by the registry and for computing the frecency
+    pub fn with_clock<C: Clock + 'static>(self, clock: C) -> URLRegistry<T> {
+        URLRegistry {
+            clock: Box::new(clock),
+            ..self
+        }
+    }
+
+    /// Applies the change to the record with given id, saves it and records the operation.
+    /// If the record is changed in the meantime, the change is applied again to its new version.
+    fn update_record<F>(&self, id: &str, operation: &str, change: F) -> Result<URLRecord, Error>
+    where
+        F: Fn(&mut URLRecord),
+    {
+        let mut attempts = UPDATE_ATTEMPTS;
+        loop {
+            let before = self
+                .storage
+                .get(id)?
+                .ok_or_else(|| Error::NotFound(id.to_string()))?;
+            let mut after = before.clone();
+            change(&mut after);
+            if after == before {
+                return Ok(after);
+            }
+            after.updated_at = Some(self.clock.now());
+
+            let changes = vec![Change::Modified {
+                before,
+                after: after.clone(),
+            }];
+            match self.apply_changes(&changes) {
+                Ok(()) => {
+                    self.record(HistoryEntry::new(operation, changes))?;
+                    return Ok(after);
+                }
+                Err(Error::Conflict(_)) if attempts > 1 => attempts -= 1,
+                Err(why) => return Err(why),
+            }
+        }
+    }
+
+    /// Applies changes to the storage, as long as none of the records was changed in the meantime.
+    /// Removed records are moved to the trash and added ones are taken back from it if possible,
+    /// so that no bookmark is lost when reverting operations.
+    fn apply_changes(&self, changes: &[Change]) -> Result<(), Error> {
+        self.storage.apply(changes, self.clock.now())
+    }
 }
 
 impl<T: Repository> Registry for URLRegistry<T> {
@@ -47,89 +263,355 @@ impl<T: Repository> Registry for URLRegistry<T> {
         url: &str,
         group: Option<&str>,
         tags: Vec<String>,
-    ) -> Result<URLRecord, Box<dyn std::error::Error>> {
-        let group = group.unwrap_or(DEFAULT_GROUP);
+    ) -> Result<URLRecord, Error> {
+        let group = groups::normalize(group.unwrap_or(DEFAULT_GROUP));
+        if name.is_empty() {
+            return Err(Error::EmptyField("Name"));
+        }
+        if url.is_empty() {
+            return Err(Error::EmptyField("URL"));
+        }
+        if group.is_empty() {
+            return Err(Error::EmptyField("Group"));
+        }
+
+        self.add(URLRecord::new_at(url, name, &group, tags, self.clock.now()))
+    }
 
-        let record = URLRecord::new(url, name, group, tags);
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+        let record = self.storage.add(record)?;
+        self.record(HistoryEntry::new(
+            "add",
+            vec![Change::Added(record.clone())],
+        ))?;
 
-        self.storage.add(record)
+        Ok(record)
     }
 
-    fn add(&self, record: URLRecord) -> Result<URLRecord, Box<dyn Error>> {
-        self.storage.add(record)
+    fn create_bundle(
+        &self,
+        name: &str,
+        group: Option<&str>,
+        tags: Vec<String>,
+        members: Vec<String>,
+    ) -> Result<URLRecord, Error> {
+        let group = groups::normalize(group.unwrap_or(DEFAULT_GROUP));
+        if name.is_empty() {
+            return Err(Error::EmptyField("Name"));
+        }
+        if group.is_empty() {
+            return Err(Error::EmptyField("Group"));
+        }
+        if members.is_empty() {
+            return Err(Error::EmptyField("Bundle"));
+        }
+
+        let mut bundle = vec![];
+        for member in members {
+            let member = member.trim();
+            match self.storage.get(member)? {
+                Some(record) if record.is_bundle() => {
+                    return Err(Error::InvalidBundleMember(member.to_string()))
+                }
+                Some(record) => bundle.push(BundleMember::Bookmark(record.id)),
+                None if BundleMember::is_url(member) => {
+                    bundle.push(BundleMember::Url(member.to_string()))
+                }
+                None => return Err(Error::InvalidBundleMember(member.to_string())),
+            }
+        }
+
+        self.add(URLRecord::new_bundle(
+            name,
+            &group,
+            tags,
+            bundle,
+            self.clock.now(),
+        ))
     }
 
-    fn delete(&self, id: &str) -> Result<bool, Box<dyn Error>> {
-        self.storage.delete_by_id(id)
+    fn delete(&self, id: &str) -> Result<bool, Error> {
+        match self.storage.trash(id, self.clock.now())? {
+            Some(trashed) => {
+                self.record(HistoryEntry::new(
+                    "delete",
+                    vec![Change::Removed(trashed.record)],
+                ))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
-    fn list_groups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+        self.storage.list_trash()
+    }
+
+    fn restore(&self, id: &str) -> Result<URLRecord, Error> {
+        let record = self
+            .storage
+            .restore(id)?
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        self.record(HistoryEntry::new(
+            "restore from trash",
+            vec![Change::Added(record.clone())],
+        ))?;
+
+        Ok(record)
+    }
+
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error> {
+        let purged = self.storage.purge(deleted_before)?;
+        if !purged.is_empty() {
+            self.commit(&format!("purge {} bookmarks from trash", purged.len()))?;
+        }
+
+        Ok(purged)
+    }
+
+    fn list_groups(&self) -> Result<Vec<String>, Error> {
         self.storage.list_groups()
     }
 
-    fn tag(&self, id: &str, tag: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        if tag == "" {
-            return Err(From::from("Tag cannot be an empty string"));
+    fn tag(&self, id: &str, tag: &str) -> Result<URLRecord, Error> {
+        if parse_tag(tag).0.is_empty() {
+            return Err(Error::EmptyField("Tag"));
         }
 
-        let record = self.storage.get(id)?; // TODO: what should be returned here
+        self.update_record(id, "tag", |record| {
+            record.set_tag(tag);
+        })
+    }
 
-        record.map_or(Ok(None), |mut record| {
-            record.tags.entry(tag.to_string()).or_insert(true);
-            self.storage.update(id, record)
+    fn untag(&self, id: &str, tag: &str) -> Result<URLRecord, Error> {
+        if parse_tag(tag).0.is_empty() {
+            return Err(Error::EmptyField("Tag"));
+        }
+        self.update_record(id, "untag", |record| {
+            record.remove_tag(tag);
         })
     }
 
-    fn untag(&self, id: &str, tag: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        if tag == "" {
-            return Err(From::from("Tag cannot be an empty string"));
+    fn change_group(&self, id: &str, group: &str) -> Result<URLRecord, Error> {
+        let group = groups::normalize(group);
+        if group.is_empty() {
+            return Err(Error::EmptyField("Group"));
         }
-        let record = self.storage.get(id)?;
 
-        record.map_or(Ok(None), |mut record| {
-            record.tags.remove(tag);
-            self.storage.update(id, record)
+        self.update_record(id, "change group", |record| {
+            record.group = group.clone();
         })
     }
 
-    fn change_group(&self, id: &str, group: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        if group == "" {
-            return Err(From::from("Group cannot be an empty string"));
+    fn move_group(&self, from: &str, to: &str) -> Result<Vec<URLRecord>, Error> {
+        let (from, to) = (groups::normalize(from), groups::normalize(to));
+        if from.is_empty() || to.is_empty() {
+            return Err(Error::EmptyField("Group"));
+        }
+
+        let now = self.clock.now();
+        let changes: Vec<Change> = self
+            .storage
+            .list()?
+            .into_iter()
+            .filter_map(|before| {
+                let group = groups::moved(&before.group, &from, &to)?;
+                if group == before.group {
+                    return None;
+                }
+                let after = URLRecord {
+                    group,
+                    updated_at: Some(now),
+                    ..before.clone()
+                };
+                Some(Change::Modified { before, after })
+            })
+            .collect();
+        if changes.is_empty() {
+            return Ok(vec![]);
         }
 
-        let record = self.storage.get(id)?;
+        self.apply_changes(&changes)?;
+        self.record(HistoryEntry::new("move group", changes.clone()))?;
 
-        record.map_or(Ok(None), |mut record| {
-            record.group = group.to_string();
-            self.storage.update(id, record)
+        Ok(changes
+            .into_iter()
+            .filter_map(|c| match c {
+                Change::Modified { after, .. } => Some(after),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn pin(&self, id: &str) -> Result<URLRecord, Error> {
+        self.update_record(id, "pin", |record| {
+            record.pinned = true;
         })
     }
 
-    fn change_name(&self, id: &str, name: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        if name == "" {
-            return Err(From::from("Name cannot be an empty string"));
-        }
+    fn unpin(&self, id: &str) -> Result<URLRecord, Error> {
+        self.update_record(id, "unpin", |record| {
+            record.pinned = false;
+        })
+    }
 
-        let record = self.storage.get(id)?;
+    fn set_keyword(&self, id: &str, keyword: &str) -> Result<URLRecord, Error> {
+        let keyword = parse_keyword(keyword)?;
 
-        record.map_or(Ok(None), |mut record| {
-            record.name = name.to_string();
-            self.storage.update(id, record)
+        self.update_record(id, "set keyword", |record| {
+            record.keyword = keyword.clone();
+        })
+    }
+
+    fn remove_keyword(&self, id: &str) -> Result<URLRecord, Error> {
+        self.update_record(id, "remove keyword", |record| {
+            record.keyword.clear();
         })
     }
 
-    fn change_url(&self, id: &str, url: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        if url == "" {
-            return Err(From::from("URL cannot be an empty string"));
+    fn change_name(&self, id: &str, name: &str) -> Result<URLRecord, Error> {
+        if name.is_empty() {
+            return Err(Error::EmptyField("Name"));
         }
 
-        let record = self.storage.get(id)?;
+        self.update_record(id, "change name", |record| {
+            record.name = name.to_string();
+        })
+    }
+
+    fn change_url(&self, id: &str, url: &str) -> Result<URLRecord, Error> {
+        if url.is_empty() {
+            return Err(Error::EmptyField("URL"));
+        }
 
-        record.map_or(Ok(None), |mut record| {
+        self.update_record(id, "change url", |record| {
             record.url = url.to_string();
-            self.storage.update(id, record)
         })
     }
+
+    fn change_notes(&self, id: &str, notes: &str) -> Result<URLRecord, Error> {
+        self.update_record(id, "change notes", |record| {
+            record.notes = notes.to_string();
+        })
+    }
+
+    fn mark_opened(&self, id: &str) -> Result<URLRecord, Error> {
+        let record = self
+            .storage
+            .record_open(id, self.clock.now())?
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        self.commit(&format!("open {}", record.name))?;
+
+        Ok(record)
+    }
+
+    fn apply_merge(
+        &self,
+        ours: &[URLRecord],
+        records: Vec<URLRecord>,
+    ) -> Result<Vec<Change>, Error> {
+        for (i, record) in records.iter().enumerate() {
+            if !is_unique(&records[..i], record) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+            ensure_keyword_unique(&records[..i], record)?;
+        }
+
+        let changes = diff(ours, &records);
+        self.apply_changes(&changes)?;
+        self.record(HistoryEntry::new("merge", changes.clone()))?;
+
+        Ok(changes)
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    fn diagnose(&self) -> Result<Vec<Problem>, Error> {
+        Ok(doctor::diagnose(&self.storage.list()?))
+    }
+
+    fn repair(&self) -> Result<Vec<Problem>, Error> {
+        let previous = self.storage.list()?;
+        let (records, problems) = doctor::repair(&previous);
+
+        // Records are matched by position, as ids might be duplicated before the repair
+        let changes: Vec<Change> = previous
+            .into_iter()
+            .zip(records)
+            .filter(|(before, after)| before != after)
+            .map(|(before, after)| Change::Modified { before, after })
+            .collect();
+        if !changes.is_empty() {
+            self.apply_changes(&changes)?;
+            self.record(HistoryEntry::new(REPAIR_OPERATION, changes))?;
+        }
+
+        Ok(problems)
+    }
+
+    fn transaction<F, R>(&self, operations: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut Transaction) -> Result<R, Error>,
+    {
+        let mut tx = Transaction::new(self.storage.list()?, self.clock.now());
+        let result = operations(&mut tx)?;
+
+        let changes = tx.changes();
+        if !changes.is_empty() {
+            self.apply_changes(&changes)?;
+            self.record(HistoryEntry::new("batch update", changes))?;
+        }
+
+        Ok(result)
+    }
+
+    fn undo(&self) -> Result<Option<HistoryEntry>, Error> {
+        let entry = self.history.modify(|log| {
+            let entry = match log.undo.last() {
+                Some(entry) => entry.clone(),
+                None => return Ok(None),
+            };
+
+            if entry.operation == REPAIR_OPERATION {
+                // Undoing the repair brings back the duplicates it has fixed
+                self.storage
+                    .apply_unchecked(&entry.reverted(), self.clock.now())?;
+            } else {
+                self.apply_changes(&entry.reverted())?;
+            }
+            log.undo.pop();
+            log.redo.push(entry.clone());
+
+            Ok(Some(entry))
+        })?;
+
+        if let Some(entry) = &entry {
+            self.commit(&format!("undo {}", entry.describe()))?;
+        }
+        Ok(entry)
+    }
+
+    fn redo(&self) -> Result<Option<HistoryEntry>, Error> {
+        let entry = self.history.modify(|log| {
+            let entry = match log.redo.last() {
+                Some(entry) => entry.clone(),
+                None => return Ok(None),
+            };
+
+            self.apply_changes(&entry.changes)?;
+            log.redo.pop();
+            log.undo.push(entry.clone());
+
+            Ok(Some(entry))
+        })?;
+
+        if let Some(entry) = &entry {
+            self.commit(&format!("redo {}", entry.describe()))?;
+        }
+        Ok(entry)
+    }
 }
 
 impl<T: Repository> RegistryReader for URLRegistry<T> {
@@ -137,57 +619,102 @@ impl<T: Repository> RegistryReader for URLRegistry<T> {
         &self,
         filter: Option<&dyn Filter>,
         sort: Option<SortConfig>,
-    ) -> Result<Vec<URLRecord>, Box<dyn std::error::Error>> {
-        let urls = self.storage.list()?;
-
+    ) -> Result<Vec<URLRecord>, Error> {
         let filter = filter.unwrap_or_else(|| self.default_filter.as_ref());
 
+        let urls = match filter.storage_query() {
+            Some(query) => self.storage.list_matching(&query)?,
+            None => self.storage.list()?,
+        };
+
         let urls = urls.into_iter().filter(|url| filter.matches(url)).collect();
 
-        if let Some(sort_cfg) = sort {
-            return Ok(sort_urls(urls, &sort_cfg));
-        }
+        let urls = match sort {
+            Some(sort_cfg) => sort_urls(urls, &sort_cfg, self.clock.now()),
+            None => urls,
+        };
 
-        Ok(urls)
+        Ok(pinned_first(urls))
     }
 
-    fn get_url(&self, id: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
+    fn get_url(&self, id: &str) -> Result<Option<URLRecord>, Error> {
         self.storage.get(id)
     }
+
+    fn urls_to_open(&self, record: &URLRecord) -> Result<Vec<String>, Error> {
+        if !record.is_bundle() {
+            return Ok(vec![record.url.clone()]);
+        }
+
+        record
+            .bundle
+            .iter()
+            .map(|member| match member {
+                BundleMember::Url(url) => Ok(url.clone()),
+                BundleMember::Bookmark(id) => self
+                    .storage
+                    .get(id)?
+                    .map(|r| r.url)
+                    .ok_or_else(|| Error::NotFound(id.to_string())),
+            })
+            .collect()
+    }
+
+    fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error> {
+        let keyword = keyword.trim();
+        if keyword.is_empty() {
+            return Ok(None);
+        }
+
+        self.storage.get_by_keyword(keyword)
+    }
 }
 
 impl<T: Repository> Importer for URLRegistry<T> {
     // TODO: opts for overriding dups, opt for migrating only unique
-    fn import_from_v_0_0_x(&self, path: &str) -> Result<Vec<URLRecord>, Box<dyn Error>> {
-        let old_urls = self.storage.list_v_0_0_x(path)?;
-        let urls: Vec<URLRecord> = old_urls
+    /// Imports URLs from the file of any known schema version.
+    /// Ids of the records are preserved, records from versions without ids get new ones.
+    fn import_from_file(&self, path: &str) -> Result<Vec<URLRecord>, Error> {
+        let registry = read_registry_file(path)?;
+        let changes = registry
+            .urls
+            .items
             .iter()
-            .map(|u| {
-                let tags = u.tags.clone().into_iter().map(|(t, _)| t).collect();
-                URLRecord::new(&u.url, &u.name, &u.group, tags)
-            })
+            .map(|r| Change::Added(r.clone()))
             .collect();
 
         // If at least one items fails, nothing will be saved
-        self.storage.add_batch(urls)
+        let imported = self.storage.add_batch(registry.urls.items)?;
+        self.record(HistoryEntry::new("import", changes))?;
+
+        Ok(imported)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::backup::BackupPolicy;
+    use crate::clock::FixedClock;
+    use crate::crypto::test::test_kdf_params;
+    use crate::diff::Change;
+    use crate::encrypted::EncryptedStorage;
+    use crate::error::Error;
     use crate::filters::Filter;
-    use crate::filters::{GroupFilter, TagsFilter};
+    use crate::filters::{GroupFilter, StorageQuery, TagsFilter};
+    use crate::memory::MemoryStorage;
     use crate::registry::URLRegistry;
-    use crate::sort::{SortBy, SortConfig};
+    use crate::sort::{SortBy, SortConfig, SortOrder};
     use crate::storage::FileStorage;
-    use crate::types::URLRecord;
+    use crate::types::{parse_tag, TrashedRecord, URLRecord};
     use crate::util::create_temp_file;
-    use crate::{Importer, Registry, RegistryReader};
+    use crate::{Importer, Registry, RegistryReader, Repository};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
     use std::collections::BTreeMap;
     use std::fs;
     use std::fs::OpenOptions;
     use std::io::{Seek, SeekFrom, Write};
     use std::path::PathBuf;
+    use tempfile::TempDir;
 
     struct TestUrl {
         name: &'static str,
@@ -196,12 +723,144 @@ mod test {
         tags: Vec<&'static str>,
     }
 
+    /// Delegates to the boxed storage, so that the same test can run against every backend
+    impl Repository for Box<dyn Repository> {
+        fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+            (**self).add(record)
+        }
+        fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+            (**self).add_batch(records)
+        }
+        fn delete_by_id(&self, id: &str) -> Result<bool, Error> {
+            (**self).delete_by_id(id)
+        }
+        fn list(&self) -> Result<Vec<URLRecord>, Error> {
+            (**self).list()
+        }
+        fn list_matching(&self, query: &StorageQuery) -> Result<Vec<URLRecord>, Error> {
+            (**self).list_matching(query)
+        }
+        fn get(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+            (**self).get(id)
+        }
+        fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error> {
+            (**self).get_by_keyword(keyword)
+        }
+        fn list_groups(&self) -> Result<Vec<String>, Error> {
+            (**self).list_groups()
+        }
+        fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error> {
+            (**self).update(id, record)
+        }
+        fn record_open(
+            &self,
+            id: &str,
+            opened_at: DateTime<Utc>,
+        ) -> Result<Option<URLRecord>, Error> {
+            (**self).record_open(id, opened_at)
+        }
+        fn trash(
+            &self,
+            id: &str,
+            deleted_at: DateTime<Utc>,
+        ) -> Result<Option<TrashedRecord>, Error> {
+            (**self).trash(id, deleted_at)
+        }
+        fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+            (**self).list_trash()
+        }
+        fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+            (**self).restore(id)
+        }
+        fn purge(
+            &self,
+            deleted_before: Option<DateTime<Utc>>,
+        ) -> Result<Vec<TrashedRecord>, Error> {
+            (**self).purge(deleted_before)
+        }
+        fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+            (**self).replace_all(records)
+        }
+        fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+            (**self).apply(changes, deleted_at)
+        }
+
+        fn apply_unchecked(
+            &self,
+            changes: &[Change],
+            deleted_at: DateTime<Utc>,
+        ) -> Result<(), Error> {
+            (**self).apply_unchecked(changes, deleted_at)
+        }
+    }
+
+    fn boxed<T: Repository + 'static>(
+        registry: URLRegistry<T>,
+    ) -> URLRegistry<Box<dyn Repository>> {
+        URLRegistry {
+            storage: Box::new(registry.storage),
+            default_filter: registry.default_filter,
+            history: registry.history,
+            git: registry.git,
+            clock: registry.clock,
+        }
+    }
+
+    /// Runs the test against the registry with each storage backend. Files are kept in
+    /// the temporary directory, which is removed even if the test panics.
+    fn for_each_backend<F>(test: F)
+    where
+        F: Fn(&URLRegistry<Box<dyn Repository>>),
+    {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+
+        println!("Running against file storage...");
+        test(&boxed(URLRegistry::new_file_based(path("urls.json"))));
+        println!("Running against memory storage...");
+        test(&boxed(URLRegistry::new_in_memory()));
+        println!("Running against SQLite storage...");
+        test(&boxed(
+            URLRegistry::new_sqlite(&path("urls.db")).expect("Failed to initialize registry"),
+        ));
+    }
+
     #[test]
     fn registry_test() {
         let (registry, file_path) =
             URLRegistry::<FileStorage>::with_temp_file("registry_tests.json")
                 .expect("Failed to initialize registry");
 
+        run_registry_test(&registry);
+
+        println!("Cleanup...");
+        let path = file_path.to_str().expect("Invalid path");
+        fs::remove_file(format!("{}.history.json", path)).expect("Failed to remove file");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn in_memory_registry_test() {
+        let registry = URLRegistry::new_in_memory();
+
+        run_registry_test(&registry);
+
+        println!("Should dump and load registry...");
+        let json = registry.to_json().expect("Failed to dump registry");
+        let loaded = URLRegistry::from_json(&json).expect("Failed to load registry");
+        assert_eq!(
+            registry
+                .list_urls(None, None)
+                .expect("Failed to list urls")
+                .len(),
+            loaded
+                .list_urls(None, None)
+                .expect("Failed to list urls")
+                .len()
+        );
+    }
+
+    fn run_registry_test<T: Repository>(registry: &URLRegistry<T>) {
         let test_urls: Vec<TestUrl> = vec![
             TestUrl {
                 name: "test1",
@@ -231,7 +890,7 @@ mod test {
                 .create(
                     tu.name,
                     tu.url,
-                    tu.group.clone(),
+                    tu.group,
                     tu.tags.iter().map(|s| s.to_string()).collect(),
                 )
                 .expect("Failed to add URL record");
@@ -320,38 +979,64 @@ mod test {
         println!("Tag URL...");
         let url_record = registry
             .tag(&id, "some-awesome-tag")
-            .expect("Failed to tag URL")
-            .expect("URL record is None");
+            .expect("Failed to tag URL");
         assert!(url_record.tags.contains_key("some-awesome-tag"));
 
         println!("Untag URL...");
-        let url_record = registry
-            .untag(&id, "tagged")
-            .expect("Failed to untag URL")
-            .expect("URL record is None");
+        let url_record = registry.untag(&id, "tagged").expect("Failed to untag URL");
         assert!(!url_record.tags.contains_key("tagged"));
 
         println!("Change group...");
         let url_record = registry
             .change_group(&id, "different-group")
-            .expect("Failed to change URL group")
-            .expect("URL record is None");
+            .expect("Failed to change URL group");
         assert_eq!(url_record.group, "different-group");
 
         println!("Change name...");
         let url_record = registry
             .change_name(&id, "different-name")
-            .expect("Failed to change URL name")
-            .expect("URL record is None");
+            .expect("Failed to change URL name");
         assert_eq!(url_record.name, "different-name");
 
         println!("Change URL...");
         let url_record = registry
             .change_url(&id, "https://new-url")
-            .expect("Failed to change URL")
-            .expect("URL record is None");
+            .expect("Failed to change URL");
         assert_eq!(url_record.url, "https://new-url");
 
+        println!("Change notes...");
+        let url_record = registry
+            .change_notes(&id, "Use staging creds\nfor the demo")
+            .expect("Failed to change URL notes");
+        assert_eq!(url_record.notes, "Use staging creds\nfor the demo");
+        let url_record = registry
+            .change_notes(&id, "")
+            .expect("Failed to change URL notes");
+        assert!(url_record.notes.is_empty());
+
+        println!("Should return errors for not existing or invalid input...");
+        assert!(matches!(
+            registry.tag("not-existing", "tag"),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            registry.change_name(&id, ""),
+            Err(Error::EmptyField(_))
+        ));
+        assert!(matches!(
+            registry.create("", "https://test.com", None, vec![]),
+            Err(Error::EmptyField("Name"))
+        ));
+        assert!(matches!(
+            registry.create(
+                "different-name",
+                "https://duplicate",
+                Some("different-group"),
+                vec![]
+            ),
+            Err(Error::Duplicate { .. })
+        ));
+
         println!("Verify changes...");
         let record = registry
             .get_url(&id)
@@ -362,12 +1047,926 @@ mod test {
         assert_eq!(url_record.url, "https://new-url");
         assert!(record.tags.contains_key("some-awesome-tag"));
         assert!(!record.tags.contains_key("tagged"));
+    }
+
+    #[test]
+    fn encrypted_registry_test() {
+        let file_path =
+            create_temp_file("registry_encrypted_tests.enc").expect("Failed to create file");
+        let path = file_path.to_str().expect("Invalid path");
+        let storage = EncryptedStorage::open_with_params(path, "secret", test_kdf_params())
+            .expect("Failed to open storage");
+        let registry = URLRegistry::from_encrypted_storage(path, storage);
+
+        run_undo_redo_test(&registry);
+
+        println!("Should encrypt history...");
+        let history_path = format!("{}.history.json", path);
+        let history = fs::read_to_string(&history_path).expect("Failed to read history");
+        assert!(!history.contains("https://"));
+
+        println!("Cleanup...");
+        fs::remove_file(history_path).expect("Failed to remove file");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        for_each_backend(run_undo_redo_test);
+    }
+
+    fn run_undo_redo_test<T: Repository>(registry: &URLRegistry<T>) {
+        println!("Should do nothing if history is empty...");
+        assert!(registry.undo().expect("Failed to undo").is_none());
+        assert!(registry.redo().expect("Failed to redo").is_none());
+
+        let record = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        registry
+            .change_group(&record.id, "dev")
+            .expect("Failed to change group");
+        registry.delete(&record.id).expect("Failed to delete URL");
+
+        println!("Should undo delete...");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "delete");
+        let restored = registry
+            .get_url(&record.id)
+            .expect("Failed to get URL")
+            .expect("URL not restored");
+        assert_eq!(restored.group, "dev");
+
+        println!("Should undo change...");
+        registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        let restored = registry
+            .get_url(&record.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert_eq!(restored, record);
+
+        println!("Should redo change...");
+        let entry = registry
+            .redo()
+            .expect("Failed to redo")
+            .expect("Nothing redone");
+        assert_eq!(entry.operation, "change group");
+        let changed = registry
+            .get_url(&record.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert_eq!(changed.group, "dev");
+
+        println!("Should not undo if bookmark changed in the meantime...");
+        registry
+            .storage
+            .update(&record.id, record.clone())
+            .expect("Failed to update URL");
+        assert!(matches!(registry.undo(), Err(Error::Conflict(_))));
+
+        println!("Should not redo after new operation...");
+        registry
+            .change_name(&record.id, "two")
+            .expect("Failed to change name");
+        assert!(registry.redo().expect("Failed to redo").is_none());
+        registry.undo().expect("Failed to undo");
+        let restored = registry
+            .get_url(&record.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert_eq!(restored.name, "one");
+    }
+
+    #[test]
+    fn apply_merge_test() {
+        let registry = URLRegistry::new_in_memory();
+        let one = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        let two = registry
+            .create("two", "https://two.com", None, vec![])
+            .expect("Failed to add URL");
+
+        let mut one_changed = one.clone();
+        one_changed.group = "dev".to_string();
+        let three = URLRecord::new("https://three.com", "three", "dev", Vec::<String>::new());
+
+        let ours = registry.list_urls(None, None).expect("Failed to list URLs");
+
+        println!("Should not apply merge if bookmarks were changed in the meantime...");
+        registry.pin(&two.id).expect("Failed to pin URL");
+        let result = registry.apply_merge(&ours, vec![one_changed.clone(), three.clone()]);
+        assert!(matches!(result, Err(Error::Conflict(id)) if id == two.id));
+        registry.undo().expect("Failed to undo");
+
+        println!("Should apply only differences...");
+        let changes = registry
+            .apply_merge(&ours, vec![one_changed.clone(), three.clone()])
+            .expect("Failed to apply merge");
+        assert_eq!(
+            changes,
+            vec![
+                Change::Modified {
+                    before: one.clone(),
+                    after: one_changed.clone()
+                },
+                Change::Removed(two.clone()),
+                Change::Added(three.clone()),
+            ]
+        );
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            vec![one_changed.clone(), three.clone()]
+        );
+        assert_eq!(
+            registry.list_trash().expect("Failed to list trash")[0].record,
+            two
+        );
+
+        println!("Should not apply merge with duplicated names...");
+        let mut duplicate = three.clone();
+        duplicate.id = "other".to_string();
+        let ours = registry.list_urls(None, None).expect("Failed to list URLs");
+        let result = registry.apply_merge(&ours, vec![one_changed, three, duplicate]);
+        assert!(matches!(result, Err(Error::Duplicate { .. })));
+
+        println!("Should undo merge at once...");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "merge");
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            vec![one, two]
+        );
+    }
+
+    #[test]
+    fn transaction_test() {
+        for_each_backend(run_transaction_test);
+    }
+
+    fn run_transaction_test<T: Repository>(registry: &URLRegistry<T>) {
+        let one = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        let two = registry
+            .create("two", "https://two.com", None, vec![])
+            .expect("Failed to add URL");
+        let before = registry.list_urls(None, None).expect("Failed to list URLs");
+
+        println!("Should apply all operations...");
+        let three = registry
+            .transaction(|tx| {
+                tx.tag(&one.id, "first")?;
+                tx.change_group(&one.id, "dev")?;
+                tx.delete(&two.id)?;
+                tx.create("three", "https://three.com", Some("dev"), vec![])
+            })
+            .expect("Failed to run transaction");
+
+        let urls = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].group, "dev");
+        assert!(urls[0].tags.contains_key("first"));
+        assert_eq!(urls[1], three);
+        assert_eq!(
+            registry.list_trash().expect("Failed to list trash")[0].record,
+            two
+        );
+
+        println!("Should undo transaction at once...");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "batch update");
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            before
+        );
+
+        println!("Should not apply any operation if one of them fails...");
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.change_name(&two.id, "one")
+        });
+        assert!(matches!(result, Err(Error::Duplicate { .. })));
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.tag("not-existing", "first")
+        });
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            before
+        );
+
+        println!("Should not apply operations if bookmark changed in the meantime...");
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.tag(&two.id, "second")?;
+            registry.change_url(&two.id, "https://changed.com")
+        });
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        let urls = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert!(urls.iter().all(|u| u.tags.is_empty()));
+
+        println!("Should roll back earlier changes if a later one fails to apply...");
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.change_name(&two.id, "four")?;
+            registry.create("four", "https://four.com", None, vec![])
+        });
+        assert!(matches!(result, Err(Error::Duplicate { .. })));
+        let urls = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert!(urls[0].tags.is_empty());
+        assert_eq!(urls[1].name, "two");
+    }
+
+    #[test]
+    fn transaction_rollback_test() {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let path = dir.path().join("urls.json");
+        let registry = URLRegistry::new_file_based(path.to_string_lossy().to_string());
+        let one = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        let two = registry
+            .create("two", "https://two.com", None, vec![])
+            .expect("Failed to add URL");
+        let before = fs::read(&path).expect("Failed to read registry file");
+
+        println!("Should leave the file untouched if an operation fails...");
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.tag(&two.id, "second")?;
+            tx.change_name(&one.id, "two")
+        });
+        assert!(matches!(result, Err(Error::Duplicate { .. })));
+        assert_eq!(
+            fs::read(&path).expect("Failed to read registry file"),
+            before
+        );
+
+        println!("Should leave the file untouched if a later change conflicts...");
+        let other = URLRegistry::new_file_based(path.to_string_lossy().to_string());
+        let result = registry.transaction(|tx| {
+            tx.tag(&one.id, "first")?;
+            tx.tag(&two.id, "second")?;
+            other.change_url(&two.id, "https://changed.com")?;
+            Ok(())
+        });
+        let changed = fs::read(&path).expect("Failed to read registry file");
+        assert!(matches!(result, Err(Error::Conflict(id)) if id == two.id));
+        assert_ne!(changed, before);
+        let urls = other.list_urls(None, None).expect("Failed to list URLs");
+        assert!(urls.iter().all(|u| u.tags.is_empty()));
+
+        println!("Should not record failed transactions in history...");
+        let result = registry.transaction(|tx| tx.tag("not-existing", "first"));
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        assert_eq!(
+            fs::read(&path).expect("Failed to read registry file"),
+            changed
+        );
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "change url");
+    }
+
+    #[test]
+    fn move_group_test() {
+        for_each_backend(run_move_group_test);
+    }
+
+    fn run_move_group_test<T: Repository>(registry: &URLRegistry<T>) {
+        for (name, group) in [
+            ("wiki", "work"),
+            ("grafana", "work/infra/"),
+            ("loki", " work / infra / logs"),
+            ("tools", "workshop"),
+        ] {
+            registry
+                .create(name, "https://example.com", Some(group), vec![])
+                .expect("Failed to add URL");
+        }
+        let before = registry.list_urls(None, None).expect("Failed to list URLs");
+
+        println!("Should normalize group paths...");
+        let groups: Vec<&str> = before.iter().map(|r| r.group.as_str()).collect();
+        assert_eq!(
+            groups,
+            vec!["work", "work/infra", "work/infra/logs", "workshop"]
+        );
+
+        println!("Should move whole subtree...");
+        let moved = registry
+            .move_group("work/infra", "ops")
+            .expect("Failed to move group");
+        assert_eq!(moved.len(), 2);
+        let groups: Vec<String> = registry
+            .list_urls(None, None)
+            .expect("Failed to list URLs")
+            .into_iter()
+            .map(|r| r.group)
+            .collect();
+        assert_eq!(groups, vec!["work", "ops", "ops/logs", "workshop"]);
+
+        println!("Should undo the move at once...");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "move group");
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            before
+        );
+
+        println!("Should move group into its own subtree...");
+        registry
+            .move_group("work", "work/old")
+            .expect("Failed to move group");
+        let groups: Vec<String> = registry
+            .list_urls(None, None)
+            .expect("Failed to list URLs")
+            .into_iter()
+            .map(|r| r.group)
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                "work/old",
+                "work/old/infra",
+                "work/old/infra/logs",
+                "workshop"
+            ]
+        );
+
+        println!("Should not move anything from not existing group...");
+        let moved = registry
+            .move_group("home", "ops")
+            .expect("Failed to move group");
+        assert!(moved.is_empty());
+
+        println!("Should fail to move into group with the same names...");
+        registry
+            .create("grafana", "https://example.com", Some("ops"), vec![])
+            .expect("Failed to add URL");
+        let result = registry.move_group("work/old/infra", "ops");
+        assert!(matches!(result, Err(Error::Duplicate { .. })));
+        assert!(matches!(
+            registry.move_group("/", "ops"),
+            Err(Error::EmptyField("Group"))
+        ));
+    }
+
+    #[test]
+    fn pin_test() {
+        for_each_backend(run_pin_test);
+    }
+
+    fn run_pin_test<T: Repository>(registry: &URLRegistry<T>) {
+        for name in ["a", "b", "c", "d"] {
+            registry
+                .create(name, "https://example.com", None, vec![])
+                .expect("Failed to add URL");
+        }
+        let names = |sort: Option<SortConfig>| -> Vec<String> {
+            registry
+                .list_urls(None, sort)
+                .expect("Failed to list URLs")
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+        let id_of = |name: &str| -> String {
+            registry
+                .list_urls(None, None)
+                .expect("Failed to list URLs")
+                .into_iter()
+                .find(|r| r.name == name)
+                .expect("URL not found")
+                .id
+        };
+
+        println!("Should list pinned bookmarks first...");
+        let pinned = registry.pin(&id_of("c")).expect("Failed to pin URL");
+        assert!(pinned.pinned);
+        registry.pin(&id_of("b")).expect("Failed to pin URL");
+        assert_eq!(names(None), vec!["b", "c", "a", "d"]);
+
+        println!("Should keep pinned bookmarks first whatever the order...");
+        let descending = SortConfig::new(SortBy::Name, SortOrder::Descending);
+        assert_eq!(names(Some(descending)), vec!["c", "b", "d", "a"]);
+
+        println!("Should unpin bookmark and undo it...");
+        let unpinned = registry.unpin(&id_of("c")).expect("Failed to unpin URL");
+        assert!(!unpinned.pinned);
+        assert_eq!(names(None), vec!["b", "a", "c", "d"]);
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.describe(), "unpin c in default");
+        assert_eq!(names(None), vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn keyword_test() {
+        for_each_backend(run_keyword_test);
+    }
+
+    fn run_keyword_test<T: Repository>(registry: &URLRegistry<T>) {
+        let github = registry
+            .create("GitHub", "https://github.com", None, vec![])
+            .expect("Failed to add URL");
+        let gitlab = registry
+            .create("GitLab", "https://gitlab.com", None, vec![])
+            .expect("Failed to add URL");
+
+        println!("Should set keyword and find bookmark by it...");
+        let record = registry
+            .set_keyword(&github.id, " gh ")
+            .expect("Failed to set keyword");
+        assert_eq!(record.keyword, "gh");
+        let found = registry
+            .get_by_keyword("gh")
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(found.id, github.id);
+        assert!(registry
+            .get_by_keyword("gl")
+            .expect("Failed to get URL")
+            .is_none());
+
+        println!("Should not allow keyword used by other bookmark...");
+        let err = registry
+            .set_keyword(&gitlab.id, "gh")
+            .expect_err("Keyword should be unique");
+        assert!(matches!(err, Error::DuplicateKeyword(k) if k == "gh"));
+        let mut copy = URLRecord::new(
+            "https://github.com",
+            "Copy",
+            "default",
+            Vec::<String>::new(),
+        );
+        copy.keyword = "gh".to_string();
+        assert!(matches!(
+            registry.add(copy),
+            Err(Error::DuplicateKeyword(_))
+        ));
+
+        println!("Should not allow invalid keywords...");
+        assert!(matches!(
+            registry.set_keyword(&gitlab.id, "g l"),
+            Err(Error::InvalidKeyword(_))
+        ));
+        for flag in ["-x", "-h", "--help", "-V"] {
+            assert!(matches!(
+                registry.set_keyword(&gitlab.id, flag),
+                Err(Error::InvalidKeyword(_))
+            ));
+        }
+        assert!(matches!(
+            registry.set_keyword(&gitlab.id, " "),
+            Err(Error::EmptyField("Keyword"))
+        ));
+        assert!(matches!(
+            registry.set_keyword(&gitlab.id, "ls"),
+            Err(Error::ReservedKeyword(_))
+        ));
+
+        println!("Should not restore bookmark with keyword used in the meantime...");
+        registry.delete(&github.id).expect("Failed to delete URL");
+        registry
+            .set_keyword(&gitlab.id, "gh")
+            .expect("Failed to set keyword");
+        assert!(matches!(
+            registry.restore(&github.id),
+            Err(Error::DuplicateKeyword(_))
+        ));
+
+        println!("Should remove keyword...");
+        let record = registry
+            .remove_keyword(&gitlab.id)
+            .expect("Failed to remove keyword");
+        assert!(record.keyword.is_empty());
+        assert!(registry
+            .get_by_keyword("gh")
+            .expect("Failed to get URL")
+            .is_none());
+        registry.restore(&github.id).expect("Failed to restore URL");
+
+        println!("Should not add batch with keyword in use...");
+        let mut imported = URLRecord::new(
+            "https://github.io",
+            "Imported",
+            "default",
+            Vec::<String>::new(),
+        );
+        imported.keyword = "gh".to_string();
+        assert!(matches!(
+            registry.storage.add_batch(vec![imported.clone()]),
+            Err(Error::DuplicateKeyword(_))
+        ));
+        imported.keyword = "io".to_string();
+        let other = URLRecord {
+            keyword: "io".to_string(),
+            ..URLRecord::new("https://other.io", "Other", "default", Vec::<String>::new())
+        };
+        assert!(matches!(
+            registry.storage.add_batch(vec![imported, other]),
+            Err(Error::DuplicateKeyword(_))
+        ));
+        assert!(registry
+            .get_by_keyword("io")
+            .expect("Failed to get URL")
+            .is_none());
+
+        println!("Should not apply changes reusing keyword...");
+        let gitlab = registry
+            .set_keyword(&gitlab.id, "gl")
+            .expect("Failed to set keyword");
+        let github = registry
+            .get_url(&github.id)
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        let reuse = Change::Modified {
+            before: gitlab.clone(),
+            after: URLRecord {
+                keyword: "gh".to_string(),
+                ..gitlab.clone()
+            },
+        };
+        assert!(matches!(
+            registry.apply_changes(&[reuse]),
+            Err(Error::DuplicateKeyword(_))
+        ));
+
+        println!("Should swap keywords in one set of changes...");
+        let swap = vec![
+            Change::Modified {
+                before: github.clone(),
+                after: URLRecord {
+                    keyword: "gl".to_string(),
+                    ..github.clone()
+                },
+            },
+            Change::Modified {
+                before: gitlab.clone(),
+                after: URLRecord {
+                    keyword: "gh".to_string(),
+                    ..gitlab.clone()
+                },
+            },
+        ];
+        registry
+            .apply_changes(&swap)
+            .expect("Failed to apply changes");
+        let found = registry
+            .get_by_keyword("gl")
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(found.id, github.id);
+    }
+
+    #[test]
+    fn bundle_test() {
+        for_each_backend(run_bundle_test);
+    }
+
+    fn run_bundle_test<T: Repository>(registry: &URLRegistry<T>) {
+        let grafana = registry
+            .create("Grafana", "https://grafana.com", Some("ops"), vec![])
+            .expect("Failed to add URL");
+        let sentry = registry
+            .create("Sentry", "https://sentry.io", Some("ops"), vec![])
+            .expect("Failed to add URL");
+
+        println!("Should create bundle of URLs and bookmarks...");
+        let bundle = registry
+            .create_bundle(
+                "Morning",
+                Some("ops/daily"),
+                vec!["daily".to_string()],
+                vec![
+                    sentry.id.clone(),
+                    "https://status.example.com".to_string(),
+                    grafana.id.clone(),
+                ],
+            )
+            .expect("Failed to create bundle");
+        assert!(bundle.is_bundle());
+        assert_eq!(
+            registry
+                .urls_to_open(&bundle)
+                .expect("Failed to get URLs of bundle"),
+            vec![
+                "https://sentry.io",
+                "https://status.example.com",
+                "https://grafana.com"
+            ]
+        );
+        assert_eq!(
+            registry
+                .urls_to_open(&grafana)
+                .expect("Failed to get URLs of bookmark"),
+            vec!["https://grafana.com"]
+        );
+
+        println!("Should list bundle like other bookmarks...");
+        let tags_filter = TagsFilter::new(vec!["daily"]);
+        let tagged = registry
+            .list_urls(Some(&tags_filter), None)
+            .expect("Failed to list URLs");
+        assert_eq!(tagged, vec![bundle.clone()]);
+        let group_filter = GroupFilter::new("ops");
+        let sorted = registry
+            .list_urls(Some(&group_filter), Some(SortConfig::new_by(SortBy::Name)))
+            .expect("Failed to list URLs");
+        let names: Vec<&str> = sorted.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Grafana", "Morning", "Sentry"]);
+
+        println!("Should reject invalid members...");
+        for members in [vec![], vec!["missing".to_string()], vec![bundle.id.clone()]] {
+            assert!(registry
+                .create_bundle("Invalid", None, vec![], members)
+                .is_err());
+        }
+
+        println!("Should reject members with colon but without the scheme...");
+        for member in [
+            "localhost:8080",
+            "ab:cd",
+            "://example.com",
+            "1http://example.com",
+        ] {
+            assert!(matches!(
+                registry.create_bundle("Invalid", None, vec![], vec![member.to_string()]),
+                Err(Error::InvalidBundleMember(m)) if m == member
+            ));
+        }
+
+        println!("Should fail to open bundle with deleted bookmark...");
+        registry.delete(&sentry.id).expect("Failed to delete URL");
+        assert!(matches!(
+            registry.urls_to_open(&bundle),
+            Err(Error::NotFound(id)) if id == sentry.id
+        ));
+    }
+
+    #[test]
+    fn timestamps_test() {
+        for_each_backend(run_timestamps_test);
+    }
+
+    fn run_timestamps_test<T: Repository>(registry: &URLRegistry<T>) {
+        let start = Utc::now();
+
+        println!("Should set creation and update time of new record...");
+        let record = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        let created_at = record.created_at.expect("Creation time not set");
+        assert!(created_at >= start);
+        assert_eq!(record.updated_at, Some(created_at));
+        assert_eq!(record.last_opened_at, None);
+
+        println!("Should set update time when record changes...");
+        let tagged = registry.tag(&record.id, "tag").expect("Failed to tag URL");
+        assert_eq!(tagged.created_at, Some(created_at));
+        assert!(tagged.updated_at > Some(created_at));
+
+        println!("Should not set update time if nothing changed...");
+        let retagged = registry.tag(&record.id, "tag").expect("Failed to tag URL");
+        assert_eq!(retagged.updated_at, tagged.updated_at);
+
+        println!("Should record opening without updating the record...");
+        let opened = registry
+            .mark_opened(&record.id)
+            .expect("Failed to mark URL as opened");
+        assert!(opened.last_opened_at >= tagged.updated_at);
+        assert_eq!(opened.updated_at, tagged.updated_at);
+
+        println!("Should undo change made before opening the record...");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "tag");
+        let restored = registry
+            .get_url(&record.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert!(restored.tags.is_empty());
+        assert_eq!(restored.updated_at, Some(created_at));
+        assert_eq!(restored.last_opened_at, opened.last_opened_at);
+
+        println!("Should return error when opening not existing record...");
+        assert!(matches!(
+            registry.mark_opened("not-existing"),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn frecency_test() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let clock = FixedClock::new(start);
+        let registry = URLRegistry::new_in_memory().with_clock(clock.clone());
+        let sort_cfg = Some(SortConfig::new(SortBy::Frecency, SortOrder::Descending));
+        let names = |registry: &URLRegistry<MemoryStorage>| -> Vec<String> {
+            registry
+                .list_urls(None, sort_cfg)
+                .expect("Failed to list URLs")
+                .into_iter()
+                .map(|u| u.name)
+                .collect()
+        };
+
+        let often = registry
+            .create("often", "https://often.com", None, vec![])
+            .expect("Failed to add URL");
+        let recent = registry
+            .create("recent", "https://recent.com", None, vec![])
+            .expect("Failed to add URL");
+        registry
+            .create("never", "https://never.com", None, vec![])
+            .expect("Failed to add URL");
+        assert_eq!(often.created_at, Some(start));
+
+        println!("Should rank frequently opened bookmark first...");
+        for _ in 0..3 {
+            registry
+                .mark_opened(&often.id)
+                .expect("Failed to mark URL as opened");
+        }
+        clock.advance(Duration::days(1));
+        assert_eq!(names(&registry), vec!["often", "recent", "never"]);
+
+        println!("Should rank recently opened bookmark first as older opens decay...");
+        clock.advance(Duration::days(94));
+        let opened = registry
+            .mark_opened(&recent.id)
+            .expect("Failed to mark URL as opened");
+        assert_eq!(opened.open_count, 1);
+        assert_eq!(opened.open_history, vec![start + Duration::days(95)]);
+        assert_eq!(names(&registry), vec!["recent", "often", "never"]);
+    }
+
+    #[test]
+    fn repair_test() {
+        let file_path =
+            create_temp_file("registry_repair_tests.json").expect("Failed to create file");
+        let path = file_path.to_str().expect("Invalid path");
+        fs::write(
+            path,
+            r#"{"version":1,"urls":{"items":[
+                {"id":"1","url":"https://github.com","name":"GitHub","group":"dev","tags":{}},
+                {"id":"1","url":"https://gitlab.com","name":"GitHub","group":"dev","tags":{" ":true}},
+                {"id":"2","url":"not a url","name":"Broken","group":"dev","tags":{}}
+            ]}}"#,
+        )
+        .expect("Failed to write file");
+        let registry = URLRegistry::new_file_based(path.to_string());
+
+        println!("Should find problems...");
+        let problems = registry.diagnose().expect("Failed to diagnose registry");
+        assert_eq!(problems.len(), 4);
+        let original = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert_eq!(original[1].id, "1");
+
+        println!("Should repair problems that can be fixed...");
+        let problems = registry.repair().expect("Failed to repair registry");
+        assert_eq!(problems.iter().filter(|p| p.fix.is_some()).count(), 3);
+        let urls = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert_ne!(urls[1].id, "1");
+        assert_eq!(urls[1].name, "GitHub (2)");
+        assert!(urls[1].tags.is_empty());
+
+        println!("Should report only problems that cannot be fixed...");
+        let problems = registry.diagnose().expect("Failed to diagnose registry");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].fix.is_none());
+
+        println!("Should bring back the problems when the repair is undone...");
+        let entry = registry.undo().expect("Failed to undo");
+        assert_eq!(entry.expect("Missing history entry").operation, "repair");
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            original
+        );
+        assert_eq!(registry.diagnose().expect("Failed to diagnose").len(), 4);
+
+        println!("Should repair problems again when the repair is redone...");
+        registry.redo().expect("Failed to redo");
+        assert_eq!(
+            registry.list_urls(None, None).expect("Failed to list URLs"),
+            urls
+        );
 
         println!("Cleanup...");
+        fs::remove_file(format!("{}.history.json", path)).expect("Failed to remove file");
         fs::remove_file(file_path).expect("Failed to remove file");
     }
 
-    fn assert_urls_match(test_urls: &Vec<&TestUrl>, actual: &Vec<URLRecord>) {
+    #[test]
+    fn trash_test() {
+        for_each_backend(run_trash_test);
+    }
+
+    fn run_trash_test<T: Repository>(registry: &URLRegistry<T>) {
+        let one = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        let two = registry
+            .create("two", "https://two.com", None, vec![])
+            .expect("Failed to add URL");
+
+        println!("Should move deleted URL to trash...");
+        assert!(registry.delete(&one.id).expect("Failed to delete URL"));
+        assert!(!registry.delete(&one.id).expect("Failed to delete URL"));
+        assert!(registry
+            .get_url(&one.id)
+            .expect("Failed to get URL")
+            .is_none());
+        let trash = registry.list_trash().expect("Failed to list trash");
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].record, one);
+
+        println!("Should restore URL from trash...");
+        let restored = registry.restore(&one.id).expect("Failed to restore URL");
+        assert_eq!(restored, one);
+        assert!(registry
+            .list_trash()
+            .expect("Failed to list trash")
+            .is_empty());
+        assert!(matches!(registry.restore(&one.id), Err(Error::NotFound(_))));
+
+        println!("Should not restore URL if name is already taken in group...");
+        registry.delete(&one.id).expect("Failed to delete URL");
+        registry
+            .change_name(&two.id, "one")
+            .expect("Failed to change name");
+        assert!(matches!(
+            registry.restore(&one.id),
+            Err(Error::Duplicate { .. })
+        ));
+        assert_eq!(
+            registry.list_trash().expect("Failed to list trash").len(),
+            1
+        );
+
+        println!("Should move URL back to trash on undo of restore...");
+        registry
+            .change_name(&two.id, "two")
+            .expect("Failed to change name");
+        registry.restore(&one.id).expect("Failed to restore URL");
+        let entry = registry
+            .undo()
+            .expect("Failed to undo")
+            .expect("Nothing undone");
+        assert_eq!(entry.operation, "restore from trash");
+        assert_eq!(
+            registry.list_trash().expect("Failed to list trash").len(),
+            1
+        );
+
+        println!("Should take URL from trash on undo of delete...");
+        registry.delete(&two.id).expect("Failed to delete URL");
+        registry.undo().expect("Failed to undo");
+        let trash = registry.list_trash().expect("Failed to list trash");
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].record.id, one.id);
+
+        println!("Should purge only URLs deleted before given time...");
+        let before_delete = Utc::now() - Duration::seconds(1);
+        registry.delete(&two.id).expect("Failed to delete URL");
+        let purged = registry
+            .purge(Some(before_delete))
+            .expect("Failed to purge trash");
+        assert!(purged.is_empty());
+
+        let purged = registry.purge(None).expect("Failed to purge trash");
+        assert_eq!(purged.len(), 2);
+        assert!(registry
+            .list_trash()
+            .expect("Failed to list trash")
+            .is_empty());
+    }
+
+    fn assert_urls_match(test_urls: &[&TestUrl], actual: &[URLRecord]) {
         for tu in test_urls {
             let exists = actual.iter().any(|rec| {
                 rec.name == tu.name
@@ -381,16 +1980,16 @@ mod test {
 
     fn group_match(input: &Option<&str>, actual: &String) -> bool {
         if let Some(g) = input {
-            return g == actual;
+            g == actual
         } else {
             "default" == actual
         }
     }
 
-    fn tags_match(expected: &Vec<&str>, actual: &BTreeMap<String, bool>) -> bool {
+    fn tags_match(expected: &Vec<&str>, actual: &BTreeMap<String, String>) -> bool {
         for t in expected {
-            let tag = actual.get(*t).expect("Tag not present");
-            if !tag {
+            let (key, value) = parse_tag(t);
+            if actual.get(&key) != Some(&value) {
                 return false;
             }
         }
@@ -399,12 +1998,12 @@ mod test {
     }
 
     #[test]
-    fn import_from_v0_0_x_test() {
+    fn import_from_v0_0_x_file_test() {
         let (registry, file_path) =
             URLRegistry::<FileStorage>::with_temp_file("registry_tests2.json")
                 .expect("Failed to initialize registry");
 
-        let expected_urls = vec![
+        let expected_urls = [
             URLRecord::new(
                 "https://github.com/Szymongib/bookmark-cli",
                 "Bookmark-CLI",
@@ -441,7 +2040,7 @@ mod test {
 
         println!("Should import URLs...");
         let imported = registry
-            .import_from_v_0_0_x(old_path.as_os_str().to_str().expect("Failed to get path"))
+            .import_from_file(old_path.as_os_str().to_str().expect("Failed to get path"))
             .expect("Failed to import bookmarks");
 
         assert_eq!(imported.len(), 5);
@@ -454,15 +2053,75 @@ mod test {
         }
 
         println!("Should fail if URLs not unique...");
-        let imported = registry
-            .import_from_v_0_0_x(old_path.as_os_str().to_str().expect("Failed to get path"));
+        let imported =
+            registry.import_from_file(old_path.as_os_str().to_str().expect("Failed to get path"));
         assert!(imported.is_err());
 
         println!("Cleanup...");
+        let path = file_path.to_str().expect("Invalid path");
+        fs::remove_file(format!("{}.history.json", path)).expect("Failed to remove file");
         fs::remove_file(file_path).expect("Failed to remove file");
         fs::remove_file(old_path).expect("Failed to remove file");
     }
 
+    #[test]
+    fn backup_restore_test() {
+        let (registry, file_path) =
+            URLRegistry::<FileStorage>::with_temp_file("registry_backup_tests.json")
+                .expect("Failed to initialize registry");
+        let registry = registry.with_backups(BackupPolicy::default());
+
+        println!("Should take snapshot before every change...");
+        let one = registry
+            .create("one", "https://one.com", None, vec![])
+            .expect("Failed to add URL");
+        registry
+            .create("two", "https://two.com", None, vec![])
+            .expect("Failed to add URL");
+        registry.delete(&one.id).expect("Failed to delete URL");
+
+        let snapshots = registry.list_backups().expect("Failed to list backups");
+        assert_eq!(snapshots.len(), 2);
+
+        println!("Should show changes between snapshot and current state...");
+        let changes = registry
+            .backup_diff(&snapshots[0].id)
+            .expect("Failed to diff backup");
+        assert_eq!(changes, vec![Change::Added(one.clone())]);
+
+        println!("Should restore snapshot...");
+        let changes = registry
+            .restore_backup(&snapshots[0].id)
+            .expect("Failed to restore backup");
+        assert_eq!(changes, vec![Change::Added(one.clone())]);
+        assert_eq!(
+            registry
+                .list_urls(None, None)
+                .expect("Failed to list urls")
+                .len(),
+            2
+        );
+        assert_eq!(
+            registry
+                .list_backups()
+                .expect("Failed to list backups")
+                .len(),
+            3
+        );
+
+        println!("Should fail to restore not existing snapshot...");
+        assert!(matches!(
+            registry.restore_backup("not-existing"),
+            Err(Error::SnapshotNotFound(_))
+        ));
+
+        println!("Cleanup...");
+        let path = file_path.to_str().expect("Invalid path");
+        fs::remove_dir_all(format!("{}.backups", path)).expect("Failed to remove backups");
+        fs::remove_file(format!("{}.history.json", path)).expect("Failed to remove file");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
     fn setup_old_urls_file() -> PathBuf {
         let old_file_content = OLD_BOOKMARKS_FILE_CONTENT;
         let path =
@@ -471,7 +2130,7 @@ mod test {
         let mut file = OpenOptions::new()
             .read(true)
             .create(true)
-            .append(false)
+            .truncate(true)
             .write(true)
             .open(path.clone())
             .expect("Failed to open old URLs file");
@@ -481,7 +2140,7 @@ mod test {
         file.write_all(old_file_content.as_bytes())
             .expect("Failed to write od URLs");
 
-        return path;
+        path
     }
 
     const OLD_BOOKMARKS_FILE_CONTENT: &str = r###"
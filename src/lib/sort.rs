@@ -1,4 +1,7 @@
+use crate::error::Error;
+use crate::frecency::frecency;
 use crate::types::URLRecord;
+use chrono::{DateTime, Utc};
 use std::cmp::Ordering;
 use std::str::FromStr;
 
@@ -14,11 +17,15 @@ impl SortConfig {
         SortConfig { sort_by, order }
     }
 
+    /// Sorts in the natural order of the column: ascending, except for the frecency,
+    /// which starts from the most used bookmarks
     pub fn new_by(sort_by: SortBy) -> SortConfig {
-        SortConfig {
-            sort_by,
-            order: SortOrder::Ascending,
-        }
+        let order = match sort_by {
+            SortBy::Frecency => SortOrder::Descending,
+            _ => SortOrder::Ascending,
+        };
+
+        SortConfig { sort_by, order }
     }
 }
 
@@ -27,18 +34,29 @@ pub enum SortBy {
     Name,
     URL,
     Group,
+    Created,
+    Updated,
+    /// Bookmarks that were never opened come before the ones opened the longest time ago
+    LastOpened,
+    /// Combines how often and how recently bookmarks were opened, see `frecency`
+    Frecency,
 }
 
 impl FromStr for SortBy {
-    type Err = Box<dyn std::error::Error>;
+    type Err = Error;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
         match value.to_lowercase().as_str() {
             "name" => Ok(SortBy::Name),
             "url" => Ok(SortBy::URL),
             "group" => Ok(SortBy::Group),
-            _ => Err(From::from(
-                "invalid sort column, must be one of: [name, url, group]",
+            "created" => Ok(SortBy::Created),
+            "updated" => Ok(SortBy::Updated),
+            "opened" | "last-opened" => Ok(SortBy::LastOpened),
+            "frecency" => Ok(SortBy::Frecency),
+            _ => Err(Error::Parse(
+                "invalid sort column, must be one of: [name, url, group, created, updated, opened, frecency]"
+                    .to_string(),
             )),
         }
     }
@@ -50,23 +68,43 @@ pub enum SortOrder {
     Descending,
 }
 
-pub(crate) fn sort_urls(mut urls: Vec<URLRecord>, config: &SortConfig) -> Vec<URLRecord> {
-    let cmp_func = match config.sort_by {
-        SortBy::Name => sort_by_name,
-        SortBy::URL => sort_by_url,
-        SortBy::Group => sort_by_group,
+type CmpFunc = dyn Fn(&URLRecord, &URLRecord) -> Ordering;
+
+/// Sorts URLs according to the config. Time-dependent orders, such as frecency, are computed
+/// for the time `now`. Sorting is stable in both orders.
+pub(crate) fn sort_urls(
+    mut urls: Vec<URLRecord>,
+    config: &SortConfig,
+    now: DateTime<Utc>,
+) -> Vec<URLRecord> {
+    let cmp_func: Box<CmpFunc> = match config.sort_by {
+        SortBy::Name => Box::new(sort_by_name),
+        SortBy::URL => Box::new(sort_by_url),
+        SortBy::Group => Box::new(sort_by_group),
+        SortBy::Created => Box::new(sort_by_created),
+        SortBy::Updated => Box::new(sort_by_updated),
+        SortBy::LastOpened => Box::new(sort_by_last_opened),
+        SortBy::Frecency => Box::new(move |a, b| {
+            frecency(a, now)
+                .partial_cmp(&frecency(b, now))
+                .unwrap_or(Ordering::Equal)
+        }),
     };
 
-    urls.sort_by(cmp_func);
-
     match config.order {
-        SortOrder::Ascending => {}
-        SortOrder::Descending => urls.reverse(),
+        SortOrder::Ascending => urls.sort_by(|a, b| cmp_func(a, b)),
+        SortOrder::Descending => urls.sort_by(|a, b| cmp_func(b, a)),
     };
 
     urls
 }
 
+/// Moves pinned URLs to the top, keeping the order within pinned and other URLs
+pub(crate) fn pinned_first(mut urls: Vec<URLRecord>) -> Vec<URLRecord> {
+    urls.sort_by_key(|u| !u.pinned);
+    urls
+}
+
 fn sort_by_name(a: &URLRecord, b: &URLRecord) -> Ordering {
     a.name.to_lowercase().cmp(&b.name.to_lowercase())
 }
@@ -79,6 +117,18 @@ fn sort_by_url(a: &URLRecord, b: &URLRecord) -> Ordering {
     strip_protocol(&a.url.to_lowercase()).cmp(&strip_protocol(&b.url.to_lowercase()))
 }
 
+fn sort_by_created(a: &URLRecord, b: &URLRecord) -> Ordering {
+    a.created_at.cmp(&b.created_at)
+}
+
+fn sort_by_updated(a: &URLRecord, b: &URLRecord) -> Ordering {
+    a.updated_at.cmp(&b.updated_at)
+}
+
+fn sort_by_last_opened(a: &URLRecord, b: &URLRecord) -> Ordering {
+    a.last_opened_at.cmp(&b.last_opened_at)
+}
+
 fn strip_protocol(url: &str) -> String {
     let possible_prefix = &["https://www.", "http://www.", "https://", "http://"];
     for prefix in possible_prefix {
@@ -94,6 +144,8 @@ fn strip_protocol(url: &str) -> String {
 mod test {
     use crate::sort::{sort_urls, SortBy, SortConfig, SortOrder};
     use crate::types::URLRecord;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::str::FromStr;
 
     fn fix_url_records() -> Vec<URLRecord> {
         vec![
@@ -117,7 +169,7 @@ mod test {
         let mut records = fix_url_records();
 
         let sort_cfg = SortConfig::new(SortBy::URL, SortOrder::Ascending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         let expected_order = &mut [
             "https://aaaa",
@@ -135,7 +187,7 @@ mod test {
 
         // Descending
         let sort_cfg = SortConfig::new(SortBy::URL, SortOrder::Descending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         expected_order.reverse();
         for (i, r) in records.iter().enumerate() {
@@ -148,7 +200,7 @@ mod test {
         let mut records = fix_url_records();
 
         let sort_cfg = SortConfig::new(SortBy::Name, SortOrder::Ascending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         let expected_order = &mut ["five", "FOUR", "one", "seven", "six", "three", "Two"];
 
@@ -158,7 +210,7 @@ mod test {
 
         // Descending
         let sort_cfg = SortConfig::new(SortBy::Name, SortOrder::Descending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         expected_order.reverse();
         for (i, r) in records.iter().enumerate() {
@@ -171,7 +223,7 @@ mod test {
         let mut records = fix_url_records();
 
         let sort_cfg = SortConfig::new(SortBy::Group, SortOrder::Ascending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         let expected_order = &mut ["abcd", "GROUP", "GROUP", "one", "one", "one", "two"];
 
@@ -181,11 +233,82 @@ mod test {
 
         // Descending
         let sort_cfg = SortConfig::new(SortBy::Group, SortOrder::Descending);
-        records = sort_urls(records, &sort_cfg);
+        records = sort_urls(records, &sort_cfg, Utc::now());
 
         expected_order.reverse();
         for (i, r) in records.iter().enumerate() {
             assert_eq!(r.group, expected_order[i])
         }
     }
+
+    #[test]
+    fn test_sort_by_time() {
+        let now = Utc::now();
+        let mut records = fix_url_records();
+        records.truncate(3);
+        for (i, r) in records.iter_mut().enumerate() {
+            r.created_at = Some(now - Duration::days(i as i64));
+            r.updated_at = Some(now + Duration::days(i as i64));
+        }
+        records[0].last_opened_at = Some(now);
+        records[2].last_opened_at = Some(now - Duration::hours(1));
+
+        let names = |records: &[URLRecord]| -> Vec<String> {
+            records.iter().map(|r| r.name.clone()).collect()
+        };
+
+        println!("Should sort by creation time...");
+        let sorted = sort_urls(records.clone(), &SortConfig::new_by(SortBy::Created), now);
+        assert_eq!(names(&sorted), vec!["three", "Two", "one"]);
+
+        println!("Should sort by update time...");
+        let sorted = sort_urls(records.clone(), &SortConfig::new_by(SortBy::Updated), now);
+        assert_eq!(names(&sorted), vec!["one", "Two", "three"]);
+
+        println!("Should sort by last opened time, starting with never opened...");
+        let sort_by = SortBy::from_str("opened").expect("Failed to parse sort column");
+        let sorted = sort_urls(records.clone(), &SortConfig::new_by(sort_by), now);
+        assert_eq!(names(&sorted), vec!["Two", "three", "one"]);
+
+        let sorted = sort_urls(
+            records,
+            &SortConfig::new(SortBy::LastOpened, SortOrder::Descending),
+            now,
+        );
+        assert_eq!(names(&sorted), vec!["one", "three", "Two"]);
+    }
+
+    #[test]
+    fn test_sort_by_frecency() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut records = fix_url_records();
+        records.truncate(4);
+        // Opened often, but long time ago
+        for _ in 0..5 {
+            records[1].record_open(now - Duration::days(200));
+        }
+        // Opened once recently
+        records[2].record_open(now - Duration::hours(1));
+        // Opened twice recently
+        records[3].record_open(now - Duration::days(2));
+        records[3].record_open(now - Duration::days(3));
+
+        let names = |records: &[URLRecord]| -> Vec<String> {
+            records.iter().map(|r| r.name.clone()).collect()
+        };
+
+        println!("Should sort by frecency starting from the highest score...");
+        let sort_cfg = SortConfig::new(SortBy::Frecency, SortOrder::Descending);
+        let sorted = sort_urls(records.clone(), &sort_cfg, now);
+        assert_eq!(names(&sorted), vec!["FOUR", "three", "Two", "one"]);
+
+        println!("Should change order as opens get older...");
+        let sorted = sort_urls(records.clone(), &sort_cfg, now + Duration::days(100));
+        assert_eq!(names(&sorted), vec!["Two", "FOUR", "three", "one"]);
+
+        println!("Should keep order of records with the same score...");
+        let records = fix_url_records();
+        let sorted = sort_urls(records.clone(), &sort_cfg, now);
+        assert_eq!(names(&sorted), names(&records));
+    }
 }
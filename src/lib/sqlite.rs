@@ -0,0 +1,1025 @@
+use crate::diff::Change;
+use crate::error::Error;
+use crate::filters::StorageQuery;
+use crate::groups::GROUP_SEPARATOR;
+use crate::migration::{Migrations, SCHEMA_VERSION};
+use crate::storage::{is_purged, is_same, is_unique};
+use crate::types::{TrashedRecord, URLRecord, URLRegistry};
+use crate::Repository;
+use chrono::{DateTime, Utc};
+use rusqlite::{
+    params, params_from_iter, Connection, OptionalExtension, Transaction, TransactionBehavior,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS urls (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    url_group TEXT NOT NULL,
+    keyword TEXT NOT NULL DEFAULT '',
+    record TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS urls_group_idx ON urls(url_group);
+CREATE INDEX IF NOT EXISTS urls_name_group_idx ON urls(name, url_group);
+CREATE TABLE IF NOT EXISTS tags (
+    url_id TEXT NOT NULL REFERENCES urls(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (url_id, tag)
+);
+CREATE INDEX IF NOT EXISTS tags_tag_idx ON tags(tag);
+CREATE TABLE IF NOT EXISTS trash (
+    id TEXT PRIMARY KEY NOT NULL,
+    deleted_at TEXT NOT NULL,
+    record TEXT NOT NULL
+);
+"#;
+
+/// Keywords are unique among the records that have them. The index is created after
+/// the column is added to databases created before keywords were introduced.
+const KEYWORD_INDEX: &str =
+    "CREATE UNIQUE INDEX IF NOT EXISTS urls_keyword_idx ON urls(keyword) WHERE keyword <> ''";
+
+/// SQLite storage was introduced with the schema version 1
+const MIN_SCHEMA_VERSION: u32 = 1;
+
+/// SqliteStorage keeps URLs in the embedded SQLite database.
+/// Records are stored as JSON documents, while id, name, group, keyword and tag keys
+/// are kept in separate indexed columns, so that lookups and filtering by group
+/// or tags do not require parsing the whole registry.
+/// Read-check-write sequences run in immediate transactions, which take the write
+/// lock up front, so that uniqueness checks of two processes cannot interleave.
+/// Schema version is kept in the `user_version` pragma and the records are
+/// migrated on open with the same migrations as the FileStorage.
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn new_urls_repository(file_path: &str) -> Result<SqliteStorage, Error> {
+        let path = Path::new(file_path);
+        if let Some(dir_path) = path.parent() {
+            if !dir_path.as_os_str().is_empty() && !dir_path.exists() {
+                fs::create_dir_all(dir_path)?;
+            }
+        }
+
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+
+        let storage = SqliteStorage { connection };
+        storage.ensure_keyword_column()?;
+        storage.ensure_migrated(file_path)?;
+
+        Ok(storage)
+    }
+
+    /// Migrates records to the current schema version, if needed.
+    /// Before the migration, the database is copied to `<FILE>.v<VERSION>.bak`.
+    fn ensure_migrated(&self, file_path: &str) -> Result<(), Error> {
+        let user_version: u32 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let version = user_version.max(MIN_SCHEMA_VERSION);
+
+        if version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedVersion {
+                version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let records = self.list_raw()?;
+        let trash = self.list_trash_raw()?;
+        if version < SCHEMA_VERSION && !(records.is_empty() && trash.is_empty()) {
+            fs::copy(file_path, format!("{}.v{}.bak", file_path, version))?;
+
+            let mut registry = serde_json::json!({
+                "version": version,
+                "urls": {"items": records},
+                "trash": trash,
+            });
+            Migrations::for_file(file_path).migrate(&mut registry)?;
+            let migrated: URLRegistry = serde_json::from_value(registry)?;
+
+            let tx = immediate_transaction(&self.connection)?;
+            tx.execute("DELETE FROM urls", [])?;
+            for r in &migrated.urls.items {
+                insert_record(&tx, r)?;
+            }
+            tx.execute("DELETE FROM trash", [])?;
+            for t in &migrated.trash {
+                trash_row(&tx, t)?;
+            }
+            tx.commit()?;
+        }
+
+        if user_version != SCHEMA_VERSION {
+            self.connection
+                .execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the keyword column to the databases created before keywords were introduced,
+    /// filling it from the records. If records already share the keyword, only the first
+    /// one keeps it in the column, so that the others fail to be saved until it is changed.
+    fn ensure_keyword_column(&self) -> Result<(), Error> {
+        let exists: bool = self.connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('urls') WHERE name = 'keyword'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !exists {
+            let tx = immediate_transaction(&self.connection)?;
+            tx.execute(
+                "ALTER TABLE urls ADD COLUMN keyword TEXT NOT NULL DEFAULT ''",
+                [],
+            )?;
+
+            let rows: Vec<(String, String)> = {
+                let mut stmt = tx.prepare("SELECT id, record FROM urls ORDER BY rowid")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<Result<_, _>>()?
+            };
+            let mut taken = HashSet::new();
+            for (id, record) in rows {
+                let record: serde_json::Value = serde_json::from_str(&record)?;
+                match record.get("keyword").and_then(|k| k.as_str()) {
+                    Some(keyword) if !keyword.is_empty() && taken.insert(keyword.to_string()) => {
+                        tx.execute(
+                            "UPDATE urls SET keyword = ?1 WHERE id = ?2",
+                            params![keyword, id],
+                        )?;
+                    }
+                    _ => {}
+                }
+            }
+            tx.commit()?;
+        }
+
+        self.connection.execute_batch(KEYWORD_INDEX)?;
+        Ok(())
+    }
+
+    /// Applies changes without checking uniqueness on the way, so that e.g. names can be swapped,
+    /// and checks the names of changed records before committing the transaction, unless
+    /// `check_unique` is off. Keywords of the changed records are released first, so that
+    /// they can be swapped as well without violating the unique index.
+    fn apply_changes(
+        &self,
+        changes: &[Change],
+        deleted_at: DateTime<Utc>,
+        check_unique: bool,
+    ) -> Result<(), Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        let mut current = HashMap::new();
+        for c in changes {
+            let record = get_record(&tx, c.id())?;
+            if c.conflicts_with(record.as_ref()) {
+                return Err(Error::Conflict(c.id().to_string()));
+            }
+            if let Some(r) = record {
+                current.insert(r.id.clone(), r);
+            }
+        }
+
+        for id in current.keys() {
+            tx.execute("UPDATE urls SET keyword = '' WHERE id = ?1", params![id])?;
+        }
+        for c in changes {
+            match c {
+                Change::Added(r) => {
+                    tx.execute("DELETE FROM trash WHERE id = ?1", params![r.id])?;
+                    if check_unique {
+                        ensure_keyword_free(&tx, r)?;
+                    }
+                    insert_row(&tx, r)?;
+                }
+                Change::Removed(r) => {
+                    let removed = current.remove(&r.id).unwrap_or_else(|| r.clone());
+                    trash_row(&tx, &TrashedRecord::new(removed, deleted_at))?;
+                }
+                Change::Modified { before, after } => {
+                    let after = match current.get(&before.id) {
+                        Some(current) => after.clone().with_usage_of(current),
+                        None => after.clone(),
+                    };
+                    if check_unique {
+                        ensure_keyword_free(&tx, &after)?;
+                    }
+                    update_row(&tx, &before.id, &after)?;
+                }
+            }
+        }
+
+        for c in changes.iter().filter(|_| check_unique) {
+            if let Change::Added(r) | Change::Modified { after: r, .. } = c {
+                let same_name = find_by_name_and_group(&tx, &r.name, &r.group)?;
+                if !is_unique(&same_name, r) {
+                    return Err(Error::duplicate(&r.name, &r.group));
+                }
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Lists records without deserializing them, as they might be in the older schema version
+    fn list_raw(&self) -> Result<Vec<serde_json::Value>, Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT record FROM urls ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut records = vec![];
+        for r in rows {
+            records.push(serde_json::from_str(&r?)?);
+        }
+
+        Ok(records)
+    }
+
+    /// Lists trashed records without deserializing them, in the same shape as in the registry file
+    fn list_trash_raw(&self) -> Result<Vec<serde_json::Value>, Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT deleted_at, record FROM trash ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut trash = vec![];
+        for r in rows {
+            let (deleted_at, record) = r?;
+            trash.push(serde_json::json!({
+                "record": serde_json::from_str::<serde_json::Value>(&record)?,
+                "deleted_at": deleted_at,
+            }));
+        }
+
+        Ok(trash)
+    }
+}
+
+impl Repository for SqliteStorage {
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+        insert_record(&tx, &record)?;
+        tx.commit()?;
+
+        Ok(record)
+    }
+
+    /// Adds all records to the registry as long as all of them are unique
+    /// If at least one name-group pair or keyword is not unique, none of the URLs is saved
+    fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+        for r in &records {
+            insert_record(&tx, r)?;
+        }
+        tx.commit()?;
+
+        Ok(records)
+    }
+
+    fn delete_by_id(&self, id: &str) -> Result<bool, Error> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM urls WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    fn list(&self) -> Result<Vec<URLRecord>, Error> {
+        query_records(
+            &self.connection,
+            "SELECT record FROM urls ORDER BY rowid",
+            Vec::<String>::new(),
+        )
+    }
+
+    /// Uses the unique index on keywords, which covers only records with the keyword
+    fn get_by_keyword(&self, keyword: &str) -> Result<Option<URLRecord>, Error> {
+        if keyword.is_empty() {
+            return Ok(None);
+        }
+
+        let records = query_records(
+            &self.connection,
+            "SELECT record FROM urls WHERE keyword = ?1 AND keyword <> ''",
+            vec![keyword.to_string()],
+        )?;
+        Ok(records.into_iter().next())
+    }
+
+    /// Uses the index on groups, matching nested groups with the range of paths
+    /// starting with `<GROUP>/`, and the index on tag keys
+    fn list_matching(&self, query: &StorageQuery) -> Result<Vec<URLRecord>, Error> {
+        match query {
+            StorageQuery::Group(group) => query_records(
+                &self.connection,
+                "SELECT record FROM urls WHERE url_group = ?1 OR (url_group >= ?2 AND url_group < ?3) ORDER BY rowid",
+                vec![
+                    group.clone(),
+                    format!("{}{}", group, GROUP_SEPARATOR),
+                    // The character following the separator ends the range of nested groups
+                    format!("{}{}", group, (GROUP_SEPARATOR as u8 + 1) as char),
+                ],
+            ),
+            StorageQuery::TagKeys(keys) => {
+                let placeholders = vec!["?"; keys.len()].join(", ");
+                query_records(
+                    &self.connection,
+                    &format!(
+                        "SELECT record FROM urls WHERE id IN (SELECT url_id FROM tags WHERE tag IN ({})) ORDER BY rowid",
+                        placeholders
+                    ),
+                    keys.clone(),
+                )
+            }
+        }
+    }
+
+    fn get(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        get_record(&self.connection, id)
+    }
+
+    fn list_groups(&self) -> Result<Vec<String>, Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT DISTINCT url_group FROM urls")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut groups = vec![];
+        for g in rows {
+            groups.push(g?);
+        }
+
+        Ok(groups)
+    }
+
+    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        if get_record(&tx, id)?.is_none() {
+            return Ok(None);
+        }
+        for r in find_by_name_and_group(&tx, &record.name, &record.group)? {
+            if is_same(&r, &record) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+        }
+        ensure_keyword_free(&tx, &record)?;
+
+        update_row(&tx, id, &record)?;
+        tx.commit()?;
+
+        Ok(Some(record))
+    }
+
+    fn record_open(&self, id: &str, opened_at: DateTime<Utc>) -> Result<Option<URLRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        let mut record = match get_record(&tx, id)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        record.record_open(opened_at);
+
+        tx.execute(
+            "UPDATE urls SET record = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&record)?, id],
+        )?;
+        tx.commit()?;
+
+        Ok(Some(record))
+    }
+
+    fn trash(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<Option<TrashedRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        let record = match get_record(&tx, id)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let trashed = TrashedRecord::new(record, deleted_at);
+
+        trash_row(&tx, &trashed)?;
+        tx.commit()?;
+
+        Ok(Some(trashed))
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+        list_trash(&self.connection)
+    }
+
+    fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        let record: Option<String> = tx
+            .query_row(
+                "SELECT record FROM trash WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let record: URLRecord = match record {
+            Some(r) => serde_json::from_str(&r)?,
+            None => return Ok(None),
+        };
+
+        insert_record(&tx, &record)?;
+        tx.execute("DELETE FROM trash WHERE id = ?1", params![id])?;
+        tx.commit()?;
+
+        Ok(Some(record))
+    }
+
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error> {
+        let tx = immediate_transaction(&self.connection)?;
+
+        let purged: Vec<TrashedRecord> = list_trash(&tx)?
+            .into_iter()
+            .filter(|t| is_purged(t, deleted_before))
+            .collect();
+        for t in &purged {
+            tx.execute("DELETE FROM trash WHERE id = ?1", params![t.record.id])?;
+        }
+        tx.commit()?;
+
+        Ok(purged)
+    }
+
+    fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        let previous = self.list()?;
+
+        let tx = immediate_transaction(&self.connection)?;
+        tx.execute("DELETE FROM urls", [])?;
+        for r in &records {
+            insert_record(&tx, r)?;
+        }
+        tx.commit()?;
+
+        Ok(previous)
+    }
+
+    fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.apply_changes(changes, deleted_at, true)
+    }
+
+    fn apply_unchecked(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.apply_changes(changes, deleted_at, false)
+    }
+}
+
+/// Starts the transaction which takes the write lock immediately, instead of on the first write
+fn immediate_transaction(connection: &Connection) -> Result<Transaction<'_>, Error> {
+    Ok(Transaction::new_unchecked(
+        connection,
+        TransactionBehavior::Immediate,
+    )?)
+}
+
+fn query_records(
+    connection: &Connection,
+    sql: &str,
+    params: Vec<String>,
+) -> Result<Vec<URLRecord>, Error> {
+    let mut stmt = connection.prepare(sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |row| row.get::<_, String>(0))?;
+
+    let mut urls = vec![];
+    for r in rows {
+        urls.push(serde_json::from_str(&r?)?);
+    }
+
+    Ok(urls)
+}
+
+fn insert_record(tx: &Transaction, record: &URLRecord) -> Result<(), Error> {
+    let same_name = find_by_name_and_group(tx, &record.name, &record.group)?;
+    if !is_unique(&same_name, record) {
+        return Err(Error::duplicate(&record.name, &record.group));
+    }
+    ensure_keyword_free(tx, record)?;
+
+    insert_row(tx, record)
+}
+
+/// Fails if the keyword of the record is used by any other record
+fn ensure_keyword_free(tx: &Transaction, record: &URLRecord) -> Result<(), Error> {
+    if indexed_keyword(tx, record)? != record.keyword {
+        return Err(Error::DuplicateKeyword(record.keyword.clone()));
+    }
+
+    Ok(())
+}
+
+/// Returns the keyword to be stored in the indexed column, which is left empty
+/// if the keyword is used by any other record, as done by the keyword migration
+fn indexed_keyword<'a>(tx: &Transaction, record: &'a URLRecord) -> Result<&'a str, Error> {
+    if record.keyword.is_empty() {
+        return Ok("");
+    }
+
+    let taken: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM urls WHERE keyword = ?1 AND id <> ?2",
+        params![record.keyword, record.id],
+        |row| row.get(0),
+    )?;
+    Ok(if taken { "" } else { &record.keyword })
+}
+
+fn insert_row(tx: &Transaction, record: &URLRecord) -> Result<(), Error> {
+    tx.execute(
+        "INSERT INTO urls (id, name, url_group, keyword, record) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            record.id,
+            record.name,
+            record.group,
+            indexed_keyword(tx, record)?,
+            serde_json::to_string(record)?
+        ],
+    )?;
+    insert_tags(tx, record)
+}
+
+/// Updates the row with the given id, which can change as well. Tags are removed before
+/// the update, as their foreign key does not follow the change of the id.
+fn update_row(tx: &Transaction, id: &str, record: &URLRecord) -> Result<(), Error> {
+    tx.execute("DELETE FROM tags WHERE url_id = ?1", params![id])?;
+    tx.execute(
+        "UPDATE urls SET id = ?1, name = ?2, url_group = ?3, keyword = ?4, record = ?5 WHERE id = ?6",
+        params![
+            record.id,
+            record.name,
+            record.group,
+            indexed_keyword(tx, record)?,
+            serde_json::to_string(record)?,
+            id
+        ],
+    )?;
+    insert_tags(tx, record)
+}
+
+fn trash_row(tx: &Transaction, trashed: &TrashedRecord) -> Result<(), Error> {
+    tx.execute("DELETE FROM urls WHERE id = ?1", params![trashed.record.id])?;
+    tx.execute(
+        "INSERT OR REPLACE INTO trash (id, deleted_at, record) VALUES (?1, ?2, ?3)",
+        params![
+            trashed.record.id,
+            trashed.deleted_at.to_rfc3339(),
+            serde_json::to_string(&trashed.record)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_tags(tx: &Transaction, record: &URLRecord) -> Result<(), Error> {
+    let mut stmt = tx.prepare("INSERT INTO tags (url_id, tag) VALUES (?1, ?2)")?;
+    for t in record.tags.keys() {
+        stmt.execute(params![record.id, t])?;
+    }
+    Ok(())
+}
+
+fn get_record(connection: &Connection, id: &str) -> Result<Option<URLRecord>, Error> {
+    let record: Option<String> = connection
+        .query_row(
+            "SELECT record FROM urls WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match record {
+        Some(r) => Ok(Some(serde_json::from_str(&r)?)),
+        None => Ok(None),
+    }
+}
+
+fn list_trash(connection: &Connection) -> Result<Vec<TrashedRecord>, Error> {
+    let mut stmt = connection.prepare("SELECT deleted_at, record FROM trash ORDER BY rowid")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut trash = vec![];
+    for r in rows {
+        let (deleted_at, record) = r?;
+        let deleted_at = DateTime::parse_from_rfc3339(&deleted_at)
+            .map_err(|err| Error::Parse(err.to_string()))?;
+        trash.push(TrashedRecord::new(
+            serde_json::from_str(&record)?,
+            deleted_at.with_timezone(&Utc),
+        ));
+    }
+
+    Ok(trash)
+}
+
+fn find_by_name_and_group(
+    connection: &Connection,
+    name: &str,
+    group: &str,
+) -> Result<Vec<URLRecord>, Error> {
+    query_records(
+        connection,
+        "SELECT record FROM urls WHERE name = ?1 AND url_group = ?2",
+        vec![name.to_string(), group.to_string()],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::Change;
+    use crate::filters::StorageQuery;
+    use crate::migration::SCHEMA_VERSION;
+    use crate::registry::URLRegistry;
+    use crate::sqlite::SqliteStorage;
+    use crate::storage::FileStorage;
+    use crate::types::URLRecord;
+    use crate::util::create_temp_file;
+    use crate::{Importer, Registry, RegistryReader, Repository};
+    use chrono::Utc;
+    use rusqlite::Connection;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn sqlite_storage_test() {
+        let file_path = create_temp_file("sqlite_storage_test.db").expect("Failed to create file");
+        let storage = SqliteStorage::new_urls_repository(file_path.to_str().unwrap())
+            .expect("Failed to open SQLite storage");
+
+        let records = vec![
+            URLRecord::new("https://one.com", "one", "default", vec!["tag"]),
+            URLRecord::new("https://two.com", "two", "default", Vec::<String>::new()),
+            URLRecord::new("https://three.com", "three", "other", vec!["tag", "more"]),
+        ];
+
+        println!("Should add URLs...");
+        for r in &records {
+            storage.add(r.clone()).expect("Failed to add URL");
+        }
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 3);
+        for (i, u) in urls.iter().enumerate() {
+            assert_eq!(u.id, records[i].id);
+            assert_eq!(u.name, records[i].name);
+            assert_eq!(u.tags, records[i].tags);
+        }
+
+        println!("Should not add duplicated name in group...");
+        let result = storage.add(URLRecord::new(
+            "https://dup.com",
+            "one",
+            "default",
+            Vec::<String>::new(),
+        ));
+        assert!(result.is_err());
+
+        println!("Should not add any URL from batch if one is not unique...");
+        let result = storage.add_batch(vec![
+            URLRecord::new("https://four.com", "four", "default", Vec::<String>::new()),
+            URLRecord::new("https://dup.com", "three", "other", Vec::<String>::new()),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(storage.list().expect("Failed to list URLs").len(), 3);
+
+        println!("Should list groups...");
+        let mut groups = storage.list_groups().expect("Failed to list groups");
+        groups.sort();
+        assert_eq!(groups, vec!["default", "other"]);
+
+        println!("Should update URL...");
+        let mut updated = records[1].clone();
+        updated.group = "other".to_string();
+        updated.set_tag("new-tag");
+        let result = storage
+            .update(&updated.id, updated.clone())
+            .expect("Failed to update URL")
+            .expect("URL is None");
+        assert_eq!(result.group, "other");
+        let record = storage
+            .get(&updated.id)
+            .expect("Failed to get URL")
+            .expect("URL is None");
+        assert_eq!(record.group, "other");
+        assert!(record.tags.contains_key("new-tag"));
+
+        println!("Should not update to duplicated name in group...");
+        let mut duplicate = records[1].clone();
+        duplicate.name = "three".to_string();
+        duplicate.group = "other".to_string();
+        assert!(storage.update(&duplicate.id, duplicate.clone()).is_err());
+
+        println!("Should return None when updating not existing URL...");
+        let not_existing = URLRecord::new("url", "name", "group", Vec::<String>::new());
+        let result = storage
+            .update(&not_existing.id, not_existing.clone())
+            .expect("Failed to update URL");
+        assert!(result.is_none());
+
+        println!("Should delete URL...");
+        assert!(storage
+            .delete_by_id(&records[0].id)
+            .expect("Failed to delete"));
+        assert!(!storage
+            .delete_by_id(&records[0].id)
+            .expect("Failed to delete"));
+        assert!(storage
+            .get(&records[0].id)
+            .expect("Failed to get")
+            .is_none());
+        assert_eq!(storage.list().expect("Failed to list URLs").len(), 2);
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn list_matching_test() {
+        let file_path = create_temp_file("sqlite_matching_test.db").expect("Failed to create file");
+        let storage = SqliteStorage::new_urls_repository(file_path.to_str().unwrap())
+            .expect("Failed to open SQLite storage");
+        let mut records = vec![
+            URLRecord::new("https://one.com", "one", "work", vec!["env=prod"]),
+            URLRecord::new("https://two.com", "two", "work/infra", vec!["git"]),
+            URLRecord::new("https://three.com", "three", "work-old", vec!["env"]),
+            URLRecord::new("https://four.com", "four", "home", Vec::<String>::new()),
+        ];
+        records[1].keyword = "infra".to_string();
+        storage
+            .add_batch(records.clone())
+            .expect("Failed to add URLs");
+
+        let test_cases = vec![
+            (StorageQuery::Group("work".to_string()), vec!["one", "two"]),
+            (StorageQuery::Group("work/infra".to_string()), vec!["two"]),
+            (
+                StorageQuery::TagKeys(vec!["env".to_string()]),
+                vec!["one", "three"],
+            ),
+            (
+                StorageQuery::TagKeys(vec!["git".to_string(), "other".to_string()]),
+                vec!["two"],
+            ),
+        ];
+
+        println!("Should match the same records as in memory...");
+        for (query, expected) in test_cases {
+            let names: Vec<String> = storage
+                .list_matching(&query)
+                .expect("Failed to list URLs")
+                .into_iter()
+                .map(|r| r.name)
+                .collect();
+            assert_eq!(names, expected);
+            let in_memory: Vec<String> = records
+                .iter()
+                .filter(|r| query.matches(r))
+                .map(|r| r.name.clone())
+                .collect();
+            assert_eq!(names, in_memory);
+        }
+
+        println!("Should get records by keyword...");
+        let by_keyword = |keyword: &str| {
+            storage
+                .get_by_keyword(keyword)
+                .expect("Failed to get URL")
+                .map(|r| r.name)
+        };
+        assert_eq!(by_keyword("infra"), Some("two".to_string()));
+        assert_eq!(by_keyword("other"), None);
+        assert_eq!(by_keyword(""), None);
+
+        println!("Should use indexes on group and tags...");
+        let plan = |sql: &str| -> String {
+            let mut stmt = storage
+                .connection
+                .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+                .expect("Failed to prepare query");
+            let params = vec![""; stmt.parameter_count()];
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(params), |row| {
+                    row.get::<_, String>(3)
+                })
+                .expect("Failed to explain query");
+            rows.map(|r| r.expect("Failed to read plan")).collect()
+        };
+        assert!(plan(
+            "SELECT record FROM urls WHERE url_group = ?1 OR (url_group >= ?2 AND url_group < ?3)"
+        )
+        .contains("urls_group_idx"));
+        assert!(
+            plan("SELECT record FROM urls WHERE id IN (SELECT url_id FROM tags WHERE tag IN (?1, ?2, ?3))")
+                .contains("tags_tag_idx")
+        );
+        assert!(
+            plan("SELECT record FROM urls WHERE keyword = ?1 AND keyword <> ''")
+                .contains("urls_keyword_idx")
+        );
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn change_id_test() {
+        let file_path =
+            create_temp_file("sqlite_change_id_test.db").expect("Failed to create file");
+        let storage = SqliteStorage::new_urls_repository(file_path.to_str().unwrap())
+            .expect("Failed to open SQLite storage");
+        let before = storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                vec!["tag"],
+            ))
+            .expect("Failed to add URL");
+
+        println!("Should change id of the tagged record...");
+        let after = URLRecord {
+            id: "new-id".to_string(),
+            ..before.clone()
+        };
+        storage
+            .apply(
+                &[Change::Modified {
+                    before: before.clone(),
+                    after: after.clone(),
+                }],
+                Utc::now(),
+            )
+            .expect("Failed to change id");
+        assert_eq!(storage.get(&before.id).expect("Failed to get URL"), None);
+        assert_eq!(
+            storage.get(&after.id).expect("Failed to get URL"),
+            Some(after.clone())
+        );
+        let tagged = storage
+            .list_matching(&StorageQuery::TagKeys(vec!["tag".to_string()]))
+            .expect("Failed to list URLs");
+        assert_eq!(tagged, vec![after]);
+
+        println!("Cleanup...");
+        drop(storage);
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn migrate_from_json_file_test() {
+        let json_path =
+            create_temp_file("sqlite_migrate_test.json").expect("Failed to create file");
+        let db_path = create_temp_file("sqlite_migrate_test.db").expect("Failed to create file");
+
+        let file_storage =
+            FileStorage::new_urls_repository(json_path.to_str().unwrap().to_string());
+        let records = vec![
+            URLRecord::new("https://one.com", "one", "default", vec!["tag"]),
+            URLRecord::new("https://two.com", "two", "other", Vec::<String>::new()),
+        ];
+        file_storage
+            .add_batch(records.clone())
+            .expect("Failed to add URLs");
+
+        let registry = URLRegistry::new_sqlite(db_path.to_str().unwrap())
+            .expect("Failed to initialize registry");
+
+        println!("Should import URLs from JSON file keeping ids...");
+        let imported = registry
+            .import_from_file(json_path.to_str().unwrap())
+            .expect("Failed to import URLs");
+        assert_eq!(imported.len(), 2);
+
+        let urls = registry.list_urls(None, None).expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        for (i, u) in urls.iter().enumerate() {
+            assert_eq!(u.id, records[i].id);
+            assert_eq!(u.url, records[i].url);
+        }
+
+        println!("Should fail if URLs not unique...");
+        assert!(registry
+            .import_from_file(json_path.to_str().unwrap())
+            .is_err());
+        registry
+            .create("three", "https://three.com", None, vec![])
+            .expect("Failed to create URL");
+
+        println!("Cleanup...");
+        fs::remove_file(json_path).expect("Failed to remove file");
+        fs::remove_file(format!(
+            "{}.history.json",
+            db_path.to_str().expect("Invalid path")
+        ))
+        .expect("Failed to remove file");
+        fs::remove_file(db_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn migrate_schema_test() {
+        let db_path = create_temp_file("sqlite_schema_test.db").expect("Failed to create file");
+        let db_path = db_path.to_str().expect("Invalid path");
+
+        let storage =
+            SqliteStorage::new_urls_repository(db_path).expect("Failed to initialize storage");
+        storage
+            .connection
+            .execute_batch(
+                r#"
+                INSERT INTO urls (id, name, url_group, record) VALUES ('abcd', 'GitHub', 'dev',
+                    '{"id": "abcd", "url": "https://github.com", "name": "GitHub", "group": "dev", "tags": {"git": true, "old": false}}');
+                INSERT INTO trash (id, deleted_at, record) VALUES ('efgh', '2021-01-01T00:00:00+00:00',
+                    '{"id": "efgh", "url": "https://gitlab.com", "name": "GitLab", "group": "dev", "tags": {"git": true}}');
+                PRAGMA user_version = 2;
+                "#,
+            )
+            .expect("Failed to write old records");
+        drop(storage);
+
+        println!("Should migrate records and trash on open...");
+        let storage =
+            SqliteStorage::new_urls_repository(db_path).expect("Failed to initialize storage");
+        let record = storage
+            .get("abcd")
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(record.tags_as_string(), "git");
+        let trash = storage.list_trash().expect("Failed to list trash");
+        assert_eq!(trash[0].record.tags_as_string(), "git");
+
+        println!("Should keep backup of the old database...");
+        let backup = format!("{}.v2.bak", db_path);
+        assert!(Path::new(&backup).exists());
+
+        println!("Cleanup...");
+        fs::remove_file(backup).expect("Failed to remove file");
+        fs::remove_file(db_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn keyword_column_test() {
+        let db_path = create_temp_file("sqlite_keyword_test.db").expect("Failed to create file");
+        let db_path = db_path.to_str().expect("Invalid path");
+
+        let connection = Connection::open(db_path).expect("Failed to open database");
+        connection
+            .execute_batch(&format!(
+                r#"
+                CREATE TABLE urls (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    name TEXT NOT NULL,
+                    url_group TEXT NOT NULL,
+                    record TEXT NOT NULL
+                );
+                INSERT INTO urls (id, name, url_group, record) VALUES
+                    ('a', 'one', 'dev', '{{"id": "a", "keyword": "gh"}}'),
+                    ('b', 'two', 'dev', '{{"id": "b", "keyword": "gh"}}'),
+                    ('c', 'three', 'dev', '{{"id": "c"}}');
+                PRAGMA user_version = {};
+                "#,
+                SCHEMA_VERSION
+            ))
+            .expect("Failed to write old records");
+        drop(connection);
+
+        println!("Should fill keyword column keeping the first of the duplicates...");
+        let storage =
+            SqliteStorage::new_urls_repository(db_path).expect("Failed to initialize storage");
+        let keywords: Vec<String> = storage
+            .connection
+            .prepare("SELECT keyword FROM urls ORDER BY rowid")
+            .expect("Failed to prepare query")
+            .query_map([], |row| row.get(0))
+            .expect("Failed to query keywords")
+            .collect::<Result<_, _>>()
+            .expect("Failed to read keywords");
+        assert_eq!(keywords, vec!["gh", "", ""]);
+
+        println!("Should enforce unique keywords with the index...");
+        let result = storage
+            .connection
+            .execute("UPDATE urls SET keyword = 'gh' WHERE id = 'c'", []);
+        assert!(result.is_err());
+
+        println!("Cleanup...");
+        drop(storage);
+        fs::remove_file(db_path).expect("Failed to remove file");
+    }
+}
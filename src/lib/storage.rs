@@ -1,163 +1,580 @@
-use super::types::{URLRecord, URLRegistry};
-use crate::import::v0_0_x;
-use crate::types::URLs;
-use crate::{Repository, RepositoryOld};
+use super::types::{TrashedRecord, URLRecord, URLRegistry};
+use crate::backup::{BackupPolicy, Backups};
+use crate::diff::Change;
+use crate::error::Error;
+use crate::format::Format;
+use crate::migration::{load_registry, schema_version, Migrations, SCHEMA_VERSION};
+use crate::Repository;
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::convert::TryInto;
-use std::error::Error;
 use std::fs;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
+const LOCK_FILE_SUFFIX: &str = ".lock";
+const TEMP_FILE_SUFFIX: &str = ".tmp";
+
+/// Coarsest resolution of modification times among common filesystems, which is 2 seconds on FAT.
+/// Files modified within it from the moment they were read could have been modified again
+/// without changing their modification time.
+const MTIME_RESOLUTION: Duration = Duration::from_secs(2);
+
+/// FileStorage keeps URLs in a single JSON file.
+/// Every read-modify-write cycle is guarded by an advisory lock on a sidecar `.lock` file,
+/// and changes are written to a temporary file which is then atomically renamed over the
+/// registry file, so that concurrent processes or interrupted writes cannot corrupt it.
+/// Files with older schema version are migrated on first access.
+/// If backups are enabled, the file is copied to the rotating snapshot before every change.
+/// The parsed registry is kept in memory and reused as long as the file on disk stays the same,
+/// which is determined by its modification time, size and inode. If the file was modified
+/// shortly before it was read, its content is compared as well.
+/// The file is written in the format it was read in, unless the format is set explicitly.
 pub struct FileStorage {
     file_path: String,
+    migrated: AtomicBool,
+    backups: Backups,
+    format: Option<Format>,
+    cache: Mutex<Option<CachedRegistry>>,
+}
+
+/// Parsed registry together with the stamp and format of the file it was read from.
+/// Stamp of the missing file is None.
+struct CachedRegistry {
+    stamp: Option<FileStamp>,
+    /// Hash of the content, kept only if the stamp cannot be trusted on its own
+    racy_hash: Option<u64>,
+    format: Format,
+    registry: URLRegistry,
+}
+
+impl CachedRegistry {
+    fn new(
+        stamp: Option<FileStamp>,
+        content: &str,
+        format: Format,
+        registry: URLRegistry,
+    ) -> CachedRegistry {
+        let racy_hash = match &stamp {
+            Some(stamp) if stamp.is_racy(SystemTime::now()) => Some(hash(content)),
+            _ => None,
+        };
+        CachedRegistry {
+            stamp,
+            racy_hash,
+            format,
+            registry,
+        }
+    }
+}
+
+/// Identifies the version of the file on disk.
+/// As every write replaces the file, the inode changes even if the modification time
+/// and size happen to be the same.
+#[derive(Clone, Debug, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    inode: u64,
+}
+
+impl FileStamp {
+    fn of(metadata: &fs::Metadata) -> FileStamp {
+        FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            inode: inode(metadata),
+        }
+    }
+
+    /// Checks if the file read at `read_at` could be modified in place afterwards
+    /// without changing the stamp, as in "racy git"
+    fn is_racy(&self, read_at: SystemTime) -> bool {
+        match self.modified {
+            Some(modified) => match read_at.duration_since(modified) {
+                Ok(elapsed) => elapsed < MTIME_RESOLUTION,
+                Err(_) => true,
+            },
+            None => true,
+        }
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
 }
 
 impl FileStorage {
     pub fn new_urls_repository(file_path: String) -> FileStorage {
-        FileStorage { file_path }
+        let backups = Backups::new(&file_path, BackupPolicy::disabled());
+
+        FileStorage {
+            file_path,
+            migrated: AtomicBool::new(false),
+            backups,
+            format: None,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Sets the format in which the registry is written, converting the file on the next change
+    pub fn with_format(self, format: Format) -> FileStorage {
+        FileStorage {
+            format: Some(format),
+            ..self
+        }
     }
 
-    fn delete_url<F>(&self, match_first: F) -> Result<bool, Box<dyn std::error::Error>>
+    pub fn with_backups(self, policy: BackupPolicy) -> FileStorage {
+        FileStorage {
+            backups: Backups::new(&self.file_path, policy),
+            ..self
+        }
+    }
+
+    pub(crate) fn backups(&self) -> &Backups {
+        &self.backups
+    }
+
+    /// Migrates the file to the current schema version, if needed.
+    /// Before the migration, the file is copied to `<FILE>.v<VERSION>.bak`.
+    fn ensure_migrated(&self) -> Result<(), Error> {
+        if self.migrated.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let _lock = lock_file(&self.file_path, true)?;
+        if let Some(content) = read_content(&self.file_path)? {
+            let format = Format::detect(&self.file_path, &content);
+            let mut registry = format.parse(&content)?;
+            let version = schema_version(&registry);
+
+            if version != SCHEMA_VERSION {
+                Migrations::for_file(&self.file_path).migrate(&mut registry)?;
+
+                fs::copy(&self.file_path, backup_path(&self.file_path, version))?;
+                write_content(&self.file_path, &format.serialize(&registry)?)?;
+            }
+        }
+
+        self.migrated.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    fn delete_url<F>(&self, match_first: F) -> Result<bool, Error>
     where
         F: Fn(&URLRecord) -> bool,
     {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let mut registry = read_urls(&mut file)?;
+        self.modify(|registry| {
+            for (index, u) in registry.urls.items.iter().enumerate() {
+                if match_first(u) {
+                    registry.urls.items.remove(index);
+                    return Ok((true, true));
+                }
+            }
+
+            Ok((false, false))
+        })
+    }
+
+    /// Runs the `read` function on the registry while holding a shared lock
+    fn read<F, T>(&self, read: F) -> Result<T, Error>
+    where
+        F: FnOnce(&URLRegistry) -> Result<T, Error>,
+    {
+        self.ensure_migrated()?;
+        let _lock = lock_file(&self.file_path, false)?;
+        let mut cache = self.lock_cache();
+        let cached = self.load(cache.take())?;
+
+        let result = read(&cached.registry);
+        *cache = Some(cached);
+
+        result
+    }
+
+    /// Runs read-modify-write cycle while holding an exclusive lock.
+    /// The `modify` function returns the result together with the flag indicating if the
+    /// registry was changed and should be written back to the file.
+    /// The cached registry is dropped if anything fails, so that it is read again from the file.
+    fn modify<F, T>(&self, modify: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut URLRegistry) -> Result<(T, bool), Error>,
+    {
+        self.ensure_migrated()?;
+        let _lock = lock_file(&self.file_path, true)?;
+        let mut cache = self.lock_cache();
+        let mut cached = self.load(cache.take())?;
+
+        let (result, changed) = modify(&mut cached.registry)?;
+        if changed {
+            let format = self.format.unwrap_or(cached.format);
+            let content = format.serialize(&cached.registry)?;
+            self.backups.snapshot(Path::new(&self.file_path))?;
+            write_content(&self.file_path, &content)?;
+            let stamp = Some(FileStamp::of(&fs::metadata(&self.file_path)?));
+            cached = CachedRegistry::new(stamp, &content, format, cached.registry);
+        }
+        *cache = Some(cached);
 
-        for (index, u) in registry.urls.items.iter().enumerate() {
-            if match_first(u) {
-                registry.urls.items.remove(index);
-                write_urls(&mut file, registry)?;
-                return Ok(true);
+        Ok(result)
+    }
+
+    /// Returns the cached registry if the file did not change since it was read,
+    /// otherwise reads and parses the file again. Missing file is an empty registry.
+    /// Has to be called while holding the file lock.
+    fn load(&self, cached: Option<CachedRegistry>) -> Result<CachedRegistry, Error> {
+        let mut file = match open_urls_file(&self.file_path)? {
+            Some(file) => file,
+            None => {
+                return match cached {
+                    Some(cached) if cached.stamp.is_none() => Ok(cached),
+                    _ => Ok(CachedRegistry::new(
+                        None,
+                        "",
+                        Format::detect(&self.file_path, ""),
+                        URLRegistry::new(vec![]),
+                    )),
+                }
+            }
+        };
+        let stamp = Some(FileStamp::of(&file.metadata()?));
+
+        let cached = match cached {
+            Some(cached) if cached.stamp == stamp => match cached.racy_hash {
+                None => return Ok(cached),
+                Some(_) => Some(cached),
+            },
+            _ => None,
+        };
+
+        let content = read_file(&mut file)?;
+        if let Some(cached) = cached {
+            if cached.racy_hash == Some(hash(&content)) {
+                return Ok(CachedRegistry::new(
+                    stamp,
+                    &content,
+                    cached.format,
+                    cached.registry,
+                ));
             }
         }
 
-        Ok(false)
+        let format = Format::detect(&self.file_path, &content);
+        let registry = load_registry(&content, format)?;
+        Ok(CachedRegistry::new(stamp, &content, format, registry))
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, Option<CachedRegistry>> {
+        // The cache is only ever replaced as a whole, so it is consistent even if poisoned
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
     }
 }
 
 impl Repository for FileStorage {
-    fn add(&self, record: URLRecord) -> Result<URLRecord, Box<dyn Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let mut registry = read_urls(&mut file)?;
+    fn add(&self, record: URLRecord) -> Result<URLRecord, Error> {
+        self.modify(|registry| {
+            if !is_unique(&registry.urls.items, &record) {
+                return Err(Error::duplicate(&record.name, &record.group));
+            }
+            ensure_keyword_unique(&registry.urls.items, &record)?;
 
-        if !is_unique(&registry.urls.items, &record) {
-            return Err(not_unique_error(&record));
-        }
+            registry.urls.items.push(record.clone());
 
-        registry.urls.items.push(record.clone());
+            Ok((record, true))
+        })
+    }
+
+    /// Adds all records to the registry as long as all of them are unique
+    /// If at least one name-group pair or keyword is not unique, none of the URLs is saved
+    fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|registry| {
+            for r in &records {
+                if !is_unique(&registry.urls.items, r) {
+                    return Err(Error::duplicate(&r.name, &r.group));
+                }
+                ensure_keyword_unique(&registry.urls.items, r)?;
+                registry.urls.items.push(r.clone());
+            }
 
-        write_urls(&mut file, registry)?;
+            Ok((registry.urls.items.clone(), true))
+        })
+    }
 
-        Ok(record)
+    fn delete_by_id(&self, id: &str) -> Result<bool, Error> {
+        self.delete_url(|u| u.id == id)
     }
 
-    /// Adds all records to the registry as long as all of them are unique
-    /// If at least one name-group pair is not unique, none of the URLs is saved
-    fn add_batch(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Box<dyn Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let mut registry = read_urls(&mut file)?;
+    fn list(&self) -> Result<Vec<URLRecord>, Error> {
+        self.read(|registry| Ok(registry.urls.items.clone()))
+    }
 
-        for r in &records {
-            if !is_unique(&registry.urls.items, &r) {
-                return Err(not_unique_error(&r));
+    fn get(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.read(|registry| {
+            for url in &registry.urls.items {
+                if url.id == id {
+                    return Ok(Some(url.clone()));
+                }
             }
-            registry.urls.items.push(r.clone());
-        }
 
-        let registry = write_urls(&mut file, registry)?;
+            Ok(None)
+        })
+    }
+
+    fn list_groups(&self) -> Result<Vec<String>, Error> {
+        self.read(|registry| {
+            let groups: Vec<&str> = registry
+                .urls
+                .items
+                .iter()
+                .map(|e: &URLRecord| e.group.as_str())
+                .collect();
+
+            let mut distinct: HashMap<&str, bool> = HashMap::new();
+
+            for g in groups {
+                distinct.insert(g, false);
+            }
 
-        Ok(registry.urls.items)
+            Ok(distinct.keys().map(|k| k.to_string()).collect())
+        })
     }
 
-    fn delete_by_id(&self, id: &str) -> Result<bool, Box<dyn Error>> {
-        self.delete_url(|u| u.id == id)
+    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Error> {
+        self.modify(|registry| {
+            ensure_keyword_unique(&registry.urls.items, &record)?;
+            let mut found = false;
+            for i in 0..registry.urls.items.len() {
+                if is_same(&registry.urls.items[i], &record) {
+                    return Err(Error::duplicate(&record.name, &record.group));
+                }
+
+                if registry.urls.items[i].id == id {
+                    registry.urls.items[i] = record.clone();
+                    found = true
+                }
+            }
+            if !found {
+                return Ok((None, false));
+            }
+
+            Ok((Some(record), true))
+        })
+    }
+
+    fn record_open(&self, id: &str, opened_at: DateTime<Utc>) -> Result<Option<URLRecord>, Error> {
+        self.modify(
+            |registry| match registry.urls.items.iter_mut().find(|u| u.id == id) {
+                Some(u) => {
+                    u.record_open(opened_at);
+                    Ok((Some(u.clone()), true))
+                }
+                None => Ok((None, false)),
+            },
+        )
+    }
+
+    fn trash(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<Option<TrashedRecord>, Error> {
+        self.modify(|registry| {
+            let index = match registry.urls.items.iter().position(|u| u.id == id) {
+                Some(index) => index,
+                None => return Ok((None, false)),
+            };
+
+            let trashed = TrashedRecord::new(registry.urls.items.remove(index), deleted_at);
+            registry.trash.retain(|t| t.record.id != id);
+            registry.trash.push(trashed.clone());
+
+            Ok((Some(trashed), true))
+        })
     }
 
-    fn list(&self) -> Result<Vec<URLRecord>, Box<dyn std::error::Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let registry = read_urls(&mut file)?;
-        Ok(registry.urls.items)
+    fn list_trash(&self) -> Result<Vec<TrashedRecord>, Error> {
+        self.read(|registry| Ok(registry.trash.clone()))
     }
 
-    fn get(&self, id: &str) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let registry = read_urls(&mut file)?;
+    fn restore(&self, id: &str) -> Result<Option<URLRecord>, Error> {
+        self.modify(|registry| {
+            let index = match registry.trash.iter().position(|t| t.record.id == id) {
+                Some(index) => index,
+                None => return Ok((None, false)),
+            };
 
-        for url in &registry.urls.items {
-            if url.id == id {
-                return Ok(Some(url.clone()));
+            let record = &registry.trash[index].record;
+            if !is_unique(&registry.urls.items, record) {
+                return Err(Error::duplicate(&record.name, &record.group));
             }
-        }
+            ensure_keyword_unique(&registry.urls.items, record)?;
 
-        Ok(None)
+            let record = registry.trash.remove(index).record;
+            registry.urls.items.push(record.clone());
+
+            Ok((Some(record), true))
+        })
     }
 
-    fn list_groups(&self) -> Result<Vec<String>, Box<dyn Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let registry = read_urls(&mut file)?;
+    fn purge(&self, deleted_before: Option<DateTime<Utc>>) -> Result<Vec<TrashedRecord>, Error> {
+        self.modify(|registry| {
+            let (purged, kept): (Vec<TrashedRecord>, Vec<TrashedRecord>) = registry
+                .trash
+                .drain(..)
+                .partition(|t| is_purged(t, deleted_before));
+            registry.trash = kept;
 
-        let groups: Vec<&str> = registry
-            .urls
-            .items
-            .iter()
-            .map(|e: &URLRecord| e.group.as_str())
-            .collect();
+            let changed = !purged.is_empty();
+            Ok((purged, changed))
+        })
+    }
 
-        let mut distinct: HashMap<&str, bool> = HashMap::new();
+    fn replace_all(&self, records: Vec<URLRecord>) -> Result<Vec<URLRecord>, Error> {
+        self.modify(|registry| {
+            let previous = std::mem::replace(&mut registry.urls.items, records);
+            Ok((previous, true))
+        })
+    }
 
-        for g in groups {
-            distinct.insert(g, false);
-        }
+    fn apply(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify(|registry| {
+            apply_changes(
+                &mut registry.urls.items,
+                &mut registry.trash,
+                changes,
+                deleted_at,
+            )?;
+            Ok(((), !changes.is_empty()))
+        })
+    }
 
-        Ok(distinct.keys().map(|k| k.to_string()).collect())
+    fn apply_unchecked(&self, changes: &[Change], deleted_at: DateTime<Utc>) -> Result<(), Error> {
+        self.modify(|registry| {
+            apply_changes_unchecked(
+                &mut registry.urls.items,
+                &mut registry.trash,
+                changes,
+                deleted_at,
+            )?;
+            Ok(((), !changes.is_empty()))
+        })
     }
+}
 
-    fn update(&self, id: &str, record: URLRecord) -> Result<Option<URLRecord>, Box<dyn Error>> {
-        let mut file = open_urls_file(self.file_path.as_str())?;
-        let mut registry = read_urls(&mut file)?;
+/// Applies changes to the records, failing with `Error::Conflict` if any of them was changed
+/// in the meantime, with `Error::Duplicate` if any changed record ends up with the name
+/// that is not unique in its group, or with `Error::DuplicateKeyword` if it ends up with
+/// the keyword of another record. Records may be left partially modified on error,
+/// so the caller should discard them.
+/// Removed records are moved to the trash and added ones are taken back from it, if present.
+pub(crate) fn apply_changes(
+    urls: &mut Vec<URLRecord>,
+    trash: &mut Vec<TrashedRecord>,
+    changes: &[Change],
+    deleted_at: DateTime<Utc>,
+) -> Result<(), Error> {
+    apply_changes_unchecked(urls, trash, changes, deleted_at)?;
+    ensure_unique_changes(urls, changes)
+}
 
-        let mut found = false;
-        for i in 0..registry.urls.items.len() {
-            if is_same(&registry.urls.items[i], &record) {
-                return Err(not_unique_error(&record));
-            }
+/// Applies changes like `apply_changes`, without checking that names and keywords stay unique
+pub(crate) fn apply_changes_unchecked(
+    urls: &mut Vec<URLRecord>,
+    trash: &mut Vec<TrashedRecord>,
+    changes: &[Change],
+    deleted_at: DateTime<Utc>,
+) -> Result<(), Error> {
+    for c in changes {
+        let current = position_of(urls, c).map(|pos| &urls[pos]);
+        if c.conflicts_with(current) {
+            return Err(Error::Conflict(c.id().to_string()));
+        }
+    }
 
-            if registry.urls.items[i].id.clone() == id {
-                registry.urls.items[i] = record.clone();
-                found = true
+    for c in changes {
+        match c {
+            Change::Added(r) => {
+                trash.retain(|t| t.record.id != r.id);
+                urls.push(r.clone());
+            }
+            Change::Removed(r) => {
+                if let Some(pos) = position_of(urls, c) {
+                    let removed = urls.remove(pos);
+                    trash.retain(|t| t.record.id != r.id);
+                    trash.push(TrashedRecord::new(removed, deleted_at));
+                }
+            }
+            Change::Modified { after, .. } => {
+                if let Some(pos) = position_of(urls, c) {
+                    urls[pos] = after.clone().with_usage_of(&urls[pos]);
+                }
             }
         }
-        if !found {
-            return Ok(None);
-        }
-
-        write_urls(&mut file, registry)?;
-
-        Ok(Some(record))
     }
+
+    Ok(())
 }
 
-impl RepositoryOld for FileStorage {
-    fn list_v_0_0_x(&self, path: &str) -> Result<Vec<v0_0_x::URLRecord>, Box<dyn Error>> {
-        let mut file = open_urls_file(path)?;
-        let content: String = read_file(&mut file)?;
+/// Finds the record the change applies to. Records with the same id, which could be
+/// introduced by editing the file by hand, are told apart by their content.
+fn position_of(urls: &[URLRecord], change: &Change) -> Option<usize> {
+    let id = change.id();
+    change
+        .expected()
+        .and_then(|e| urls.iter().position(|u| u.id == id && u.same_content(e)))
+        .or_else(|| urls.iter().position(|u| u.id == id))
+}
 
-        let urls: v0_0_x::URLRegistry = if content != "" {
-            serde_json::from_str(content.as_str())?
-        } else {
-            v0_0_x::URLRegistry {
-                urls: v0_0_x::URLs { items: vec![] },
+/// Fails if any record added or modified by the changes has the name that is not unique
+/// in its group or the keyword of another record
+fn ensure_unique_changes(urls: &[URLRecord], changes: &[Change]) -> Result<(), Error> {
+    let mut names: HashMap<(&str, &str), usize> = HashMap::new();
+    for u in urls {
+        *names.entry((&u.group, &u.name)).or_insert(0) += 1;
+    }
+    let mut keywords: HashMap<&str, usize> = HashMap::new();
+    for u in urls.iter().filter(|u| !u.keyword.is_empty()) {
+        *keywords.entry(&u.keyword).or_insert(0) += 1;
+    }
+    for c in changes {
+        if let Change::Added(r) | Change::Modified { after: r, .. } = c {
+            if names.get(&(r.group.as_str(), r.name.as_str())) > Some(&1) {
+                return Err(Error::duplicate(&r.name, &r.group));
             }
-        };
+            if keywords.get(r.keyword.as_str()) > Some(&1) {
+                return Err(Error::DuplicateKeyword(r.keyword.clone()));
+            }
+        }
+    }
+
+    Ok(())
+}
 
-        Ok(urls.urls.items)
+/// Checks if the trashed record should be purged, which is the case for all of them
+/// if the time is not specified
+pub(crate) fn is_purged(record: &TrashedRecord, deleted_before: Option<DateTime<Utc>>) -> bool {
+    match deleted_before {
+        Some(time) => record.deleted_at < time,
+        None => true,
     }
 }
 
-fn is_unique(urls: &[URLRecord], record: &URLRecord) -> bool {
+pub(crate) fn is_unique(urls: &[URLRecord], record: &URLRecord) -> bool {
     for u in urls {
         if is_same(u, record) {
             return false;
@@ -167,37 +584,73 @@ fn is_unique(urls: &[URLRecord], record: &URLRecord) -> bool {
     true
 }
 
-fn is_same(a: &URLRecord, b: &URLRecord) -> bool {
+pub(crate) fn is_same(a: &URLRecord, b: &URLRecord) -> bool {
     a.name == b.name && a.group == b.group && a.id != b.id
 }
 
-fn open_urls_file(path: &str) -> Result<File, Box<dyn std::error::Error>> {
-    let path = Path::new(path);
+/// Fails if the keyword of the record is used by any other record
+pub(crate) fn ensure_keyword_unique(urls: &[URLRecord], record: &URLRecord) -> Result<(), Error> {
+    if record.keyword.is_empty() {
+        return Ok(());
+    }
+    if urls
+        .iter()
+        .any(|u| u.keyword == record.keyword && u.id != record.id)
+    {
+        return Err(Error::DuplicateKeyword(record.keyword.clone()));
+    }
 
-    if !path.exists() {
-        if let Some(dir_path) = path.parent() {
-            if !dir_path.exists() {
-                fs::create_dir_all(dir_path)?;
-            }
-        };
+    Ok(())
+}
+
+fn ensure_parent_dir(path: &Path) -> Result<(), Error> {
+    if let Some(dir_path) = path.parent() {
+        if !dir_path.as_os_str().is_empty() && !dir_path.exists() {
+            fs::create_dir_all(dir_path)?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Opens the registry file for reading, returning None if it does not exist yet
+fn open_urls_file(path: &str) -> Result<Option<File>, Error> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(why) if why.kind() == ErrorKind::NotFound => Ok(None),
+        Err(why) => Err(From::from(why)),
     }
+}
+
+fn with_suffix(path: &str, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", path, suffix))
+}
+
+/// Acquires advisory lock guarding the registry file.
+/// The lock is taken on a separate file, as the registry file itself is replaced on every write.
+/// The lock is released when the returned file is dropped.
+pub(crate) fn lock_file(path: &str, exclusive: bool) -> Result<File, Error> {
+    let lock_path = with_suffix(path, LOCK_FILE_SUFFIX);
+    ensure_parent_dir(&lock_path)?;
 
-    match OpenOptions::new()
+    let lock = OpenOptions::new()
         .read(true)
-        .create(true)
-        .append(false)
         .write(true)
-        .open(path)
-    {
-        Err(why) => Err(From::from(format!(
-            "could not read URLs, failed to open file: {}",
-            why
-        ))),
-        Ok(file) => Ok(file),
-    }
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)?;
+
+    let locked = if exclusive {
+        lock.lock()
+    } else {
+        lock.lock_shared()
+    };
+    locked?;
+
+    Ok(lock)
 }
 
-fn read_file(file: &mut File) -> Result<String, Box<dyn std::error::Error>> {
+fn read_file(file: &mut File) -> Result<String, Error> {
     let mut content: String = String::new();
 
     match file.read_to_string(&mut content) {
@@ -206,39 +659,463 @@ fn read_file(file: &mut File) -> Result<String, Box<dyn std::error::Error>> {
     }
 }
 
-fn read_urls(file: &mut File) -> Result<URLRegistry, Box<dyn std::error::Error>> {
-    let content: String = read_file(file)?;
+/// Reads registry file of any known schema version, without modifying it
+pub(crate) fn read_registry_file(path: &str) -> Result<URLRegistry, Error> {
+    let content = fs::read_to_string(path)?;
 
-    let urls: URLRegistry = if content != "" {
-        serde_json::from_str(content.as_str())?
-    } else {
-        URLRegistry {
-            urls: URLs { items: vec![] },
-        }
-    };
+    load_registry(&content, Format::detect(path, &content))
+}
+
+/// Reads the whole file, returning None if it does not exist or is empty
+pub(crate) fn read_content(path: &str) -> Result<Option<String>, Error> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(content))
+}
 
-    Ok(urls)
+fn backup_path(path: &str, version: u32) -> PathBuf {
+    with_suffix(path, &format!(".v{}.bak", version))
 }
 
-fn write_urls(
-    file: &mut File,
-    urls: URLRegistry,
-) -> Result<URLRegistry, Box<dyn std::error::Error>> {
-    let urls_json = serde_json::to_string(&urls)?;
+/// Writes content to the temporary file, flushes it to the disk and renames it to the target path.
+/// If the process is interrupted at any point, the registry file is either in the old or in the new state.
+pub(crate) fn write_content(path: &str, content: &str) -> Result<(), Error> {
+    ensure_parent_dir(Path::new(path))?;
+    let temp_path = with_suffix(path, TEMP_FILE_SUFFIX);
+    let mut temp_file = File::create(&temp_path)?;
+    temp_file.write_all(content.as_bytes())?;
+    temp_file.sync_all()?;
 
-    file.seek(SeekFrom::Start(0))?;
-    file.write_all(urls_json.as_bytes())?;
+    fs::rename(&temp_path, path)?;
+    sync_parent_dir(Path::new(path))?;
 
-    let desired_length: u64 = urls_json.len().try_into()?;
-    file.set_len(desired_length)?;
+    Ok(())
+}
 
-    Ok(urls)
+/// Makes sure that the rename is persisted
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            File::open(dir)?.sync_all()?;
+        }
+    }
+    Ok(())
 }
 
-fn not_unique_error(record: &URLRecord) -> Box<dyn std::error::Error> {
-    From::from(format!(
-        "URL with name '{}' already exists in '{}' group",
-        record.name.clone(),
-        record.group.clone()
-    ))
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::format::Format;
+    use crate::migration::SCHEMA_VERSION;
+    use crate::storage::{backup_path, with_suffix, FileStamp, FileStorage, TEMP_FILE_SUFFIX};
+    use crate::types::URLRecord;
+    use crate::util::{create_temp_dir, create_temp_file};
+    use crate::Repository;
+    use std::fs;
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn concurrent_writers_test() {
+        let file_path =
+            create_temp_file("storage_concurrent_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+
+        let writers = 8;
+        let records_per_writer = 20;
+
+        let handles: Vec<thread::JoinHandle<()>> = (0..writers)
+            .map(|w| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    // Each writer uses its own storage as separate processes would
+                    let storage = FileStorage::new_urls_repository(path);
+                    for i in 0..records_per_writer {
+                        let name = format!("writer-{}-{}", w, i);
+                        storage
+                            .add(URLRecord::new(
+                                "https://test.com",
+                                &name,
+                                "default",
+                                vec!["tag"],
+                            ))
+                            .expect("Failed to add URL");
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().expect("Writer thread panicked");
+        }
+
+        println!("Should not lose any records...");
+        let storage = FileStorage::new_urls_repository(path.clone());
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), writers * records_per_writer);
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn interrupted_write_test() {
+        let file_path =
+            create_temp_file("storage_interrupted_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+        let temp_path = with_suffix(&path, TEMP_FILE_SUFFIX);
+
+        let storage = FileStorage::new_urls_repository(path.clone());
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+
+        println!("Should ignore partially written temporary file...");
+        fs::write(&temp_path, r#"{"urls":{"items":[{"id":"#).expect("Failed to write file");
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 1);
+
+        storage
+            .add(URLRecord::new(
+                "https://two.com",
+                "two",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert!(!temp_path.exists());
+
+        println!("Should keep registry intact when write fails...");
+        // Directory in place of the temporary file makes the write fail before rename
+        fs::create_dir(&temp_path).expect("Failed to create directory");
+        let result = storage.add(URLRecord::new(
+            "https://three.com",
+            "three",
+            "default",
+            Vec::<String>::new(),
+        ));
+        assert!(result.is_err());
+
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].name, "one");
+        assert_eq!(urls[1].name, "two");
+
+        println!("Cleanup...");
+        fs::remove_dir(&temp_path).expect("Failed to remove directory");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn migrate_on_open_test() {
+        let file_path =
+            create_temp_file("storage_migration_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+
+        let old_content = r#"{"urls": {"items": [
+            {"url": "https://github.com", "name": "GitHub", "group": "dev", "tags": {"git": true}},
+            {"url": "https://reddit.com", "name": "Reddit", "group": "fun", "tags": {}}
+        ]}}"#;
+        fs::write(&path, old_content).expect("Failed to write file");
+
+        println!("Should migrate file from v0.0.x...");
+        let storage = FileStorage::new_urls_repository(path.clone());
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].id.len(), 16);
+        assert_ne!(urls[0].id, urls[1].id);
+
+        println!("Should write migrated file with schema version...");
+        let content = fs::read_to_string(&path).expect("Failed to read file");
+        let registry: serde_json::Value =
+            serde_json::from_str(&content).expect("Failed to parse file");
+        assert_eq!(registry["version"], SCHEMA_VERSION);
+        assert_eq!(registry["urls"]["items"][0]["id"], urls[0].id.as_str());
+
+        println!("Should backup file before migration...");
+        let backup = backup_path(&path, 0);
+        let backup_content = fs::read_to_string(&backup).expect("Failed to read backup");
+        assert_eq!(backup_content, old_content);
+
+        println!("Should keep ids stable after migration...");
+        let storage = FileStorage::new_urls_repository(path.clone());
+        let reopened = storage.list().expect("Failed to list URLs");
+        assert_eq!(reopened[0].id, urls[0].id);
+
+        println!("Cleanup...");
+        fs::remove_file(backup).expect("Failed to remove file");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn cache_invalidation_test() {
+        let file_path =
+            create_temp_file("storage_cache_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+
+        let storage = FileStorage::new_urls_repository(path.clone());
+        let other = FileStorage::new_urls_repository(path.clone());
+
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+
+        println!("Should keep parsed registry after write...");
+        let stamp = cached_stamp(&storage).expect("Registry not cached");
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 1);
+        assert_eq!(cached_stamp(&storage), Some(stamp.clone()));
+
+        println!("Should read registry again when file is changed by other storage...");
+        other
+            .add(URLRecord::new(
+                "https://two.com",
+                "two",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        assert_ne!(cached_stamp(&storage), Some(stamp));
+
+        println!("Should read registry again when file is edited by hand...");
+        fs::write(&path, r#"{"version":1,"urls":{"items":[]}}"#).expect("Failed to write file");
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 0);
+
+        println!("Should drop cached registry when modification fails...");
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        let result = storage.add_batch(vec![
+            URLRecord::new(
+                "https://three.com",
+                "three",
+                "default",
+                Vec::<String>::new(),
+            ),
+            URLRecord::new("https://one.com", "one", "default", Vec::<String>::new()),
+        ]);
+        assert!(result.is_err());
+        assert!(cached_stamp(&storage).is_none());
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 1);
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    fn cached_stamp(storage: &FileStorage) -> Option<FileStamp> {
+        storage.lock_cache().as_ref().and_then(|c| c.stamp.clone())
+    }
+
+    fn is_cache_racy(storage: &FileStorage) -> bool {
+        storage
+            .lock_cache()
+            .as_ref()
+            .map(|c| c.racy_hash.is_some())
+            .unwrap_or(false)
+    }
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .expect("Failed to open file")
+            .set_modified(time)
+            .expect("Failed to set modification time");
+    }
+
+    #[test]
+    fn cache_racy_write_test() {
+        let file_path =
+            create_temp_file("storage_cache_racy_test.json").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+
+        let storage = FileStorage::new_urls_repository(path.clone());
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        let stamp = cached_stamp(&storage).expect("Registry not cached");
+        assert!(is_cache_racy(&storage));
+
+        println!("Should read registry again when file is rewritten in place with the same size and time...");
+        let content = fs::read_to_string(&path)
+            .expect("Failed to read file")
+            .replace("one", "owt");
+        let mut file = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .expect("Failed to open file");
+        file.write_all(content.as_bytes())
+            .expect("Failed to write file");
+        file.set_modified(stamp.modified.expect("Modification time not supported"))
+            .expect("Failed to set modification time");
+        drop(file);
+        assert_eq!(
+            FileStamp::of(&fs::metadata(&path).expect("Failed to read metadata")),
+            stamp
+        );
+
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls[0].name, "owt");
+        assert_eq!(urls[0].url, "https://owt.com");
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn cache_replaced_file_test() {
+        let file_path = create_temp_file("storage_cache_replaced_test.json")
+            .expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+        let old_time = SystemTime::now() - Duration::from_secs(60);
+
+        let storage = FileStorage::new_urls_repository(path.clone());
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        set_modified(&file_path, old_time);
+        storage.list().expect("Failed to list URLs");
+        let stamp = cached_stamp(&storage).expect("Registry not cached");
+        assert!(!is_cache_racy(&storage));
+
+        println!("Should read registry again when file is replaced with the same size and time...");
+        let content = fs::read_to_string(&path)
+            .expect("Failed to read file")
+            .replace("one", "owt");
+        let temp_path = with_suffix(&path, TEMP_FILE_SUFFIX);
+        fs::write(&temp_path, content).expect("Failed to write file");
+        set_modified(&temp_path, old_time);
+        fs::rename(&temp_path, &path).expect("Failed to rename file");
+
+        let replaced = FileStamp::of(&fs::metadata(&path).expect("Failed to read metadata"));
+        assert_eq!(replaced.modified, stamp.modified);
+        assert_eq!(replaced.len, stamp.len);
+        assert_ne!(replaced.inode, stamp.inode);
+
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls[0].name, "owt");
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
+
+    #[test]
+    fn read_missing_file_test() {
+        let dir = create_temp_dir("storage_missing_test").expect("Failed to create temp dir");
+        let path = dir.join("nested").join("urls.json");
+        let storage = FileStorage::new_urls_repository(path.to_string_lossy().to_string());
+
+        println!("Should read missing file as empty registry without creating it...");
+        assert!(storage.list().expect("Failed to list URLs").is_empty());
+        assert!(!path.exists());
+
+        println!("Should create file with directories on first write...");
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        assert_eq!(storage.list().expect("Failed to list URLs").len(), 1);
+        assert!(path.exists());
+
+        println!("Cleanup...");
+        fs::remove_dir_all(dir).expect("Failed to remove temp dir");
+    }
+
+    #[test]
+    fn format_test() {
+        let file_path =
+            create_temp_file("storage_format_test.toml").expect("Failed to create temp file");
+        let path = file_path.to_str().expect("Invalid path").to_string();
+
+        println!("Should write file in format detected from extension...");
+        let storage = FileStorage::new_urls_repository(path.clone());
+        storage
+            .add(URLRecord::new(
+                "https://one.com",
+                "one",
+                "default",
+                vec!["tag"],
+            ))
+            .expect("Failed to add URL");
+        let content = fs::read_to_string(&path).expect("Failed to read file");
+        assert!(content.starts_with("# format: toml\n"));
+        assert!(content.contains("[[urls.items]]"));
+
+        println!("Should convert file to the selected format...");
+        let storage = FileStorage::new_urls_repository(path.clone()).with_format(Format::Yaml);
+        storage
+            .add(URLRecord::new(
+                "https://two.com",
+                "two",
+                "default",
+                Vec::<String>::new(),
+            ))
+            .expect("Failed to add URL");
+        let content = fs::read_to_string(&path).expect("Failed to read file");
+        assert!(content.starts_with("# format: yaml\n"));
+
+        println!("Should keep format from header...");
+        let storage = FileStorage::new_urls_repository(path.clone());
+        let urls = storage.list().expect("Failed to list URLs");
+        assert_eq!(urls.len(), 2);
+        storage
+            .delete_by_id(&urls[0].id)
+            .expect("Failed to delete URL");
+        let content = fs::read_to_string(&path).expect("Failed to read file");
+        assert!(content.starts_with("# format: yaml\n"));
+
+        println!("Cleanup...");
+        fs::remove_file(file_path).expect("Failed to remove file");
+    }
 }
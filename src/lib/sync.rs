@@ -0,0 +1,658 @@
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::format::Format;
+use crate::merge::{merge_registries, Conflict};
+use crate::migration::load_registry;
+use crate::storage::{lock_file, write_content};
+use crate::types::URLRegistry;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Git config option enabling commits after every change of the registry
+const AUTOCOMMIT_CONFIG: &str = "bookmark.autocommit";
+
+const INITIAL_COMMIT_MESSAGE: &str = "add bookmarks";
+const PENDING_CHANGES_MESSAGE: &str = "update bookmarks";
+
+/// Outcome of pulling changes from the remote
+#[derive(Debug, PartialEq)]
+pub enum Pull {
+    /// Remote does not have any commits that are not present locally
+    UpToDate,
+    /// Branch does not exist on the remote yet
+    NoRemoteBranch,
+    /// Local branch did not have any new commits and was moved to the remote one
+    FastForward,
+    /// Histories diverged and registries were merged record by record
+    Merge,
+}
+
+pub struct SyncReport {
+    pub pull: Pull,
+    pub pushed: bool,
+    /// Conflicting changes, resolved in favour of the local ones as described by `Merge`
+    pub conflicts: Vec<Conflict>,
+}
+
+/// GitSync keeps the registry file in the git repository.
+/// It commits changes of the registry file and synchronizes them with the remote. When histories
+/// diverge, the registry file changed on both sides is merged by record ids instead of lines,
+/// as the whole registry is written in a single line. Other files, e.g. in the dotfiles
+/// repository, are merged by git.
+pub struct GitSync {
+    registry_path: String,
+    root: PathBuf,
+    file: String,
+    remote: String,
+    clock: Box<dyn Clock>,
+}
+
+impl GitSync {
+    /// Finds the git repository containing the registry file.
+    /// Returns None if there is no repository or git is not installed.
+    pub fn discover(registry_path: &str) -> Result<Option<GitSync>, Error> {
+        let dir = parent_dir(registry_path);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+
+        let output = match Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["rev-parse", "--show-toplevel", "--show-prefix"])
+            .output()
+        {
+            Ok(output) if output.status.success() => output,
+            _ => return Ok(None),
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        let root = lines
+            .next()
+            .ok_or_else(|| Error::Git("failed to find repository root".to_string()))?;
+        let prefix = lines.next().unwrap_or("");
+        let file_name = Path::new(registry_path)
+            .file_name()
+            .ok_or_else(|| Error::Git(format!("invalid registry path '{}'", registry_path)))?
+            .to_string_lossy();
+
+        Ok(Some(GitSync {
+            registry_path: registry_path.to_string(),
+            root: PathBuf::from(root),
+            file: format!("{}{}", prefix, file_name),
+            remote: DEFAULT_REMOTE.to_string(),
+            clock: Box::new(SystemClock::default()),
+        }))
+    }
+
+    /// Initializes the repository in the registry directory, unless the directory already
+    /// belongs to one, and enables commits after every change.
+    pub fn init(registry_path: &str) -> Result<GitSync, Error> {
+        let dir = parent_dir(registry_path);
+        fs::create_dir_all(&dir)?;
+
+        let git = match GitSync::discover(registry_path)? {
+            Some(git) => git,
+            None => {
+                let output = Command::new("git")
+                    .arg("init")
+                    .arg("-q")
+                    .arg(&dir)
+                    .output()
+                    .map_err(run_error)?;
+                if !output.status.success() {
+                    return Err(command_error(&["init"], &output));
+                }
+                GitSync::discover(registry_path)?.ok_or_else(|| {
+                    Error::Git("repository not found after initialization".to_string())
+                })?
+            }
+        };
+
+        // Fails early if the committer is not configured, instead of on every change
+        git.git(&["var", "GIT_COMMITTER_IDENT"])?;
+        git.commit(INITIAL_COMMIT_MESSAGE)?;
+        git.git(&["config", AUTOCOMMIT_CONFIG, "true"])?;
+
+        Ok(git)
+    }
+
+    /// Uses the remote with given name instead of the default one
+    pub fn with_remote(self, remote: &str) -> GitSync {
+        GitSync {
+            remote: remote.to_string(),
+            ..self
+        }
+    }
+
+    /// Uses the clock for the deletion time of records dropped when merging
+    pub fn with_clock<C: Clock + 'static>(self, clock: C) -> GitSync {
+        GitSync {
+            clock: Box::new(clock),
+            ..self
+        }
+    }
+
+    /// Returns true if changes of the registry should be committed right away
+    pub fn autocommit(&self) -> Result<bool, Error> {
+        let output = self.run(&["config", "--bool", "--get", AUTOCOMMIT_CONFIG])?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim() == "true")
+    }
+
+    /// Commits the registry file. Returns false if there was nothing to commit.
+    pub fn commit(&self, message: &str) -> Result<bool, Error> {
+        if !Path::new(&self.registry_path).exists() {
+            return Ok(false);
+        }
+
+        self.git(&["add", "--", &self.file])?;
+        if self.succeeds(&["diff", "--cached", "--quiet", "--", &self.file])? {
+            return Ok(false);
+        }
+        self.git(&["commit", "-q", "-m", message, "--", &self.file])?;
+
+        Ok(true)
+    }
+
+    /// Commits pending changes, pulls changes from the remote merging them if needed,
+    /// and pushes the result.
+    pub fn sync(&self) -> Result<SyncReport, Error> {
+        let _lock = lock_file(&self.registry_path, true)?;
+        self.commit(PENDING_CHANGES_MESSAGE)?;
+
+        let branch = self.git(&["symbolic-ref", "--short", "HEAD"])?;
+        let branch = branch.trim();
+        let remote_head = self.remote_head(branch)?;
+        let head = self.rev_parse("HEAD")?;
+
+        let mut conflicts = vec![];
+        let pull = match &remote_head {
+            None => Pull::NoRemoteBranch,
+            Some(remote_head) => {
+                self.git(&["fetch", "-q", &self.remote, branch])?;
+                match &head {
+                    Some(head) if self.is_ancestor(remote_head, head)? => Pull::UpToDate,
+                    Some(head) if !self.is_ancestor(head, remote_head)? => {
+                        conflicts = self.merge(head, remote_head, branch)?;
+                        Pull::Merge
+                    }
+                    _ => {
+                        self.git(&["merge", "-q", "--ff-only", remote_head])?;
+                        Pull::FastForward
+                    }
+                }
+            }
+        };
+
+        let head = self.rev_parse("HEAD")?;
+        let pushed = head.is_some() && head != remote_head;
+        if pushed {
+            self.git(&["push", "-q", "-u", &self.remote, branch])?;
+        }
+
+        Ok(SyncReport {
+            pull,
+            pushed,
+            conflicts,
+        })
+    }
+
+    /// Sets URL of the remote, adding the remote if it does not exist
+    pub fn set_remote_url(&self, url: &str) -> Result<(), Error> {
+        let remotes = self.git(&["remote"])?;
+        if remotes.lines().any(|r| r == self.remote) {
+            self.git(&["remote", "set-url", &self.remote, url])?;
+        } else {
+            self.git(&["remote", "add", &self.remote, url])?;
+        }
+        Ok(())
+    }
+
+    fn remote_head(&self, branch: &str) -> Result<Option<String>, Error> {
+        let refs = self.git(&["ls-remote", &self.remote, &format!("refs/heads/{}", branch)])?;
+        Ok(refs
+            .split_whitespace()
+            .next()
+            .map(|commit| commit.to_string()))
+    }
+
+    /// Merges diverged histories. The registry file changed on both sides is merged by records,
+    /// while other files in the repository are merged by git. If any of them conflicts,
+    /// the merge is aborted.
+    fn merge(&self, head: &str, remote_head: &str, branch: &str) -> Result<Vec<Conflict>, Error> {
+        let base = self.merge_base(head, remote_head)?;
+
+        let mut args = vec!["merge", "-q", "--no-commit", "--no-ff"];
+        if base.is_none() {
+            args.push("--allow-unrelated-histories");
+        }
+        args.push(remote_head);
+
+        let output = self.run(&args)?;
+        if !output.status.success() && self.rev_parse("MERGE_HEAD")?.is_none() {
+            return Err(command_error(&args, &output));
+        }
+
+        let conflicts = match self.resolve(base.as_deref(), head, remote_head) {
+            Ok(conflicts) => conflicts,
+            Err(why) => {
+                self.run(&["merge", "--abort"])?;
+                return Err(why);
+            }
+        };
+
+        let message = format!("merge bookmarks from {}/{}", self.remote, branch);
+        self.git(&["commit", "-q", "-m", &message])?;
+
+        Ok(conflicts)
+    }
+
+    fn resolve(
+        &self,
+        base: Option<&str>,
+        head: &str,
+        remote_head: &str,
+    ) -> Result<Vec<Conflict>, Error> {
+        let mut conflicts = vec![];
+        if self.file_changed(base, head)? && self.file_changed(base, remote_head)? {
+            let base_registry = match base {
+                Some(base) => self.load(base)?.0,
+                None => URLRegistry::new(vec![]),
+            };
+            let (ours, format) = self.load(head)?;
+            let merge = merge_registries(
+                &base_registry,
+                &ours,
+                &self.load(remote_head)?.0,
+                self.clock.now(),
+            );
+
+            conflicts.extend(merge.conflicts.iter().cloned());
+
+            write_content(
+                &self.root.join(&self.file).to_string_lossy(),
+                &format.serialize(&merge.into_registry())?,
+            )?;
+            self.git(&["add", "--", &self.file])?;
+        }
+
+        let unmerged = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.trim().is_empty() {
+            return Err(Error::Git(format!(
+                "files other than the registry conflict, merge them with git: {}",
+                unmerged
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )));
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Reads registry file from the commit together with its format.
+    /// Missing file is treated as an empty registry.
+    fn load(&self, commit: &str) -> Result<(URLRegistry, Format), Error> {
+        let output = self.run(&["show", &format!("{}:{}", commit, self.file)])?;
+        if !output.status.success() {
+            return Ok((URLRegistry::new(vec![]), Format::detect(&self.file, "")));
+        }
+
+        let content = String::from_utf8_lossy(&output.stdout);
+        let format = Format::detect(&self.file, &content);
+        let registry = load_registry(&content, format)
+            .map_err(|why| Error::Git(format!("failed to merge '{}': {}", self.file, why)))?;
+
+        Ok((registry, format))
+    }
+
+    /// Checks if the registry file was changed in the commit since the base,
+    /// or exists in the commit if histories are unrelated
+    fn file_changed(&self, base: Option<&str>, commit: &str) -> Result<bool, Error> {
+        match base {
+            Some(base) => Ok(!self.succeeds(&["diff", "--quiet", base, commit, "--", &self.file])?),
+            None => Ok(self
+                .rev_parse(&format!("{}:{}", commit, self.file))?
+                .is_some()),
+        }
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>, Error> {
+        let output = self.run(&["rev-parse", "-q", "--verify", rev])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>, Error> {
+        let output = self.run(&["merge-base", a, b])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    fn is_ancestor(&self, ancestor: &str, commit: &str) -> Result<bool, Error> {
+        self.succeeds(&["merge-base", "--is-ancestor", ancestor, commit])
+    }
+
+    fn succeeds(&self, args: &[&str]) -> Result<bool, Error> {
+        Ok(self.run(args)?.status.success())
+    }
+
+    /// Runs git command, returning its output if it succeeded
+    fn git(&self, args: &[&str]) -> Result<String, Error> {
+        let output = self.run(args)?;
+        if !output.status.success() {
+            return Err(command_error(args, &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output, Error> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .output()
+            .map_err(run_error)
+    }
+}
+
+fn parent_dir(path: &str) -> PathBuf {
+    match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn run_error(why: std::io::Error) -> Error {
+    Error::Git(format!("failed to run git: {}", why))
+}
+
+fn command_error(args: &[&str], output: &Output) -> Error {
+    Error::Git(format!(
+        "'git {}' failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::clock::FixedClock;
+    use crate::registry::URLRegistry;
+    use crate::storage::FileStorage;
+    use crate::sync::{GitSync, Pull};
+    use crate::util::create_temp_dir;
+    use crate::{Registry, RegistryReader};
+    use chrono::{TimeZone, Utc};
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .expect("Failed to run git")
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    /// Initializes registry in its own repository, with the committer configured
+    fn init_registry(dir: &Path, remote: &str) -> (URLRegistry<FileStorage>, GitSync) {
+        fs::create_dir_all(dir).expect("Failed to create dir");
+        git(dir, &["init", "-q", "-b", "main"]);
+        git(dir, &["config", "user.name", "Test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+
+        let path = dir.join("urls.json").to_string_lossy().to_string();
+        let registry_git = GitSync::init(&path).expect("Failed to init repository");
+        registry_git
+            .set_remote_url(remote)
+            .expect("Failed to set remote URL");
+        let sync = GitSync::discover(&path)
+            .expect("Failed to discover repository")
+            .expect("Repository not found");
+
+        let registry = URLRegistry::new_file_based(path).with_git(registry_git);
+        (registry, sync)
+    }
+
+    fn commit_messages(dir: &Path) -> Vec<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["log", "--format=%s"])
+            .output()
+            .expect("Failed to run git");
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn git_sync_test() {
+        let dir = create_temp_dir("git_sync_test").expect("Failed to create temp dir");
+        let remote = dir.join("remote.git");
+        git(&dir, &["init", "-q", "--bare", "-b", "main", "remote.git"]);
+        let remote_url = remote.to_string_lossy().to_string();
+
+        let (first, first_sync) = init_registry(&dir.join("first"), &remote_url);
+        let (second, second_sync) = init_registry(&dir.join("second"), &remote_url);
+
+        println!("Should commit every change...");
+        let github = first
+            .create("GitHub", "https://github.com", None, vec![])
+            .expect("Failed to add URL");
+        let docs = first
+            .create("Docs", "https://docs.rs", Some("rust"), vec![])
+            .expect("Failed to add URL");
+        first.tag(&github.id, "git").expect("Failed to tag URL");
+        assert_eq!(
+            commit_messages(&dir.join("first")),
+            vec![
+                "tag GitHub with git",
+                "add Docs to rust",
+                "add GitHub to default"
+            ]
+        );
+
+        println!("Should push to the empty remote...");
+        let report = first_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::NoRemoteBranch);
+        assert!(report.pushed);
+
+        println!("Should fast forward to the remote...");
+        let report = second_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::FastForward);
+        assert!(!report.pushed);
+        assert_eq!(
+            second.list_urls(None, None).expect("Failed to list").len(),
+            2
+        );
+
+        println!("Should merge diverged changes by records...");
+        first
+            .change_name(&docs.id, "Rust docs")
+            .expect("Failed to change name");
+        second
+            .change_group(&github.id, "dev")
+            .expect("Failed to change group");
+        second
+            .create("Crates", "https://crates.io", Some("rust"), vec![])
+            .expect("Failed to add URL");
+
+        let report = first_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::UpToDate);
+        assert!(report.pushed);
+
+        let report = second_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::Merge);
+        assert!(report.pushed);
+        assert!(report.conflicts.is_empty());
+
+        let merged = second.list_urls(None, None).expect("Failed to list");
+        assert_eq!(merged.len(), 3);
+        let merged_github = second
+            .get_url(&github.id)
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(merged_github.group, "dev");
+        assert!(merged_github.tags.contains_key("git"));
+        let merged_docs = second
+            .get_url(&docs.id)
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(merged_docs.name, "Rust docs");
+
+        println!("Should receive merged registry...");
+        let report = first_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::FastForward);
+        assert_eq!(first.list_urls(None, None).expect("Failed to list"), merged);
+
+        println!("Should report conflicting changes...");
+        first
+            .change_url(&docs.id, "https://first.rs")
+            .expect("Failed to change URL");
+        second
+            .change_url(&docs.id, "https://second.rs")
+            .expect("Failed to change URL");
+        first_sync.sync().expect("Failed to sync");
+        let report = second_sync.sync().expect("Failed to sync");
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].id, docs.id);
+        let kept = second
+            .get_url(&docs.id)
+            .expect("Failed to get URL")
+            .expect("URL not found");
+        assert_eq!(kept.url, "https://second.rs");
+
+        println!("Should trash their duplicate at the time of the clock...");
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let second_sync = second_sync.with_clock(FixedClock::new(now));
+        let theirs = first
+            .create("Blog", "https://first.blog", None, vec![])
+            .expect("Failed to add URL");
+        second
+            .create("Blog", "https://second.blog", None, vec![])
+            .expect("Failed to add URL");
+        first_sync.sync().expect("Failed to sync");
+        let report = second_sync.sync().expect("Failed to sync");
+        assert_eq!(report.conflicts.len(), 1);
+        let trash = second.list_trash().expect("Failed to list trash");
+        let trashed = trash
+            .iter()
+            .find(|t| t.record.id == theirs.id)
+            .expect("Duplicate not trashed");
+        assert_eq!(trashed.deleted_at, now);
+
+        println!("Cleanup...");
+        fs::remove_dir_all(dir).expect("Failed to remove temp dir");
+    }
+
+    /// Commits the other file of the dotfiles repository
+    fn commit_file(dir: &Path, name: &str, content: &str) {
+        fs::write(dir.join(name), content).expect("Failed to write file");
+        git(dir, &["add", name]);
+        git(dir, &["commit", "-q", "-m", &format!("edit {}", name)]);
+    }
+
+    #[test]
+    fn git_sync_other_files_test() {
+        let dir = create_temp_dir("git_sync_other_files_test").expect("Failed to create temp dir");
+        git(&dir, &["init", "-q", "--bare", "-b", "main", "remote.git"]);
+        let remote_url = dir.join("remote.git").to_string_lossy().to_string();
+
+        let first_dir = dir.join("first");
+        let second_dir = dir.join("second");
+        let (first, first_sync) = init_registry(&first_dir, &remote_url);
+        let (second, second_sync) = init_registry(&second_dir, &remote_url);
+
+        first
+            .create("GitHub", "https://github.com", None, vec![])
+            .expect("Failed to add URL");
+        commit_file(&first_dir, ".bashrc", "alias a=1\nalias b=2\nalias c=3\n");
+        first_sync.sync().expect("Failed to sync");
+        second_sync.sync().expect("Failed to sync");
+
+        println!("Should merge other files changed on both sides with git...");
+        first
+            .create("Docs", "https://docs.rs", None, vec![])
+            .expect("Failed to add URL");
+        commit_file(
+            &first_dir,
+            ".bashrc",
+            "alias a=first\nalias b=2\nalias c=3\n",
+        );
+        second
+            .create("Crates", "https://crates.io", None, vec![])
+            .expect("Failed to add URL");
+        commit_file(
+            &second_dir,
+            ".bashrc",
+            "alias a=1\nalias b=2\nalias c=second\n",
+        );
+
+        first_sync.sync().expect("Failed to sync");
+        let report = second_sync.sync().expect("Failed to sync");
+        assert_eq!(report.pull, Pull::Merge);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(
+            fs::read_to_string(second_dir.join(".bashrc")).expect("Failed to read file"),
+            "alias a=first\nalias b=2\nalias c=second\n"
+        );
+        assert_eq!(
+            second.list_urls(None, None).expect("Failed to list").len(),
+            3
+        );
+        first_sync.sync().expect("Failed to sync");
+
+        println!("Should abort the merge if other files conflict...");
+        first
+            .create("Rust", "https://rust-lang.org", None, vec![])
+            .expect("Failed to add URL");
+        commit_file(
+            &first_dir,
+            ".bashrc",
+            "alias a=first\nalias b=one\nalias c=second\n",
+        );
+        commit_file(
+            &second_dir,
+            ".bashrc",
+            "alias a=first\nalias b=two\nalias c=second\n",
+        );
+
+        first_sync.sync().expect("Failed to sync");
+        let err = match second_sync.sync() {
+            Ok(_) => panic!("Sync should fail on the conflicting file"),
+            Err(err) => err,
+        };
+        assert!(err.to_string().contains(".bashrc"));
+        assert!(!second_dir.join(".git/MERGE_HEAD").exists());
+        assert_eq!(
+            fs::read_to_string(second_dir.join(".bashrc")).expect("Failed to read file"),
+            "alias a=first\nalias b=two\nalias c=second\n"
+        );
+        assert_eq!(
+            second.list_urls(None, None).expect("Failed to list").len(),
+            3
+        );
+
+        println!("Cleanup...");
+        fs::remove_dir_all(dir).expect("Failed to remove temp dir");
+    }
+}
@@ -0,0 +1,205 @@
+use crate::error::Error;
+use std::collections::BTreeMap;
+
+/// Returns names of the placeholders in the URL template, such as `ticket` in
+/// `https://jira.example.com/browse/{ticket}`, in the order of their first appearance.
+/// Names consist of letters, digits, `_` and `-`, so other braces are not placeholders.
+pub fn placeholders(url: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for (_, name) in find_placeholders(url) {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Returns names of the placeholders in all URLs, e.g. members of the bundle,
+/// in the order of their first appearance.
+pub fn placeholders_of_all(urls: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for name in urls.iter().flat_map(|url| placeholders(url)) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+pub fn is_template(url: &str) -> bool {
+    !find_placeholders(url).is_empty()
+}
+
+/// Replaces placeholders with the percent-encoded values.
+/// Fails if any placeholder does not have the value.
+pub fn fill(url: &str, values: &BTreeMap<String, String>) -> Result<String, Error> {
+    let missing: Vec<String> = placeholders(url)
+        .into_iter()
+        .filter(|p| !values.contains_key(p))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::UnfilledPlaceholders(missing));
+    }
+
+    let mut filled = String::new();
+    let mut rest = 0;
+    for (start, name) in find_placeholders(url) {
+        filled.push_str(&url[rest..start]);
+        filled.push_str(&encode(&values[name]));
+        rest = start + name.len() + 2;
+    }
+    filled.push_str(&url[rest..]);
+
+    Ok(filled)
+}
+
+/// Matches values to the placeholders of the URL templates. Values are either named,
+/// e.g. `ticket=ABC-123`, or positional, which fill the remaining placeholders in order.
+/// Values with `=` whose key is not a placeholder are positional.
+pub fn values_from_args(urls: &[String], args: &[&str]) -> Result<BTreeMap<String, String>, Error> {
+    let names = placeholders_of_all(urls);
+
+    let mut values = BTreeMap::new();
+    let mut positional = vec![];
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if names.iter().any(|n| n == name) => {
+                values.insert(name.to_string(), value.to_string());
+            }
+            _ => positional.push(arg.to_string()),
+        }
+    }
+
+    let mut unnamed = names.iter().filter(|n| !values.contains_key(*n));
+    let mut positional_values = vec![];
+    for value in positional {
+        match unnamed.next() {
+            Some(name) => positional_values.push((name.clone(), value)),
+            None => {
+                return Err(Error::Parse(format!(
+                    "unexpected value '{}', URL has placeholders: {}",
+                    value,
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+    values.extend(positional_values);
+
+    Ok(values)
+}
+
+/// Returns start indexes and names of all placeholders
+fn find_placeholders(url: &str) -> Vec<(usize, &str)> {
+    let mut found = vec![];
+    let mut from = 0;
+    while let Some(open) = url[from..].find('{').map(|i| i + from) {
+        let close = match url[open..].find('}') {
+            Some(i) => open + i,
+            None => break,
+        };
+        let name = &url[open + 1..close];
+        if is_placeholder_name(name) {
+            found.push((open, name));
+            from = close + 1;
+        } else {
+            from = open + 1;
+        }
+    }
+    found
+}
+
+fn is_placeholder_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Percent-encodes all characters except the unreserved ones
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::template::{fill, is_template, placeholders, placeholders_of_all, values_from_args};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn placeholders_test() {
+        assert_eq!(
+            placeholders("https://github.com/{org}/{repo}/pulls?author={org}"),
+            vec!["org", "repo"]
+        );
+        assert!(placeholders("https://example.com/{}/{a b}/{").is_empty());
+        assert!(is_template("https://jira.example.com/browse/{ticket}"));
+        assert!(!is_template("https://example.com/?q={}"));
+        assert_eq!(
+            placeholders_of_all(&[
+                "https://github.com/{org}/{repo}".to_string(),
+                "https://example.com/{user}/{org}".to_string(),
+            ]),
+            vec!["org", "repo", "user"]
+        );
+    }
+
+    #[test]
+    fn fill_test() {
+        let url = "https://github.com/{org}/{repo}/pulls?q={query}";
+        let values: BTreeMap<String, String> = [
+            ("org", "rust-lang"),
+            ("repo", "rust"),
+            ("query", "is:open label:A-cli"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        println!("Should fill placeholders with encoded values...");
+        assert_eq!(
+            fill(url, &values).expect("Failed to fill template"),
+            "https://github.com/rust-lang/rust/pulls?q=is%3Aopen%20label%3AA-cli"
+        );
+        assert_eq!(
+            fill("https://example.com/{}", &BTreeMap::new()).expect("Failed to fill template"),
+            "https://example.com/{}"
+        );
+
+        println!("Should reject unfilled placeholders...");
+        let err = fill(url, &BTreeMap::new()).expect_err("Placeholders should be required");
+        assert!(matches!(err, Error::UnfilledPlaceholders(p) if p == vec!["org", "repo", "query"]));
+    }
+
+    #[test]
+    fn values_from_args_test() {
+        let urls = vec!["https://github.com/{org}/{repo}/pulls".to_string()];
+
+        println!("Should match named and positional values...");
+        let values = values_from_args(&urls, &["repo=bookmark", "Szymongib"])
+            .expect("Failed to parse values");
+        assert_eq!(values["org"], "Szymongib");
+        assert_eq!(values["repo"], "bookmark");
+        let values = values_from_args(&["https://example.com/?q={q}".to_string()], &["a=b"])
+            .expect("Failed to parse values");
+        assert_eq!(values["q"], "a=b");
+
+        println!("Should match values to placeholders of all URLs...");
+        let bundle = vec![urls[0].clone(), "https://example.com/{user}".to_string()];
+        let values = values_from_args(&bundle, &["rust-lang", "rust", "ferris"])
+            .expect("Failed to parse values");
+        assert_eq!(values["user"], "ferris");
+
+        println!("Should reject too many values...");
+        assert!(values_from_args(&urls, &["a", "b", "c"]).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::diff::{diff, Change};
+use crate::error::Error;
+use crate::groups;
+use crate::registry::DEFAULT_GROUP;
+use crate::storage::{ensure_keyword_unique, is_unique};
+use crate::types::{parse_keyword, parse_tag, URLRecord};
+use chrono::{DateTime, Utc};
+
+/// Transaction collects operations on the copy of the registry records.
+/// Every operation is validated against the state left by the previous ones,
+/// and the registry is modified only after all of them succeed.
+pub struct Transaction {
+    before: Vec<URLRecord>,
+    records: Vec<URLRecord>,
+    /// Time recorded as the creation and update time of the records
+    now: DateTime<Utc>,
+}
+
+impl Transaction {
+    pub(crate) fn new(records: Vec<URLRecord>, now: DateTime<Utc>) -> Transaction {
+        Transaction {
+            before: records.clone(),
+            records,
+            now,
+        }
+    }
+
+    /// Returns records as they will be after the transaction is committed
+    pub fn records(&self) -> &[URLRecord] {
+        &self.records
+    }
+
+    pub fn get(&self, id: &str) -> Option<&URLRecord> {
+        self.records.iter().find(|r| r.id == id)
+    }
+
+    pub fn create(
+        &mut self,
+        name: &str,
+        url: &str,
+        group: Option<&str>,
+        tags: Vec<String>,
+    ) -> Result<URLRecord, Error> {
+        let group = groups::normalize(group.unwrap_or(DEFAULT_GROUP));
+        if name.is_empty() {
+            return Err(Error::EmptyField("Name"));
+        }
+        if url.is_empty() {
+            return Err(Error::EmptyField("URL"));
+        }
+        if group.is_empty() {
+            return Err(Error::EmptyField("Group"));
+        }
+
+        self.add(URLRecord::new_at(url, name, &group, tags, self.now))
+    }
+
+    pub fn add(&mut self, record: URLRecord) -> Result<URLRecord, Error> {
+        if self.get(&record.id).is_some() {
+            return Err(Error::Conflict(record.id));
+        }
+        if !is_unique(&self.records, &record) {
+            return Err(Error::duplicate(&record.name, &record.group));
+        }
+        ensure_keyword_unique(&self.records, &record)?;
+
+        self.records.push(record.clone());
+        Ok(record)
+    }
+
+    /// Removes the bookmark, which is moved to the trash on commit.
+    /// Returns false if bookmark does not exist.
+    pub fn delete(&mut self, id: &str) -> Result<bool, Error> {
+        match self.records.iter().position(|r| r.id == id) {
+            Some(index) => {
+                self.records.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn tag(&mut self, id: &str, tag: &str) -> Result<URLRecord, Error> {
+        if parse_tag(tag).0.is_empty() {
+            return Err(Error::EmptyField("Tag"));
+        }
+
+        self.update(id, |record| {
+            record.set_tag(tag);
+        })
+    }
+
+    pub fn untag(&mut self, id: &str, tag: &str) -> Result<URLRecord, Error> {
+        if parse_tag(tag).0.is_empty() {
+            return Err(Error::EmptyField("Tag"));
+        }
+
+        self.update(id, |record| {
+            record.remove_tag(tag);
+        })
+    }
+
+    pub fn change_group(&mut self, id: &str, group: &str) -> Result<URLRecord, Error> {
+        let group = groups::normalize(group);
+        if group.is_empty() {
+            return Err(Error::EmptyField("Group"));
+        }
+
+        self.update(id, |record| {
+            record.group = group;
+        })
+    }
+
+    pub fn pin(&mut self, id: &str) -> Result<URLRecord, Error> {
+        self.update(id, |record| {
+            record.pinned = true;
+        })
+    }
+
+    pub fn unpin(&mut self, id: &str) -> Result<URLRecord, Error> {
+        self.update(id, |record| {
+            record.pinned = false;
+        })
+    }
+
+    pub fn set_keyword(&mut self, id: &str, keyword: &str) -> Result<URLRecord, Error> {
+        let keyword = parse_keyword(keyword)?;
+
+        self.update(id, |record| {
+            record.keyword = keyword;
+        })
+    }
+
+    pub fn remove_keyword(&mut self, id: &str) -> Result<URLRecord, Error> {
+        self.update(id, |record| {
+            record.keyword.clear();
+        })
+    }
+
+    pub fn change_name(&mut self, id: &str, name: &str) -> Result<URLRecord, Error> {
+        if name.is_empty() {
+            return Err(Error::EmptyField("Name"));
+        }
+
+        self.update(id, |record| {
+            record.name = name.to_string();
+        })
+    }
+
+    pub fn change_url(&mut self, id: &str, url: &str) -> Result<URLRecord, Error> {
+        if url.is_empty() {
+            return Err(Error::EmptyField("URL"));
+        }
+
+        self.update(id, |record| {
+            record.url = url.to_string();
+        })
+    }
+
+    pub fn change_notes(&mut self, id: &str, notes: &str) -> Result<URLRecord, Error> {
+        self.update(id, |record| {
+            record.notes = notes.to_string();
+        })
+    }
+
+    /// Returns changes made by the transaction
+    pub(crate) fn changes(&self) -> Vec<Change> {
+        diff(&self.before, &self.records)
+    }
+
+    fn update<F>(&mut self, id: &str, change: F) -> Result<URLRecord, Error>
+    where
+        F: FnOnce(&mut URLRecord),
+    {
+        let index = self
+            .records
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        let mut record = self.records[index].clone();
+        change(&mut record);
+        if record != self.records[index] {
+            record.updated_at = Some(self.now);
+        }
+        if !is_unique(&self.records, &record) {
+            return Err(Error::duplicate(&record.name, &record.group));
+        }
+        ensure_keyword_unique(&self.records, &record)?;
+
+        self.records[index] = record.clone();
+        Ok(record)
+    }
+}
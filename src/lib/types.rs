@@ -1,11 +1,28 @@
+use crate::error::Error;
+use crate::migration::SCHEMA_VERSION;
+use chrono::{DateTime, Utc};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Serialize, Deserialize)]
 pub struct URLRegistry {
+    #[serde(default)]
+    pub version: u32,
     pub urls: URLs,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trash: Vec<TrashedRecord>,
+}
+
+impl URLRegistry {
+    pub fn new(items: Vec<URLRecord>) -> URLRegistry {
+        URLRegistry {
+            version: SCHEMA_VERSION,
+            urls: URLs { items },
+            trash: vec![],
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -29,42 +46,193 @@ pub struct URLs {
     pub items: Vec<URLRecord>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct URLRecord {
     pub id: String,
     pub url: String,
     pub name: String,
     pub group: String,
-    pub tags: BTreeMap<String, bool>,
+    /// Tags by their keys. Plain tags have empty values, while key/value tags
+    /// such as `env=prod` keep the part after `=` as the value.
+    #[serde(deserialize_with = "deserialize_tags")]
+    pub tags: BTreeMap<String, String>,
+    /// Free-form, possibly multi-line notes about the bookmark
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// Members of the bundle, opened together in order. Bundles have empty URLs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bundle: Vec<BundleMember>,
+    /// Short alias, unique in the registry, with which the bookmark can be opened, e.g. `gh`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub keyword: String,
+    /// Pinned bookmarks are listed before all others, whatever the order
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Time when the bookmark was last opened, which is not considered an update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub open_count: u32,
+    /// Times of the most recent opens, up to `OPEN_HISTORY_LEN`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub open_history: Vec<DateTime<Utc>>,
+}
+
+/// Number of the most recent opens kept for computing the frecency of the bookmark
+pub const OPEN_HISTORY_LEN: usize = 10;
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Reads tags also in the format used before tags had values, in which every tag was set
+/// to `true`. Registry files are migrated, but records are kept also in the history.
+fn deserialize_tags<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TagValue {
+        Value(String),
+        Legacy(bool),
+    }
+
+    let tags = BTreeMap::<String, TagValue>::deserialize(deserializer)?;
+    Ok(tags
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            TagValue::Value(value) => Some((key, value)),
+            TagValue::Legacy(true) => Some((key, String::new())),
+            TagValue::Legacy(false) => None,
+        })
+        .collect())
 }
 
 impl URLRecord {
+    /// Creates the record at the current system time
     pub fn new<S: Into<String>>(url: &str, name: &str, group: &str, tags_vec: Vec<S>) -> URLRecord {
-        let mut tags: BTreeMap<String, bool> = BTreeMap::new();
+        URLRecord::new_at(url, name, group, tags_vec, Utc::now())
+    }
+
+    /// Creates the record with the given time as its creation and update time
+    pub fn new_at<S: Into<String>>(
+        url: &str,
+        name: &str,
+        group: &str,
+        tags_vec: Vec<S>,
+        now: DateTime<Utc>,
+    ) -> URLRecord {
+        let mut tags: BTreeMap<String, String> = BTreeMap::new();
         for t in tags_vec {
-            tags.insert(t.into(), true);
+            let (key, value) = parse_tag(&t.into());
+            tags.insert(key, value);
         }
 
-        let random_bytes = rand::thread_rng().gen::<[u8; 8]>();
-
         URLRecord {
-            id: hex::encode(random_bytes),
+            id: new_id(),
             url: url.to_string(),
             name: name.to_string(),
             group: group.to_string(),
             tags,
+            notes: String::new(),
+            bundle: vec![],
+            keyword: String::new(),
+            pinned: false,
+            created_at: Some(now),
+            updated_at: Some(now),
+            last_opened_at: None,
+            open_count: 0,
+            open_history: vec![],
+        }
+    }
+
+    /// Creates the bundle opening all members at once
+    pub fn new_bundle<S: Into<String>>(
+        name: &str,
+        group: &str,
+        tags_vec: Vec<S>,
+        members: Vec<BundleMember>,
+        now: DateTime<Utc>,
+    ) -> URLRecord {
+        URLRecord {
+            bundle: members,
+            ..URLRecord::new_at("", name, group, tags_vec, now)
+        }
+    }
+
+    pub fn is_bundle(&self) -> bool {
+        !self.bundle.is_empty()
+    }
+
+    /// Records that the bookmark was opened at the given time
+    pub fn record_open(&mut self, time: DateTime<Utc>) {
+        self.last_opened_at = Some(time);
+        self.open_count += 1;
+        self.open_history.push(time);
+        let excess = self.open_history.len().saturating_sub(OPEN_HISTORY_LEN);
+        self.open_history.drain(..excess);
+    }
+
+    /// Returns the record with the open statistics taken from the other one
+    pub fn with_usage_of(self, other: &URLRecord) -> URLRecord {
+        URLRecord {
+            last_opened_at: other.last_opened_at,
+            open_count: other.open_count,
+            open_history: other.open_history.clone(),
+            ..self
+        }
+    }
+
+    /// Checks if records are the same, ignoring when and how often they were opened
+    pub fn same_content(&self, other: &URLRecord) -> bool {
+        self.id == other.id
+            && self.url == other.url
+            && self.name == other.name
+            && self.group == other.group
+            && self.tags == other.tags
+            && self.notes == other.notes
+            && self.bundle == other.bundle
+            && self.keyword == other.keyword
+            && self.pinned == other.pinned
+            && self.created_at == other.created_at
+            && self.updated_at == other.updated_at
+    }
+
+    /// Adds the tag in the `key` or `key=value` format, replacing the value of the existing one
+    pub fn set_tag(&mut self, tag: &str) {
+        let (key, value) = parse_tag(tag);
+        self.tags.insert(key, value);
+    }
+
+    /// Removes the tag with the key, or only with the key and value if the tag is in
+    /// the `key=value` format. Returns false if there was no such tag.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let (key, value) = parse_tag(tag);
+        match self.tags.get(&key) {
+            Some(v) if value.is_empty() || *v == value => self.tags.remove(&key).is_some(),
+            _ => false,
         }
     }
 
     pub fn tags_as_string(&self) -> String {
         let tags: Vec<String> = self
             .tags
-            .keys()
-            .map(|k| {
-                if k.contains(|c| c == ' ' || c == ',') {
-                    format!("\"{}\"", k)
+            .iter()
+            .map(|(k, v)| {
+                let tag = format_tag(k, v);
+                if tag.contains([' ', ',']) {
+                    format!("\"{}\"", tag)
                 } else {
-                    k.to_owned()
+                    tag
                 }
             })
             .collect();
@@ -72,6 +240,95 @@ impl URLRecord {
     }
 }
 
+/// Member of the bundle, either the URL or the id of other bookmark, whose URL is opened
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleMember {
+    Url(String),
+    Bookmark(String),
+}
+
+impl BundleMember {
+    /// Checks that the member is the URL starting with the scheme followed by `://`,
+    /// e.g. `https://grafana.com`. The scheme starts with a letter and contains only
+    /// letters, digits, `+`, `-` and `.`.
+    pub fn is_url(member: &str) -> bool {
+        match member.split_once("://") {
+            Some((scheme, rest)) => {
+                scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                    && !rest.is_empty()
+            }
+            None => false,
+        }
+    }
+}
+
+/// Separator of the key and value of the tag, e.g. `env=prod`
+pub const TAG_VALUE_SEPARATOR: char = '=';
+
+/// Splits the tag into the key and value, which is empty for plain tags
+pub fn parse_tag(tag: &str) -> (String, String) {
+    match tag.split_once(TAG_VALUE_SEPARATOR) {
+        Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+        None => (tag.trim().to_string(), String::new()),
+    }
+}
+
+/// Formats the tag as `key=value`, or just `key` for plain tags
+pub fn format_tag(key: &str, value: &str) -> String {
+    if value.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}{}{}", key, TAG_VALUE_SEPARATOR, value)
+    }
+}
+
+/// Names and aliases of the subcommands, which are run instead of opening the bookmark
+/// with such keyword
+pub const RESERVED_KEYWORDS: &[&str] = &[
+    "add", "backup", "bundle", "chg", "chgroup", "chn", "chname", "chu", "churl", "delete",
+    "doctor", "group", "help", "import", "keyword", "list", "ls", "merge", "notes", "open", "pin",
+    "profile", "redo", "sync", "tag", "trash", "undo", "unpin", "untag",
+];
+
+/// Trims the keyword and checks that it is not empty, has no whitespaces inside,
+/// does not start with `-`, which would be parsed as a flag, and is not the name of a subcommand
+pub fn parse_keyword(keyword: &str) -> Result<String, Error> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() {
+        return Err(Error::EmptyField("Keyword"));
+    }
+    if keyword.contains(char::is_whitespace) || keyword.starts_with('-') {
+        return Err(Error::InvalidKeyword(keyword.to_string()));
+    }
+    if RESERVED_KEYWORDS.contains(&keyword) {
+        return Err(Error::ReservedKeyword(keyword.to_string()));
+    }
+
+    Ok(keyword.to_string())
+}
+
+/// Deleted record kept in the trash until it is restored or purged
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TrashedRecord {
+    pub record: URLRecord,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl TrashedRecord {
+    pub fn new(record: URLRecord, deleted_at: DateTime<Utc>) -> TrashedRecord {
+        TrashedRecord { record, deleted_at }
+    }
+}
+
+pub(crate) fn new_id() -> String {
+    let random_bytes = rand::thread_rng().gen::<[u8; 8]>();
+    hex::encode(random_bytes)
+}
+
 impl fmt::Display for URLRecord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -81,3 +338,43 @@ impl fmt::Display for URLRecord {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::types::{parse_tag, URLRecord};
+
+    #[test]
+    fn tags_test() {
+        let mut record =
+            URLRecord::new("https://one.com", "one", "default", vec!["git", "env=prod"]);
+        assert_eq!(
+            parse_tag(" owner = platform "),
+            ("owner".to_string(), "platform".to_string())
+        );
+
+        println!("Should replace value of the tag...");
+        record.set_tag("env=staging");
+        record.set_tag("team=dev ops");
+        assert_eq!(
+            record.tags_as_string(),
+            "env=staging, git, \"team=dev ops\""
+        );
+
+        println!("Should remove tag only with matching value...");
+        assert!(!record.remove_tag("env=prod"));
+        assert!(record.remove_tag("env=staging"));
+        assert!(record.remove_tag("team"));
+        assert_eq!(record.tags_as_string(), "git");
+    }
+
+    #[test]
+    fn deserialize_legacy_tags_test() {
+        let record: URLRecord = serde_json::from_str(
+            r#"{"id": "abcd", "url": "https://one.com", "name": "one", "group": "default",
+                "tags": {"git": true, "old": false, "env": "prod"}}"#,
+        )
+        .expect("Failed to deserialize record");
+
+        assert_eq!(record.tags_as_string(), "env=prod, git");
+    }
+}
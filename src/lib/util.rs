@@ -1,10 +1,16 @@
+use crate::error::Error;
 use std::env;
+#[cfg(test)]
+use std::fs;
 use std::fs::File;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
-pub(crate) fn create_temp_file(suffix: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let time = SystemTime::now().elapsed()?.as_nanos();
+pub(crate) fn create_temp_file(suffix: &str) -> Result<PathBuf, Error> {
+    let time = SystemTime::now()
+        .elapsed()
+        .map_err(|why| Error::Storage(why.to_string()))?
+        .as_nanos();
 
     let mut temp_path = env::temp_dir();
     temp_path.push(format!("{}_{}", time, suffix));
@@ -13,3 +19,12 @@ pub(crate) fn create_temp_file(suffix: &str) -> Result<PathBuf, Box<dyn std::err
 
     Ok(temp_path)
 }
+
+#[cfg(test)]
+pub(crate) fn create_temp_dir(suffix: &str) -> Result<PathBuf, Error> {
+    let path = create_temp_file(suffix)?;
+    fs::remove_file(&path)?;
+    fs::create_dir(&path)?;
+
+    Ok(path)
+}